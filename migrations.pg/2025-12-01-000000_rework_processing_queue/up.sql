@@ -0,0 +1,23 @@
+DROP TABLE IF EXISTS processing_queue;
+
+CREATE TYPE queue_status AS ENUM ('pending', 'processing', 'complete', 'dead');
+
+CREATE TABLE processing_queue (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  ap_id TEXT,
+  kind TEXT NOT NULL,
+  actor TEXT NOT NULL,
+  raw JSONB NOT NULL,
+  status queue_status NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  locked_at TIMESTAMPTZ,
+  last_error TEXT
+);
+
+CREATE UNIQUE INDEX idx_processing_queue_ap_id ON processing_queue (ap_id);
+CREATE INDEX idx_processing_queue_status_next_attempt ON processing_queue (status, next_attempt_at);
+
+SELECT diesel_manage_updated_at('processing_queue');
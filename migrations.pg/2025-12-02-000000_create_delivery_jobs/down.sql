@@ -0,0 +1,2 @@
+DROP TABLE delivery_jobs;
+DROP TYPE delivery_status;
@@ -0,0 +1,23 @@
+CREATE TYPE delivery_status AS ENUM ('pending', 'delivered', 'failed');
+
+CREATE TABLE delivery_jobs (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  activity_ap_id TEXT NOT NULL,
+  inbox TEXT NOT NULL,
+  sender_id INT NOT NULL,
+  body TEXT NOT NULL,
+  status delivery_status NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  last_attempt_at TIMESTAMPTZ,
+  last_status_code INT,
+  last_error TEXT,
+  CONSTRAINT fk_delivery_jobs_sender FOREIGN KEY(sender_id) REFERENCES actors(id) ON DELETE CASCADE,
+  UNIQUE(activity_ap_id, inbox)
+);
+
+CREATE INDEX idx_delivery_jobs_status_next_attempt ON delivery_jobs (status, next_attempt_at);
+
+SELECT diesel_manage_updated_at('delivery_jobs');
@@ -0,0 +1,7 @@
+ALTER TABLE instances DROP COLUMN last_probed_at;
+ALTER TABLE instances DROP COLUMN unreachable_since;
+ALTER TABLE instances DROP COLUMN unreachable;
+ALTER TABLE instances DROP COLUMN last_success_at;
+ALTER TABLE instances DROP COLUMN consecutive_failures;
+ALTER TABLE instances DROP COLUMN delivery_failure_count;
+ALTER TABLE instances DROP COLUMN delivery_success_count;
@@ -0,0 +1,7 @@
+ALTER TABLE instances ADD COLUMN delivery_success_count INT NOT NULL DEFAULT 0;
+ALTER TABLE instances ADD COLUMN delivery_failure_count INT NOT NULL DEFAULT 0;
+ALTER TABLE instances ADD COLUMN consecutive_failures INT NOT NULL DEFAULT 0;
+ALTER TABLE instances ADD COLUMN last_success_at TIMESTAMPTZ;
+ALTER TABLE instances ADD COLUMN unreachable BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE instances ADD COLUMN unreachable_since TIMESTAMPTZ;
+ALTER TABLE instances ADD COLUMN last_probed_at TIMESTAMPTZ;
@@ -0,0 +1,17 @@
+CREATE TABLE blocks (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  blocker_ap_id TEXT NOT NULL,
+  blocked_ap_id TEXT NOT NULL,
+  block_activity_ap_id TEXT,
+  blocker_actor_id INT,
+  blocked_actor_id INT,
+  CONSTRAINT fk_blocks_blocker FOREIGN KEY(blocker_actor_id) REFERENCES actors(id) ON DELETE CASCADE,
+  CONSTRAINT fk_blocks_blocked FOREIGN KEY(blocked_actor_id) REFERENCES actors(id) ON DELETE CASCADE,
+  UNIQUE(blocker_ap_id, blocked_ap_id)
+);
+
+CREATE INDEX idx_blocks_blocked_ap_id ON blocks (blocked_ap_id);
+
+SELECT diesel_manage_updated_at('blocks');
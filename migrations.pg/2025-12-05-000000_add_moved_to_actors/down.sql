@@ -0,0 +1 @@
+ALTER TABLE actors DROP COLUMN as_moved_to;
@@ -0,0 +1 @@
+ALTER TABLE actors ADD COLUMN as_moved_to TEXT;
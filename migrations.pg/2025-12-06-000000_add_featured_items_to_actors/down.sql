@@ -0,0 +1 @@
+ALTER TABLE actors DROP COLUMN ek_featured_items;
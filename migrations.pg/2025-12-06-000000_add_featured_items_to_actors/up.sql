@@ -0,0 +1,4 @@
+ALTER TABLE actors ADD COLUMN ek_featured_items JSONB NOT NULL DEFAULT '[]'::jsonb;
+
+UPDATE actors SET as_featured = to_jsonb(as_id || '/featured')
+WHERE ek_username IS NOT NULL AND as_featured IS NULL;
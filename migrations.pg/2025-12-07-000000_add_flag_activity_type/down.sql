@@ -0,0 +1 @@
+-- Cannot remove enum values in PostgreSQL
@@ -0,0 +1 @@
+ALTER TYPE activity_type ADD VALUE IF NOT EXISTS 'flag';
@@ -0,0 +1,26 @@
+CREATE TYPE report_status AS ENUM ('open', 'resolved');
+
+CREATE TABLE reports (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  uuid TEXT NOT NULL UNIQUE,
+  reporter_ap_id TEXT NOT NULL,
+  reporter_actor_id INT,
+  target_ap_id TEXT NOT NULL,
+  target_actor_id INT,
+  object_ap_ids JSONB NOT NULL DEFAULT '[]',
+  comment TEXT,
+  flag_ap_id TEXT UNIQUE,
+  forwarded BOOLEAN NOT NULL DEFAULT FALSE,
+  status report_status NOT NULL DEFAULT 'open',
+  assigned_to TEXT,
+  resolved_at TIMESTAMPTZ,
+  notes JSONB NOT NULL DEFAULT '[]',
+  CONSTRAINT fk_reports_reporter FOREIGN KEY(reporter_actor_id) REFERENCES actors(id) ON DELETE SET NULL,
+  CONSTRAINT fk_reports_target FOREIGN KEY(target_actor_id) REFERENCES actors(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_reports_status_created_at ON reports (status, created_at);
+
+SELECT diesel_manage_updated_at('reports');
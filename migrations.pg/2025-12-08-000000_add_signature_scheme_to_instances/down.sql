@@ -0,0 +1,3 @@
+ALTER TABLE instances DROP COLUMN signature_scheme;
+
+DROP TYPE signature_scheme;
@@ -0,0 +1,3 @@
+CREATE TYPE signature_scheme AS ENUM ('rfc9421', 'cavage');
+
+ALTER TABLE instances ADD COLUMN signature_scheme signature_scheme;
@@ -0,0 +1,2 @@
+ALTER TABLE actors DROP COLUMN ek_ed25519_private_key;
+ALTER TABLE actors DROP COLUMN as_assertion_method;
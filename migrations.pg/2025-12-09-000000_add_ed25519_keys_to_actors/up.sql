@@ -0,0 +1,2 @@
+ALTER TABLE actors ADD COLUMN as_assertion_method JSONB NOT NULL DEFAULT '[]'::jsonb;
+ALTER TABLE actors ADD COLUMN ek_ed25519_private_key TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE actors DROP COLUMN ek_key_rotated_at;
+ALTER TABLE actors DROP COLUMN ek_previous_public_key;
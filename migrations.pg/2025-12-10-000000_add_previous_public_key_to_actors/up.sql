@@ -0,0 +1,2 @@
+ALTER TABLE actors ADD COLUMN ek_previous_public_key JSONB;
+ALTER TABLE actors ADD COLUMN ek_key_rotated_at TIMESTAMPTZ;
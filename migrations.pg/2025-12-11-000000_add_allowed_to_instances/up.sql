@@ -0,0 +1 @@
+ALTER TABLE instances ADD COLUMN allowed BOOLEAN NOT NULL DEFAULT 'false';
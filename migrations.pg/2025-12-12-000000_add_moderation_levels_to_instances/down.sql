@@ -0,0 +1,5 @@
+ALTER TABLE instances DROP COLUMN private_comment;
+ALTER TABLE instances DROP COLUMN public_comment;
+ALTER TABLE instances DROP COLUMN reject_reports;
+ALTER TABLE instances DROP COLUMN reject_media;
+ALTER TABLE instances DROP COLUMN silenced;
@@ -0,0 +1,5 @@
+ALTER TABLE instances ADD COLUMN silenced BOOLEAN NOT NULL DEFAULT 'false';
+ALTER TABLE instances ADD COLUMN reject_media BOOLEAN NOT NULL DEFAULT 'false';
+ALTER TABLE instances ADD COLUMN reject_reports BOOLEAN NOT NULL DEFAULT 'false';
+ALTER TABLE instances ADD COLUMN public_comment TEXT;
+ALTER TABLE instances ADD COLUMN private_comment TEXT;
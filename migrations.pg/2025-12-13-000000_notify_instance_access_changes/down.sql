@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS instances_access_delete ON instances;
+DROP TRIGGER IF EXISTS instances_access_update ON instances;
+DROP TRIGGER IF EXISTS instances_access_insert ON instances;
+DROP FUNCTION IF EXISTS notify_instance_access_change();
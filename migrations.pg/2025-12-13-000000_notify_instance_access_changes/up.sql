@@ -0,0 +1,35 @@
+-- Notify running servers when a domain is blocked, unblocked, allowed or disallowed so
+-- that they can reload their BlockList without waiting for the periodic refresh.
+
+CREATE OR REPLACE FUNCTION notify_instance_access_change()
+  RETURNS TRIGGER
+  AS $$
+  BEGIN
+      IF TG_OP = 'DELETE' THEN
+          PERFORM pg_notify('instance_access', OLD.domain_name);
+      ELSE
+          PERFORM pg_notify('instance_access', NEW.domain_name);
+      END IF;
+
+      RETURN NULL;
+  END;
+  $$
+  LANGUAGE plpgsql;
+
+CREATE TRIGGER instances_access_insert
+  AFTER INSERT ON instances
+  FOR EACH ROW
+  WHEN (NEW.blocked OR NEW.allowed)
+  EXECUTE FUNCTION notify_instance_access_change();
+
+CREATE TRIGGER instances_access_update
+  AFTER UPDATE ON instances
+  FOR EACH ROW
+  WHEN (OLD.blocked IS DISTINCT FROM NEW.blocked OR OLD.allowed IS DISTINCT FROM NEW.allowed)
+  EXECUTE FUNCTION notify_instance_access_change();
+
+CREATE TRIGGER instances_access_delete
+  AFTER DELETE ON instances
+  FOR EACH ROW
+  WHEN (OLD.blocked OR OLD.allowed)
+  EXECUTE FUNCTION notify_instance_access_change();
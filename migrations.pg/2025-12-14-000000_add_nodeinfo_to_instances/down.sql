@@ -0,0 +1,6 @@
+ALTER TABLE instances DROP COLUMN crawled_at;
+ALTER TABLE instances DROP COLUMN open_registrations;
+ALTER TABLE instances DROP COLUMN active_user_count;
+ALTER TABLE instances DROP COLUMN user_count;
+ALTER TABLE instances DROP COLUMN software_version;
+ALTER TABLE instances DROP COLUMN software_name;
@@ -0,0 +1,6 @@
+ALTER TABLE instances ADD COLUMN software_name TEXT;
+ALTER TABLE instances ADD COLUMN software_version TEXT;
+ALTER TABLE instances ADD COLUMN user_count BIGINT;
+ALTER TABLE instances ADD COLUMN active_user_count BIGINT;
+ALTER TABLE instances ADD COLUMN open_registrations BOOLEAN;
+ALTER TABLE instances ADD COLUMN crawled_at TIMESTAMPTZ;
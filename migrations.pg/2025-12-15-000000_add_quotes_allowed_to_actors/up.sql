@@ -0,0 +1 @@
+ALTER TABLE actors ADD COLUMN ek_quotes_allowed BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,20 @@
+CREATE TABLE reactions (
+  id SERIAL PRIMARY KEY,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  uuid TEXT NOT NULL UNIQUE,
+  ap_id TEXT NOT NULL UNIQUE,
+  actor_ap_id TEXT NOT NULL,
+  actor_id INT,
+  object_ap_id TEXT NOT NULL,
+  object_id INT,
+  content TEXT NOT NULL,
+  emoji JSONB,
+  CONSTRAINT fk_reactions_actor FOREIGN KEY(actor_id) REFERENCES actors(id) ON DELETE CASCADE,
+  CONSTRAINT fk_reactions_object FOREIGN KEY(object_id) REFERENCES objects(id) ON DELETE CASCADE,
+  UNIQUE(actor_ap_id, object_ap_id, content)
+);
+
+CREATE INDEX idx_reactions_object_ap_id ON reactions (object_ap_id);
+
+SELECT diesel_manage_updated_at('reactions');
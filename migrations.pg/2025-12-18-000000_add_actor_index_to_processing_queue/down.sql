@@ -0,0 +1 @@
+DROP INDEX idx_processing_queue_actor_status;
@@ -0,0 +1 @@
+CREATE INDEX idx_processing_queue_actor_status ON processing_queue (actor, status, id);
@@ -0,0 +1 @@
+ALTER TABLE delivery_jobs DROP COLUMN deferred_at;
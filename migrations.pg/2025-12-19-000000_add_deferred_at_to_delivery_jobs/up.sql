@@ -0,0 +1 @@
+ALTER TABLE delivery_jobs ADD COLUMN deferred_at TIMESTAMPTZ;
@@ -0,0 +1,4 @@
+DROP INDEX idx_processing_queue_object_status;
+CREATE INDEX idx_processing_queue_actor_status ON processing_queue (actor, status, id);
+
+ALTER TABLE processing_queue DROP COLUMN object_ap_id;
@@ -0,0 +1,11 @@
+ALTER TABLE processing_queue ADD COLUMN object_ap_id TEXT;
+
+UPDATE processing_queue
+SET object_ap_id = CASE jsonb_typeof(raw->'object')
+  WHEN 'string' THEN raw->>'object'
+  WHEN 'object' THEN raw->'object'->>'id'
+END
+WHERE status IN ('pending', 'processing');
+
+DROP INDEX idx_processing_queue_actor_status;
+CREATE INDEX idx_processing_queue_object_status ON processing_queue (object_ap_id, status, id);
@@ -16,10 +16,12 @@ use uuid::Uuid;
 use crate::db::runner::DbRunner;
 use crate::helper::get_ap_id_from_username;
 use crate::models::actors::{
-    create_or_update_actor, get_actor_by_username, Actor, ActorType, NewActor,
+    create_or_update_actor, get_actor_by_username, update_public_key_by_username, Actor, ActorType,
+    NewActor,
 };
 use crate::models::cache::Cache;
 use crate::models::profiles::Profile;
+use crate::proofs::{assertion_method, generate_ed25519_key_pair};
 use jdt_activity_pub::MaybeMultiple;
 use jdt_activity_pub::{ApActor, ApCapabilities, ApContext, ApEndpoint, ApImage, ApPublicKey};
 
@@ -135,8 +137,15 @@ async fn generate_avatar(username: String) -> Result<String> {
     Ok(filename)
 }
 
+/// A new ID for a user's rotated RSA signing key. Each rotated key gets its own path so
+/// that a key replaced by a later rotation can still be dereferenced during the grace period.
+fn main_key_id(owner: &str) -> String {
+    format!("{owner}/main-key/{}", Uuid::new_v4())
+}
+
 pub async fn create_user<C: DbRunner>(conn: &C, user: NewUser) -> Result<Actor> {
     let key_pair = get_key_pair();
+    let ed25519_key_pair = generate_ed25519_key_pair();
     let owner = get_ap_id_from_username(user.username.clone());
     let server_name = crate::SERVER_NAME.as_str();
     let server_url = format!("https://{server_name}");
@@ -166,6 +175,9 @@ pub async fn create_user<C: DbRunner>(conn: &C, user: NewUser) -> Result<Actor>
                 .to_public_key_pem(LineEnding::default())
                 .unwrap(),
         }),
+        as_assertion_method: json!([assertion_method(&owner, ed25519_key_pair.public_key)]),
+        ek_ed25519_private_key: Some(ed25519_key_pair.private_key),
+        as_moved_to: None,
         ek_password: Some(hash.unprotected_as_encoded().to_string()),
         ek_client_public_key: user.client_public_key,
         ek_client_private_key: user.client_private_key,
@@ -195,7 +207,7 @@ pub async fn create_user<C: DbRunner>(conn: &C, user: NewUser) -> Result<Actor>
         }),
         as_also_known_as: json!([]),
         as_tag: json!([]),
-        as_id: owner,
+        as_id: owner.clone(),
         as_icon: {
             let mut image = ApImage::from(format!("{server_url}/media/avatars/{avatar}"));
             image.media_type = Some("image/png".to_string());
@@ -210,7 +222,7 @@ pub async fn create_user<C: DbRunner>(conn: &C, user: NewUser) -> Result<Actor>
         as_type: user.kind.unwrap_or(ActorType::Person),
         as_attachment: json!([]),
         as_context: Some(json!(ApContext::default())),
-        as_featured: None,
+        as_featured: Some(json!(format!("{owner}/featured"))),
         as_featured_tags: None,
     };
 
@@ -219,3 +231,28 @@ pub async fn create_user<C: DbRunner>(conn: &C, user: NewUser) -> Result<Actor>
 
     Ok(actor)
 }
+
+/// Generates a new signing key for a local user. Rotated keys get IDs that can be
+/// dereferenced on their own, so the replaced key stays resolvable for the grace period.
+/// The caller publishes the new key with an actor Update.
+pub async fn rotate_user_key<C: DbRunner>(conn: &C, username: String) -> Result<Actor> {
+    let key_pair = get_key_pair();
+    let owner = get_ap_id_from_username(username.clone());
+
+    let public_key = json!(ApPublicKey {
+        id: main_key_id(&owner),
+        owner,
+        public_key_pem: key_pair
+            .public_key
+            .to_public_key_pem(LineEnding::default())
+            .map_err(anyhow::Error::msg)?,
+    });
+
+    let private_key = key_pair
+        .private_key
+        .to_pkcs8_pem(LineEnding::default())
+        .map_err(anyhow::Error::msg)?
+        .to_string();
+
+    update_public_key_by_username(conn, username, private_key, public_key).await
+}
@@ -23,7 +23,8 @@ pub async fn main() {
     println!("First: {:?}", collection.first);
     println!("Next: {:?}", collection.next);
 
-    let mut stream = collection.stream_all(collection_fetcher());
+    let conn = enigmatick::db::POOL.get().await.unwrap();
+    let mut stream = collection.stream_all(collection_fetcher(&conn).await);
     while let Some(item_result) = stream.next().await {
         match item_result {
             Ok(item) => println!("Got item: {item:?}"),
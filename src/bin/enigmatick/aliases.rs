@@ -0,0 +1,115 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use enigmatick::models::actors::{get_also_known_as_by_username, update_also_known_as_by_username};
+use enigmatick::retriever::get_actor;
+use enigmatick::runner::user::send_actor_update_task;
+
+#[derive(Parser)]
+pub struct AliasesArgs {
+    #[command(subcommand)]
+    pub command: AliasesCommands,
+}
+
+#[derive(Subcommand)]
+pub enum AliasesCommands {
+    /// List alsoKnownAs aliases for user
+    List { username: String },
+    /// Add alsoKnownAs alias (an actor ID) for user
+    Add { username: String, alias: String },
+    /// Remove alsoKnownAs alias for user
+    Remove { username: String, alias: String },
+}
+
+pub async fn handle_aliases_command(args: AliasesArgs) -> Result<()> {
+    let conn = enigmatick::db::POOL.get().await?;
+
+    let aliases = match args.command {
+        AliasesCommands::List { username } => {
+            println!("Listing aliases for user: {username}...");
+
+            let aliases = get_also_known_as_by_username(&conn, username.clone())
+                .await
+                .map_err(|e| {
+                    eprintln!("Error retrieving aliases for user '{username}': {e}");
+                    e
+                })?;
+
+            if aliases.is_empty() {
+                println!("No aliases found for user '{username}'.");
+            } else {
+                println!("Aliases for user '{username}':");
+                for (index, alias) in aliases.iter().enumerate() {
+                    println!("  {}. {alias}", index + 1);
+                }
+                println!("Total: {} alias(es)", aliases.len());
+            };
+
+            return Ok(());
+        }
+        AliasesCommands::Add { username, alias } => {
+            println!("Adding alias '{alias}' for user: {username}...");
+
+            let alias = match get_actor(&conn, alias.clone(), None, true).await {
+                Ok(actor) => actor.id.map(|id| id.to_string()).unwrap_or(alias),
+                Err(e) => {
+                    eprintln!("Error resolving alias '{alias}': {e}");
+                    return Ok(());
+                }
+            };
+
+            let mut current_aliases = get_also_known_as_by_username(&conn, username.clone())
+                .await
+                .map_err(|e| {
+                    eprintln!("Error retrieving current aliases for user '{username}': {e}");
+                    e
+                })?;
+
+            if current_aliases.contains(&alias) {
+                println!("Alias '{alias}' is already listed for user '{username}'.");
+                return Ok(());
+            }
+
+            current_aliases.push(alias.clone());
+            (username, alias, current_aliases)
+        }
+        AliasesCommands::Remove { username, alias } => {
+            println!("Removing alias '{alias}' for user: {username}...");
+
+            let mut current_aliases = get_also_known_as_by_username(&conn, username.clone())
+                .await
+                .map_err(|e| {
+                    eprintln!("Error retrieving current aliases for user '{username}': {e}");
+                    e
+                })?;
+
+            if let Some(pos) = current_aliases.iter().position(|x| x == &alias) {
+                current_aliases.remove(pos);
+            } else {
+                println!("Alias '{alias}' is not listed for user '{username}'.");
+                return Ok(());
+            }
+
+            (username, alias, current_aliases)
+        }
+    };
+
+    let (username, alias, aliases) = aliases;
+
+    match update_also_known_as_by_username(&conn, username.clone(), aliases).await {
+        Ok(actor) => {
+            println!("Successfully updated alias '{alias}' for user '{username}'.");
+
+            // Followers and the alias's server need the new alsoKnownAs
+            if let Some(uuid) = actor.ek_uuid {
+                if let Err(e) =
+                    send_actor_update_task(enigmatick::db::POOL.clone(), None, vec![uuid]).await
+                {
+                    eprintln!("Error sending actor update for user '{username}': {e:?}");
+                }
+            }
+        }
+        Err(e) => eprintln!("Error updating aliases for user '{username}': {e}"),
+    }
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use comfy_table::{presets, Attribute, Cell, Color, ColumnConstraint, Table, Width};
-use enigmatick::models::instances::Instance;
+use enigmatick::models::instances::{Instance, ModerationLevel};
+use enigmatick::runner::domain_blocks::DomainBlockChange;
 
 pub fn format_relative_time(datetime: DateTime<Utc>) -> String {
     let now = Utc::now();
@@ -58,6 +59,32 @@ pub fn format_relative_time(datetime: DateTime<Utc>) -> String {
     format!("{} weeks ago", duration_since.num_weeks())
 }
 
+pub fn format_software(instance: &Instance) -> String {
+    match (&instance.software_name, &instance.software_version) {
+        (Some(name), Some(version)) => format!("{name} {version}"),
+        (Some(name), None) => name.clone(),
+        _ => "Unknown".to_string(),
+    }
+}
+
+pub fn format_user_count(user_count: Option<i64>) -> String {
+    user_count
+        .map(|user_count| user_count.to_string())
+        .unwrap_or("-".to_string())
+}
+
+fn format_moderation_levels(levels: &[ModerationLevel]) -> String {
+    if levels.is_empty() {
+        "None".to_string()
+    } else {
+        levels
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 pub fn print_instance_table(instances: Vec<Instance>) {
     if instances.is_empty() {
         println!("No instances found.");
@@ -69,12 +96,16 @@ pub fn print_instance_table(instances: Vec<Instance>) {
     table.set_header(vec![
         Cell::new("Domain Name").add_attribute(Attribute::Bold),
         Cell::new("Blocked").add_attribute(Attribute::Bold),
+        Cell::new("Software").add_attribute(Attribute::Bold),
+        Cell::new("Users").add_attribute(Attribute::Bold),
         Cell::new("Last Message At").add_attribute(Attribute::Bold),
     ]);
     table.set_constraints(vec![
         ColumnConstraint::LowerBoundary(Width::Fixed(40)), // For "Domain Name" column (index 0)
         ColumnConstraint::ContentWidth,                    // For "Blocked" column (index 1)
-        ColumnConstraint::ContentWidth,                    // For "Last Message At" column (index 2)
+        ColumnConstraint::ContentWidth,                    // For "Software" column (index 2)
+        ColumnConstraint::ContentWidth,                    // For "Users" column (index 3)
+        ColumnConstraint::ContentWidth,                    // For "Last Message At" column (index 4)
     ]);
 
     for instance in instances {
@@ -86,8 +117,10 @@ pub fn print_instance_table(instances: Vec<Instance>) {
         };
 
         table.add_row(vec![
-            Cell::new(instance.domain_name),
+            Cell::new(&instance.domain_name),
             blocked_cell,
+            Cell::new(format_software(&instance)),
+            Cell::new(format_user_count(instance.user_count)),
             Cell::new(format_relative_time(instance.last_message_at)),
         ]);
     }
@@ -122,10 +155,91 @@ pub fn print_instance_detail(instance: Instance, operation_description: &str) {
         Cell::new("Blocked").add_attribute(Attribute::Italic),
         blocked_value_cell,
     ]);
+    table.add_row(vec![
+        Cell::new("Allowed").add_attribute(Attribute::Italic),
+        Cell::new(if instance.allowed { "Yes" } else { "No" }),
+    ]);
+    table.add_row(vec![
+        Cell::new("Moderation").add_attribute(Attribute::Italic),
+        Cell::new(format_moderation_levels(&instance.moderation_levels())),
+    ]);
+    if let Some(public_comment) = &instance.public_comment {
+        table.add_row(vec![
+            Cell::new("Public Comment").add_attribute(Attribute::Italic),
+            Cell::new(public_comment),
+        ]);
+    }
+    if let Some(private_comment) = &instance.private_comment {
+        table.add_row(vec![
+            Cell::new("Private Comment").add_attribute(Attribute::Italic),
+            Cell::new(private_comment),
+        ]);
+    }
     table.add_row(vec![
         Cell::new("Last Message At").add_attribute(Attribute::Italic),
         Cell::new(format_relative_time(instance.last_message_at)),
     ]);
+    table.add_row(vec![
+        Cell::new("Software").add_attribute(Attribute::Italic),
+        Cell::new(format_software(&instance)),
+    ]);
+    table.add_row(vec![
+        Cell::new("Users").add_attribute(Attribute::Italic),
+        Cell::new(format!(
+            "{} ({} active this month)",
+            format_user_count(instance.user_count),
+            format_user_count(instance.active_user_count)
+        )),
+    ]);
+    table.add_row(vec![
+        Cell::new("Open Registrations").add_attribute(Attribute::Italic),
+        Cell::new(match instance.open_registrations {
+            Some(true) => "Yes",
+            Some(false) => "No",
+            None => "Unknown",
+        }),
+    ]);
+    table.add_row(vec![
+        Cell::new("Last Crawled").add_attribute(Attribute::Italic),
+        Cell::new(
+            instance
+                .crawled_at
+                .map(format_relative_time)
+                .unwrap_or("Never".to_string()),
+        ),
+    ]);
+    let reachable_value_cell = if instance.unreachable {
+        let since = instance
+            .unreachable_since
+            .map(|since| format!(" (since {})", format_relative_time(since)))
+            .unwrap_or_default();
+        Cell::new(format!("No{since}")).fg(Color::Red)
+    } else {
+        Cell::new("Yes").fg(Color::Green)
+    };
+
+    table.add_row(vec![
+        Cell::new("Reachable").add_attribute(Attribute::Italic),
+        reachable_value_cell,
+    ]);
+    table.add_row(vec![
+        Cell::new("Deliveries").add_attribute(Attribute::Italic),
+        Cell::new(format!(
+            "{} succeeded, {} failed ({} consecutive)",
+            instance.delivery_success_count,
+            instance.delivery_failure_count,
+            instance.consecutive_failures
+        )),
+    ]);
+    table.add_row(vec![
+        Cell::new("Last Successful Delivery").add_attribute(Attribute::Italic),
+        Cell::new(
+            instance
+                .last_success_at
+                .map(format_relative_time)
+                .unwrap_or("Never".to_string()),
+        ),
+    ]);
     table.add_row(vec![
         Cell::new("Created At").add_attribute(Attribute::Italic),
         Cell::new(instance.created_at.to_rfc3339()),
@@ -136,3 +250,41 @@ pub fn print_instance_detail(instance: Instance, operation_description: &str) {
     ]);
     println!("{table}");
 }
+
+pub fn print_domain_block_changes(changes: Vec<DomainBlockChange>) {
+    let total = changes.len();
+    let changes: Vec<DomainBlockChange> = changes
+        .into_iter()
+        .filter(|change| change.is_change())
+        .collect();
+
+    if changes.is_empty() {
+        println!("No moderation changes for {total} domains.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table.load_preset(presets::UTF8_FULL);
+    table.set_header(vec![
+        Cell::new("Domain Name").add_attribute(Attribute::Bold),
+        Cell::new("Current").add_attribute(Attribute::Bold),
+        Cell::new("Imported").add_attribute(Attribute::Bold),
+    ]);
+    table.set_constraints(vec![
+        ColumnConstraint::LowerBoundary(Width::Fixed(40)),
+        ColumnConstraint::ContentWidth,
+        ColumnConstraint::ContentWidth,
+    ]);
+
+    let changed = changes.len();
+    for change in changes {
+        table.add_row(vec![
+            Cell::new(change.domain),
+            Cell::new(format_moderation_levels(&change.before)),
+            Cell::new(format_moderation_levels(&change.after)).fg(Color::Yellow),
+        ]);
+    }
+
+    println!("{table}");
+    println!("{changed} of {total} domains changed.");
+}
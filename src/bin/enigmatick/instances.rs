@@ -7,21 +7,24 @@ use crossterm::{
     execute,
     terminal::{self, disable_raw_mode, enable_raw_mode, Clear, ClearType},
 };
-use enigmatick::models::{
-    activities::delete_activities_by_domain_pattern, actors::delete_actors_by_domain_pattern,
-    cache::delete_cache_items_by_server_pattern, follows::delete_follows_by_domain_pattern,
-    objects::delete_objects_by_domain_pattern,
+use enigmatick::runner::domain_blocks::{
+    export_domain_blocks, format_domain_blocks, import_domain_blocks, parse_domain_blocks,
+    purge_domain,
 };
 use enigmatick::{
     db::runner::DbRunner,
     models::instances::{
-        self as instance_model_ops, SortDirection as LibSortDirection, SortField as LibSortField,
-        SortParam as LibSortParam,
+        self as instance_model_ops, ModerationLevel, SortDirection as LibSortDirection,
+        SortField as LibSortField, SortParam as LibSortParam,
     },
 };
 use std::io::stdout;
+use std::path::PathBuf;
 
-use crate::display::{format_relative_time, print_instance_detail, print_instance_table};
+use crate::display::{
+    format_relative_time, format_software, format_user_count, print_domain_block_changes,
+    print_instance_detail, print_instance_table,
+};
 
 // Helper struct for RAII raw mode management
 struct RawModeGuard;
@@ -58,16 +61,55 @@ pub enum InstanceCommands {
         #[clap(long)]
         page_size: Option<i64>,
         /// Sort order: "field[:direction][,field[:direction]...]"
-        /// Fields: domain, blocked, last. Directions: asc, desc
+        /// Fields: domain, blocked, last, software, users, crawled. Directions: asc, desc
         #[clap(long)]
         sort: Option<String>,
     },
     /// Block instance by domain name
-    Block { domain_name: String },
+    Block {
+        domain_name: String,
+        /// Keep the instance's content and cached media; queued deliveries are always removed
+        #[clap(long)]
+        keep_content: bool,
+    },
     /// Unblock instance by domain name
     Unblock { domain_name: String },
     /// Get instance details by domain name
     Get { domain_name: String },
+    /// Add instance to the allowlist used in ALLOWLIST_MODE
+    Allow { domain_name: String },
+    /// Remove instance from the allowlist
+    Disallow { domain_name: String },
+    /// List instances on the allowlist
+    Allowlist,
+    /// Set the moderation levels applied to an instance, replacing any already applied
+    Moderate {
+        domain_name: String,
+        /// Comma-separated levels: silence, reject-media, reject-reports, suspend. Omit to
+        /// lift all moderation.
+        #[clap(long = "level", value_delimiter = ',')]
+        levels: Vec<String>,
+        /// Reason shown publicly in the list of moderated domains; pass "" to clear it
+        #[clap(long)]
+        public_comment: Option<String>,
+        /// Note visible only to administrators; pass "" to clear it
+        #[clap(long)]
+        private_comment: Option<String>,
+        /// When suspending, keep the instance's content and cached media
+        #[clap(long)]
+        keep_content: bool,
+    },
+    /// List instances with moderation levels applied
+    Moderated,
+    /// Import domain blocks from a Mastodon domain_blocks.csv file
+    Import {
+        file: PathBuf,
+        /// Show the changes the import would make without applying them
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Export domain blocks as a Mastodon domain_blocks.csv file, or to stdout
+    Export { file: Option<PathBuf> },
 }
 
 // Helper function to parse a single sort field string like "blocked" or "blocked:asc"
@@ -87,6 +129,9 @@ fn parse_one_lib_sort_param(s: &str) -> Result<LibSortParam, String> {
         "domain" | "domain_name" | "name" => LibSortField::DomainName,
         "blocked" => LibSortField::Blocked,
         "last" | "last_message_at" | "lastmessageat" => LibSortField::LastMessageAt,
+        "software" => LibSortField::Software,
+        "users" | "user_count" => LibSortField::Users,
+        "crawled" | "crawled_at" => LibSortField::CrawledAt,
         _ => return Err(format!("Unknown sort field: '{field_str}'")),
     };
 
@@ -110,6 +155,36 @@ fn parse_sort_string_to_lib_params(s: &str) -> Result<Vec<LibSortParam>, String>
         .collect()
 }
 
+/// Purges a blocked domain and reports what was removed
+async fn cleanup_domain_data(conn: &impl DbRunner, domain: &str, keep_content: bool) {
+    let purge = purge_domain(conn, domain, keep_content).await;
+
+    println!(
+        "Deleted {} queued deliveries to blocked domain.",
+        purge.deliveries
+    );
+
+    if keep_content {
+        return;
+    }
+
+    println!("Deleted {} objects from blocked domain.", purge.objects);
+    println!(
+        "Deleted {} activities from blocked domain.",
+        purge.activities
+    );
+    println!("Deleted {} followers from blocked domain.", purge.follows);
+    println!("Deleted {} actors from blocked domain.", purge.actors);
+    if purge.cache_items == 0 {
+        println!("No cache items found for blocked domain.");
+    } else {
+        println!(
+            "Deleted {} cache items from blocked domain.",
+            purge.cache_items
+        );
+    }
+}
+
 const DEFAULT_CLAP_PAGE_SIZE: i64 = 20;
 
 pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
@@ -252,12 +327,16 @@ pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
                         table_display.set_header(vec![
                             Cell::new("Domain Name").add_attribute(Attribute::Bold),
                             Cell::new("Blocked").add_attribute(Attribute::Bold),
+                            Cell::new("Software").add_attribute(Attribute::Bold),
+                            Cell::new("Users").add_attribute(Attribute::Bold),
                             Cell::new("Last Message At").add_attribute(Attribute::Bold),
                         ]);
                         table_display.set_constraints(vec![
                             ColumnConstraint::LowerBoundary(Width::Fixed(40)),
                             ColumnConstraint::ContentWidth,
                             ColumnConstraint::ContentWidth,
+                            ColumnConstraint::ContentWidth,
+                            ColumnConstraint::ContentWidth,
                         ]);
 
                         for instance_item in instances_data {
@@ -270,8 +349,10 @@ pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
                             };
 
                             table_display.add_row(vec![
-                                Cell::new(instance_item.domain_name),
+                                Cell::new(&instance_item.domain_name),
                                 blocked_cell,
+                                Cell::new(format_software(&instance_item)),
+                                Cell::new(format_user_count(instance_item.user_count)),
                                 Cell::new(format_relative_time(instance_item.last_message_at)),
                             ]);
                         }
@@ -364,7 +445,10 @@ pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
 
             //result?
         }
-        InstanceCommands::Block { domain_name } => {
+        InstanceCommands::Block {
+            domain_name,
+            keep_content,
+        } => {
             println!("Attempting to block instance: {domain_name}...");
             //handle.block_on(async {
             let conn = match enigmatick::db::POOL.get().await {
@@ -391,62 +475,7 @@ pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
                                 "Instance blocked successfully. Cleaning up associated data..."
                             );
 
-                            async fn cleanup_domain_data(conn: &impl DbRunner, domain: &str) {
-                                match delete_objects_by_domain_pattern(conn, domain.to_string())
-                                    .await
-                                {
-                                    Ok(count) => {
-                                        println!("Deleted {count} objects from blocked domain.")
-                                    }
-                                    Err(e) => eprintln!("Error deleting objects: {e}"),
-                                }
-
-                                match delete_activities_by_domain_pattern(conn, domain.to_string())
-                                    .await
-                                {
-                                    Ok(count) => {
-                                        println!("Deleted {count} activities from blocked domain.")
-                                    }
-                                    Err(e) => eprintln!("Error deleting activities: {e}"),
-                                }
-
-                                match delete_follows_by_domain_pattern(conn, domain.to_string())
-                                    .await
-                                {
-                                    Ok(count) => {
-                                        println!("Deleted {count} followers from blocked domain.")
-                                    }
-                                    Err(e) => eprintln!("Error deleting followers: {e}"),
-                                }
-
-                                match delete_actors_by_domain_pattern(conn, domain.to_string())
-                                    .await
-                                {
-                                    Ok(count) => {
-                                        println!("Deleted {count} actors from blocked domain.")
-                                    }
-                                    Err(e) => eprintln!("Error deleting actors: {e}"),
-                                }
-
-                                match delete_cache_items_by_server_pattern(conn, domain.to_string())
-                                    .await
-                                {
-                                    Ok(deleted_items) => {
-                                        let message = if deleted_items.is_empty() {
-                                            "No cache items found for blocked domain.".to_string()
-                                        } else {
-                                            format!(
-                                                "Deleted {} cache items from blocked domain.",
-                                                deleted_items.len()
-                                            )
-                                        };
-                                        println!("{message}");
-                                    }
-                                    Err(e) => eprintln!("Error deleting cache items: {e}"),
-                                }
-                            }
-
-                            cleanup_domain_data(&conn, &domain_name).await;
+                            cleanup_domain_data(&conn, &domain_name, keep_content).await;
                             print_instance_detail(
                                 instance,
                                 "blocked successfully with cleanup completed",
@@ -515,6 +544,108 @@ pub async fn handle_instance_command(args: InstanceArgs) -> Result<()> {
             //});
             anyhow::Ok(())?
         }
+        InstanceCommands::Allow { domain_name } => {
+            let conn = enigmatick::db::POOL.get().await?;
+
+            match instance_model_ops::set_allow_status(&conn, domain_name.clone(), true).await {
+                Ok(instance) => print_instance_detail(instance, "allowed successfully"),
+                Err(e) => eprintln!("Error allowing instance {domain_name}: {e}"),
+            }
+        }
+        InstanceCommands::Disallow { domain_name } => {
+            let conn = enigmatick::db::POOL.get().await?;
+
+            match instance_model_ops::get_instance_by_domain_name(&conn, domain_name.clone()).await
+            {
+                Ok(Some(instance)) if !instance.allowed => {
+                    println!("Instance {domain_name} is not on the allowlist.");
+                }
+                Ok(Some(_)) => {
+                    match instance_model_ops::set_allow_status(&conn, domain_name.clone(), false)
+                        .await
+                    {
+                        Ok(instance) => print_instance_detail(instance, "disallowed successfully"),
+                        Err(e) => eprintln!("Error disallowing instance {domain_name}: {e}"),
+                    }
+                }
+                Ok(None) => eprintln!("Instance {domain_name} not found."),
+                Err(e) => eprintln!("Error checking instance {domain_name}: {e}"),
+            }
+        }
+        InstanceCommands::Allowlist => {
+            let conn = enigmatick::db::POOL.get().await?;
+
+            if !*enigmatick::ALLOWLIST_MODE {
+                println!("Note: ALLOWLIST_MODE is not enabled, so the allowlist is not enforced.");
+            }
+
+            print_instance_table(instance_model_ops::get_allowed_instances(&conn).await);
+        }
+        InstanceCommands::Moderate {
+            domain_name,
+            levels,
+            public_comment,
+            private_comment,
+            keep_content,
+        } => {
+            let levels = levels
+                .into_iter()
+                .map(ModerationLevel::try_from)
+                .collect::<Result<Vec<_>>>()?;
+
+            let conn = enigmatick::db::POOL.get().await?;
+
+            let was_blocked =
+                instance_model_ops::get_instance_by_domain_name(&conn, domain_name.clone())
+                    .await?
+                    .is_some_and(|instance| instance.blocked);
+
+            match instance_model_ops::set_moderation(
+                &conn,
+                domain_name.clone(),
+                levels,
+                public_comment,
+                private_comment,
+            )
+            .await
+            {
+                Ok(instance) => {
+                    if instance.blocked && !was_blocked {
+                        println!("Instance suspended. Cleaning up associated data...");
+                        cleanup_domain_data(&conn, &domain_name, keep_content).await;
+                    }
+                    print_instance_detail(instance, "moderation updated");
+                }
+                Err(e) => eprintln!("Error moderating instance {domain_name}: {e}"),
+            }
+        }
+        InstanceCommands::Moderated => {
+            let conn = enigmatick::db::POOL.get().await?;
+
+            print_instance_table(instance_model_ops::get_moderated_instances(&conn).await);
+        }
+        InstanceCommands::Import { file, dry_run } => {
+            let blocks = parse_domain_blocks(&tokio::fs::read_to_string(&file).await?)?;
+            let conn = enigmatick::db::POOL.get().await?;
+
+            if dry_run {
+                println!("Dry run: no changes will be made.");
+            }
+
+            print_domain_block_changes(import_domain_blocks(&conn, blocks, dry_run).await?);
+        }
+        InstanceCommands::Export { file } => {
+            let conn = enigmatick::db::POOL.get().await?;
+            let csv = format_domain_blocks(&export_domain_blocks(&conn).await);
+
+            match file {
+                Some(file) => {
+                    tokio::fs::write(&file, csv).await?;
+                    println!("Exported domain blocks to {}", file.display());
+                }
+                None => print!("{csv}"),
+            }
+        }
     }
     Ok(())
 }
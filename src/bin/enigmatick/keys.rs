@@ -0,0 +1,92 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use enigmatick::admin::rotate_user_key;
+use enigmatick::models::actors::{
+    get_local_actors_without_ed25519_key, update_ed25519_key_by_as_id,
+};
+use enigmatick::proofs::{assertion_method, generate_ed25519_key_pair};
+use enigmatick::runner::user::send_actor_update_task;
+use jdt_activity_pub::ApActor;
+use serde_json::json;
+
+#[derive(Parser)]
+pub struct KeysArgs {
+    #[command(subcommand)]
+    pub command: KeysCommands,
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommands {
+    /// Generate Ed25519 keys for local users created before they were introduced
+    Backfill,
+    /// Replace a user's signing key and publish the new key to their followers
+    Rotate {
+        /// Username of the local user
+        username: String,
+    },
+}
+
+pub async fn handle_keys_command(args: KeysArgs) -> Result<()> {
+    let conn = enigmatick::db::POOL.get().await?;
+
+    match args.command {
+        KeysCommands::Backfill => {
+            let actors = get_local_actors_without_ed25519_key(&conn).await?;
+
+            if actors.is_empty() {
+                println!("All local users have Ed25519 keys.");
+                return Ok(());
+            }
+
+            let mut updated = vec![];
+            for actor in actors {
+                let key_pair = generate_ed25519_key_pair();
+                let methods = json!([assertion_method(&actor.as_id, key_pair.public_key)]);
+
+                match update_ed25519_key_by_as_id(
+                    &conn,
+                    actor.as_id.clone(),
+                    key_pair.private_key,
+                    methods,
+                )
+                .await
+                {
+                    Ok(actor) => {
+                        println!("Generated Ed25519 key for {}", actor.as_id);
+                        updated.extend(actor.ek_uuid);
+                    }
+                    Err(e) => eprintln!("Error generating Ed25519 key for {}: {e}", actor.as_id),
+                }
+            }
+
+            // Followers need the new assertionMethod to verify proofs
+            if let Err(e) =
+                send_actor_update_task(enigmatick::db::POOL.clone(), None, updated.clone()).await
+            {
+                eprintln!("Error sending actor updates: {e:?}");
+            }
+
+            println!("Total: {} user(s) updated", updated.len());
+        }
+        KeysCommands::Rotate { username } => {
+            let actor = rotate_user_key(&conn, username).await?;
+            let key_id = ApActor::from(actor.clone()).public_key.id;
+
+            send_actor_update_task(
+                enigmatick::db::POOL.clone(),
+                None,
+                actor.ek_uuid.into_iter().collect(),
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Error sending actor update: {e:?}"))?;
+
+            println!("Rotated signing key for {}: {key_id}", actor.as_id);
+            println!(
+                "The previous key remains resolvable for {} hours.",
+                *enigmatick::KEY_ROTATION_GRACE_HOURS
+            );
+        }
+    }
+
+    Ok(())
+}
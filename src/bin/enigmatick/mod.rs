@@ -1,17 +1,25 @@
 use clap::Parser;
 use std::env;
 
+mod aliases;
 mod cache;
 mod display;
 mod instances;
+mod keys;
 mod muted_terms;
+mod relays;
+mod reports;
 mod search;
 mod send;
 mod system;
 
+use aliases::{handle_aliases_command, AliasesArgs};
 use cache::{handle_cache_command, CacheArgs};
 use instances::{handle_instance_command, InstanceArgs};
+use keys::{handle_keys_command, KeysArgs};
 use muted_terms::{handle_muted_terms_command, MutedTermsArgs};
+use relays::{handle_relays_command, RelaysArgs};
+use reports::{handle_reports_command, ReportsArgs};
 use search::{handle_search_command, SearchArgs};
 use send::{handle_send_command, SendArgs};
 use system::{handle_init, handle_migrations, handle_system_user, handle_template};
@@ -36,6 +44,14 @@ pub enum Commands {
     Send(SendArgs),
     /// Manage user muted terms
     MutedTerms(MutedTermsArgs),
+    /// Manage user alsoKnownAs aliases
+    Aliases(AliasesArgs),
+    /// Manage moderation reports
+    Reports(ReportsArgs),
+    /// Manage user signing keys
+    Keys(KeysArgs),
+    /// Manage relay subscriptions
+    Relays(RelaysArgs),
     /// [Internal] Run the application server
     #[command(hide = true)]
     App,
@@ -78,6 +94,18 @@ async fn main() {
         Commands::MutedTerms(args) => handle_muted_terms_command(args)
             .await
             .expect("muted terms command failed"),
+        Commands::Aliases(args) => handle_aliases_command(args)
+            .await
+            .expect("aliases command failed"),
+        Commands::Reports(args) => handle_reports_command(args)
+            .await
+            .expect("reports command failed"),
+        Commands::Keys(args) => handle_keys_command(args)
+            .await
+            .expect("keys command failed"),
+        Commands::Relays(args) => handle_relays_command(args)
+            .await
+            .expect("relays command failed"),
         Commands::App => enigmatick::server::start().await,
     }
 }
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use enigmatick::runner::relay::{get_relays, subscribe, unsubscribe, Relay};
+
+use crate::display::format_relative_time;
+
+#[derive(Parser)]
+pub struct RelaysArgs {
+    #[command(subcommand)]
+    pub command: RelaysCommands,
+}
+
+#[derive(Subcommand)]
+pub enum RelaysCommands {
+    /// Subscribe to a relay by its actor or inbox URL
+    Add { url: String },
+    /// Unsubscribe from a relay
+    Remove { url: String },
+    /// List relay subscriptions and their state
+    List,
+}
+
+pub async fn handle_relays_command(args: RelaysArgs) -> Result<()> {
+    let conn = enigmatick::db::POOL.get().await?;
+
+    match args.command {
+        RelaysCommands::Add { url } => match subscribe(&conn, url.clone()).await {
+            Ok(relay) => {
+                println!("Subscription requested for '{url}'.");
+                print_relay(&relay);
+            }
+            Err(e) => eprintln!("Error subscribing to '{url}': {e}"),
+        },
+        RelaysCommands::Remove { url } => match unsubscribe(&conn, url.clone()).await {
+            Ok(()) => println!("Successfully unsubscribed from '{url}'."),
+            Err(e) => eprintln!("Error unsubscribing from '{url}': {e}"),
+        },
+        RelaysCommands::List => {
+            let relays = get_relays(&conn).await?;
+
+            if relays.is_empty() {
+                println!("No relays found.");
+            } else {
+                for relay in &relays {
+                    print_relay(relay);
+                }
+                println!("Total: {} relay(s)", relays.len());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_relay(relay: &Relay) {
+    println!(
+        "{} [{}] inbox: {} ({})",
+        relay.actor,
+        relay.state,
+        relay.inbox.as_deref().unwrap_or("unknown"),
+        format_relative_time(relay.created_at)
+    );
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use enigmatick::models::reports::{
+    get_report_by_uuid, get_reports, Report, ReportAction, ReportStatus,
+};
+use enigmatick::runner::report::update_report;
+
+use crate::display::format_relative_time;
+
+#[derive(Parser)]
+pub struct ReportsArgs {
+    #[command(subcommand)]
+    pub command: ReportsCommands,
+}
+
+#[derive(Subcommand)]
+pub enum ReportsCommands {
+    /// List reports, newest first
+    List {
+        /// Only list reports with this status (open or resolved)
+        #[clap(long)]
+        status: Option<String>,
+    },
+    /// Show a report and its notes
+    Show { uuid: String },
+    /// Assign a report to a local user
+    Assign { uuid: String, assignee: String },
+    /// Remove a report's assignee
+    Unassign { uuid: String },
+    /// Mark a report as resolved
+    Resolve { uuid: String },
+    /// Reopen a resolved report
+    Reopen { uuid: String },
+    /// Add a note to a report
+    Note {
+        uuid: String,
+        content: String,
+        #[clap(long)]
+        author: Option<String>,
+    },
+    /// Send a report about a remote actor to that actor's server
+    Forward { uuid: String },
+}
+
+pub async fn handle_reports_command(args: ReportsArgs) -> Result<()> {
+    let conn = enigmatick::db::POOL.get().await?;
+
+    let (uuid, action) = match args.command {
+        ReportsCommands::List { status } => {
+            let status = status.map(ReportStatus::try_from).transpose()?;
+            let reports = get_reports(&conn, status).await?;
+
+            if reports.is_empty() {
+                println!("No reports found.");
+            } else {
+                for report in &reports {
+                    print_report_summary(report);
+                }
+                println!("Total: {} report(s)", reports.len());
+            }
+
+            return Ok(());
+        }
+        ReportsCommands::Show { uuid } => {
+            match get_report_by_uuid(&conn, uuid.clone()).await {
+                Ok(report) => print_report_detail(&report),
+                Err(_) => eprintln!("Report '{uuid}' not found."),
+            }
+
+            return Ok(());
+        }
+        ReportsCommands::Assign { uuid, assignee } => (uuid, ReportAction::Assign { assignee }),
+        ReportsCommands::Unassign { uuid } => (uuid, ReportAction::Unassign),
+        ReportsCommands::Resolve { uuid } => (uuid, ReportAction::Resolve),
+        ReportsCommands::Reopen { uuid } => (uuid, ReportAction::Reopen),
+        ReportsCommands::Note {
+            uuid,
+            content,
+            author,
+        } => (uuid, ReportAction::Note { content, author }),
+        ReportsCommands::Forward { uuid } => (uuid, ReportAction::Forward),
+    };
+
+    if get_report_by_uuid(&conn, uuid.clone()).await.is_err() {
+        eprintln!("Report '{uuid}' not found.");
+        return Ok(());
+    }
+
+    match update_report(&conn, uuid.clone(), action).await {
+        Ok(report) => {
+            println!("Successfully updated report '{uuid}'.");
+            print_report_detail(&report);
+        }
+        Err(e) => eprintln!("Error updating report '{uuid}': {e}"),
+    }
+
+    Ok(())
+}
+
+fn print_report_summary(report: &Report) {
+    println!(
+        "{} [{}] {} reported by {} ({}){}",
+        report.uuid,
+        report.status,
+        report.target_ap_id,
+        report.reporter_ap_id,
+        format_relative_time(report.created_at),
+        report
+            .assigned_to
+            .as_ref()
+            .map(|assignee| format!(", assigned to {assignee}"))
+            .unwrap_or_default()
+    );
+}
+
+fn print_report_detail(report: &Report) {
+    println!("Report:      {}", report.uuid);
+    println!("Status:      {}", report.status);
+    println!("Filed:       {}", format_relative_time(report.created_at));
+    println!("Reporter:    {}", report.reporter_ap_id);
+    println!("Reported:    {}", report.target_ap_id);
+
+    for object in report.object_ap_ids() {
+        println!("Object:      {object}");
+    }
+
+    if let Some(comment) = &report.comment {
+        println!("Comment:     {comment}");
+    }
+
+    println!(
+        "Assigned to: {}",
+        report.assigned_to.as_deref().unwrap_or("(unassigned)")
+    );
+    println!("Forwarded:   {}", report.forwarded);
+
+    if let Some(resolved_at) = report.resolved_at {
+        println!("Resolved:    {}", format_relative_time(resolved_at));
+    }
+
+    for note in report.notes() {
+        println!(
+            "Note ({}{}): {}",
+            format_relative_time(note.created_at),
+            note.author
+                .map(|author| format!(" by {author}"))
+                .unwrap_or_default(),
+            note.content
+        );
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use clap::{Parser, Subcommand};
 use enigmatick::models::activities::NewActivity;
 use enigmatick::models::actors as actor_model_ops;
+use enigmatick::runner::ap_move::{create_move, send_move_task};
 use enigmatick::runner::{get_inboxes, send_to_inboxes};
 use jdt_activity_pub::{ApActivity, ApActor, ApDelete, ApUpdate};
 
@@ -17,6 +18,8 @@ pub enum SendCommands {
     Update(UpdateArgs),
     /// Send delete activities
     Delete(DeleteArgs),
+    /// Move a user to another account that lists it in alsoKnownAs
+    Move { username: String, target: String },
 }
 
 #[derive(Parser)]
@@ -47,6 +50,14 @@ pub async fn handle_send_command(args: SendArgs) -> Result<()> {
     match args.command {
         SendCommands::Update(update_args) => handle_update_command(update_args).await,
         SendCommands::Delete(delete_args) => handle_delete_command(delete_args).await,
+        SendCommands::Move { username, target } => {
+            println!("Attempting to move user {username} to {target}...");
+            match execute_send_move(username, target).await {
+                Ok(_) => println!("Successfully processed sending of move."),
+                Err(e) => eprintln!("Error sending move: {e:?}"),
+            };
+            Ok(())
+        }
     }
 }
 
@@ -193,3 +204,26 @@ async fn execute_send_actor_update(username: String) -> Result<()> {
 
     Ok(())
 }
+
+async fn execute_send_move(username: String, target: String) -> Result<()> {
+    let conn = enigmatick::db::POOL.get().await?;
+    let actor_record = actor_model_ops::get_actor_by_username(&conn, username.clone()).await?;
+
+    let ap_move = create_move(&conn, actor_record.clone(), target.clone()).await?;
+    let move_id = ap_move
+        .id
+        .ok_or(anyhow!("CLI: Saved Activity does not have an ID."))?;
+    println!("CLI: Successfully saved Move activity for '{username}' locally.");
+
+    send_move_task(
+        enigmatick::db::POOL.clone(),
+        None,
+        vec![actor_record.as_id, target, move_id],
+    )
+    .await
+    .map_err(|e| anyhow!("CLI: Error sending Move for '{username}': {e:?}"))?;
+
+    println!("CLI: Move for '{username}' has been queued for sending to followers.");
+
+    Ok(())
+}
@@ -1,9 +1,18 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use crate::{models::instances::Instance, schema::instances};
-use async_mutex::Mutex;
 use deadpool_diesel::postgres::Pool;
 use diesel::prelude::*;
+use tokio::sync::Notify;
+
+// The channel notified by the instances table's triggers when a domain's blocked or
+// allowed status changes
+const NOTIFY_CHANNEL: &str = "instance_access";
+const LISTEN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const LISTEN_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+const RELOAD_INTERVAL: Duration = Duration::from_secs(300);
 
 #[derive(Debug)]
 pub enum AccessControlError {
@@ -19,14 +28,29 @@ impl Permitted {
     }
 }
 
+/// The domains this server refuses to federate with: blocked domains and, in
+/// ALLOWLIST_MODE, every domain that isn't on the allowlist
 #[derive(Clone)]
 pub struct BlockList {
-    pub blocked_servers: Arc<Mutex<Vec<String>>>,
+    pub blocked_servers: Arc<RwLock<HashSet<String>>>,
+    pub allowed_servers: Arc<RwLock<HashSet<String>>>,
 }
 
 impl BlockList {
     // Add this new async function specifically for Axum's pool type
     pub async fn new_axum(pool: &Pool) -> anyhow::Result<Self> {
+        let block_list = BlockList {
+            blocked_servers: Arc::new(RwLock::new(HashSet::new())),
+            allowed_servers: Arc::new(RwLock::new(HashSet::new())),
+        };
+
+        block_list.reload(pool).await?;
+
+        Ok(block_list)
+    }
+
+    /// Replaces the blocked and allowed domains with those currently in the database
+    pub async fn reload(&self, pool: &Pool) -> anyhow::Result<()> {
         let conn = pool.get().await?;
         // The `??` operator fails here because the error type returned by `interact`
         // is not `Sync`, which is required by `anyhow`'s `From` trait implementation.
@@ -34,7 +58,7 @@ impl BlockList {
         let query_result = conn
             .interact(move |c| {
                 instances::table
-                    .filter(instances::blocked.eq(true))
+                    .filter(instances::blocked.eq(true).or(instances::allowed.eq(true)))
                     .get_results::<Instance>(c)
             })
             .await
@@ -42,28 +66,126 @@ impl BlockList {
 
         let instances = query_result?;
 
-        log::debug!("loading {:?} blocked servers for Axum", instances.len());
-        let blocked_servers: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(
-            instances.iter().map(|x| x.domain_name.clone()).collect(),
-        ));
+        let blocked: HashSet<String> = instances
+            .iter()
+            .filter(|x| x.blocked)
+            .map(|x| x.domain_name.clone())
+            .collect();
+        let allowed: HashSet<String> = instances
+            .iter()
+            .filter(|x| x.allowed)
+            .map(|x| x.domain_name.clone())
+            .collect();
+
+        log::debug!(
+            "loading {:?} blocked and {:?} allowed servers",
+            blocked.len(),
+            allowed.len()
+        );
+
+        *self
+            .blocked_servers
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = blocked;
+        *self
+            .allowed_servers
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = allowed;
 
-        Ok(BlockList { blocked_servers })
+        Ok(())
+    }
+
+    /// Reloads the lists whenever another process blocks, unblocks, allows or disallows a
+    /// domain, so that changes made with `enigmatick instances` reach the running server
+    /// within seconds. The lists are also reloaded periodically in case a notification is
+    /// missed while the listening connection is down.
+    pub fn spawn_reload(&self, pool: Pool) {
+        let block_list = self.clone();
+        let changed = Arc::new(Notify::new());
+
+        match std::env::var("DATABASE_URL") {
+            Ok(database_url) => {
+                let changed = changed.clone();
+                std::thread::spawn(move || loop {
+                    if let Err(e) = listen_for_changes(&database_url, &changed) {
+                        log::error!("Stopped listening for BlockList changes: {e}");
+                    }
+                    std::thread::sleep(LISTEN_RETRY_INTERVAL);
+                    // Changes made while the connection was down would otherwise be missed
+                    changed.notify_one();
+                });
+            }
+            Err(e) => log::warn!("Unable to listen for BlockList changes: {e}"),
+        }
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            interval.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = changed.notified() => {}
+                }
+
+                if let Err(e) = block_list.reload(&pool).await {
+                    log::error!("Failed to reload BlockList: {e}");
+                }
+            }
+        });
     }
 
     pub fn add(&mut self, server: String) {
         log::debug!("adding {server} to BlockList");
-        if let Some(mut x) = self.blocked_servers.try_lock() {
-            x.push(server);
-        }
+        self.blocked_servers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(server);
     }
 
     pub fn is_blocked(&self, server: String) -> bool {
         log::debug!("checking {server} against BlockList");
-        if let Some(x) = self.blocked_servers.try_lock() {
-            x.contains(&server)
-        } else {
-            // fail open
-            false
+        if server == *crate::SERVER_NAME {
+            return false;
+        }
+
+        if self
+            .blocked_servers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&server)
+        {
+            return true;
+        }
+
+        *crate::ALLOWLIST_MODE
+            && !self
+                .allowed_servers
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .contains(&server)
+    }
+}
+
+/// Waits for notifications on a dedicated connection, rather than one from the pool, so
+/// that the LISTEN registration isn't handed to other users of the pool. Only returns if
+/// the connection fails.
+fn listen_for_changes(database_url: &str, changed: &Notify) -> anyhow::Result<()> {
+    let mut conn = PgConnection::establish(database_url)?;
+    diesel::sql_query(format!("LISTEN {NOTIFY_CHANNEL}")).execute(&mut conn)?;
+    log::debug!("listening for BlockList changes");
+
+    loop {
+        std::thread::sleep(LISTEN_POLL_INTERVAL);
+
+        let domains = conn
+            .notifications_iter()
+            .map(|notification| notification.map(|notification| notification.payload))
+            .collect::<QueryResult<Vec<String>>>()?;
+
+        if !domains.is_empty() {
+            log::debug!("BlockList changed for {}", domains.join(", "));
+            changed.notify_one();
         }
     }
 }
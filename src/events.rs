@@ -106,6 +106,20 @@ impl EventChannels {
         (uuid, rx)
     }
 
+    /// Sends the message to each authorized stream belonging to the user
+    pub fn send_to_user(&mut self, username: &str, message: String) {
+        if let Some(mut x) = self.sending_channels.try_lock() {
+            for (uuid, identified_sender) in (*x).clone() {
+                if identified_sender.authorized
+                    && identified_sender.username == username
+                    && identified_sender.sender.try_send(message.clone()).is_err()
+                {
+                    x.remove(&uuid);
+                }
+            }
+        }
+    }
+
     pub fn send(&mut self, stream: Option<String>, message: String) {
         log::debug!("send called");
         if let Some(mut x) = self.sending_channels.try_lock() {
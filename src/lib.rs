@@ -18,6 +18,7 @@ extern crate log;
 // #[macro_use]
 use crate::db::runner::DbRunner;
 use crate::models::actors::{get_actor_by_as_id, Actor};
+use crate::server::is_visible;
 use crate::webfinger::retrieve_webfinger;
 use atty as _;
 use clap as _;
@@ -29,28 +30,31 @@ use dotenvy::dotenv;
 use env_logger as _;
 use indicatif as _;
 use jdt_activity_pub::MaybeMultiple;
-use maplit::{hashmap, hashset};
 use jdt_activity_pub::{
-    ActivityPub, ApActivity, ApActor, ApArticle, ApNote, ApObject, ApQuestion, ApTag, Ephemeral,
+    ActivityPub, ApActivity, ApActor, ApActorTerse, ApArticle, ApNote, ApObject, ApQuestion, ApTag,
+    Ephemeral,
 };
 use jdt_activity_pub::{ApCollection, MaybeReference};
 use lazy_static::lazy_static;
 use log4rs as _;
+use maplit::{hashmap, hashset};
 use models::activities::{get_announced, get_announcers, get_liked, get_likers};
+use models::actors::get_actors_by_as_ids;
 use models::actors::guaranteed_actor;
 use models::follows::{
     get_follow, get_follower_count_by_actor_id, get_leader_count_by_follower_actor_id,
 };
-use models::objects::get_object_by_as_id;
+use models::objects::{get_object_by_as_id, get_objects_by_as_ids, Object};
+use models::reactions::get_reaction_counts;
 use regex::Regex;
 use reqwest::StatusCode;
 use retriever::{collection_fetcher, signed_get};
 use runner::note::{fetch_remote_object, handle_object};
 use rust_embed as _;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Map, Value};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -70,6 +74,8 @@ pub mod db;
 pub mod events;
 pub mod helper;
 pub mod models;
+pub mod nodeinfo;
+pub mod proofs;
 pub mod retriever;
 pub mod runner;
 
@@ -206,6 +212,125 @@ lazy_static! {
             .unwrap_or(false)
     };
 
+    // INBOX_WORKERS controls how many workers drain the inbound activity queue
+    pub static ref INBOX_WORKERS: usize = {
+        dotenv().ok();
+        env::var("INBOX_WORKERS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(4)
+    };
+
+    // INBOX_MAX_ATTEMPTS is the number of processing attempts before an inbound activity is dead-lettered
+    pub static ref INBOX_MAX_ATTEMPTS: i32 = {
+        dotenv().ok();
+        env::var("INBOX_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(8)
+    };
+
+    // DELIVERY_MAX_ATTEMPTS is the number of attempts made to deliver an activity to an inbox before giving up
+    pub static ref DELIVERY_MAX_ATTEMPTS: i32 = {
+        dotenv().ok();
+        env::var("DELIVERY_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(10)
+    };
+
+    // DELIVERY_MAX_DEFERRAL_DAYS is how long a delivery waits for an unreachable instance to answer again before it is marked as failed
+    pub static ref DELIVERY_MAX_DEFERRAL_DAYS: i64 = {
+        dotenv().ok();
+        env::var("DELIVERY_MAX_DEFERRAL_DAYS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(7)
+    };
+
+    // INSTANCE_UNREACHABLE_THRESHOLD is the number of consecutive delivery failures after which an instance is skipped until it answers a health probe
+    pub static ref INSTANCE_UNREACHABLE_THRESHOLD: i32 = {
+        dotenv().ok();
+        env::var("INSTANCE_UNREACHABLE_THRESHOLD")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(10)
+    };
+
+    // BROADCAST_TO_INSTANCES sends public activities to the shared inbox of every known instance rather than only to followers and relays
+    pub static ref BROADCAST_TO_INSTANCES: bool = {
+        dotenv().ok();
+        env::var("BROADCAST_TO_INSTANCES")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(false)
+    };
+
+    // SECURE_MODE (authorized fetch) requires a valid signature on ActivityPub GET requests
+    pub static ref SECURE_MODE: bool = {
+        dotenv().ok();
+        env::var("SECURE_MODE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(false)
+    };
+
+    // ALLOWLIST_MODE limits federation to the instances on the allowlist
+    pub static ref ALLOWLIST_MODE: bool = {
+        dotenv().ok();
+        env::var("ALLOWLIST_MODE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(false)
+    };
+
+    // DOMAIN_BLOCKLIST_PATH is a Mastodon domain_blocks.csv file re-imported by the tasks runner
+    pub static ref DOMAIN_BLOCKLIST_PATH: Option<PathBuf> = {
+        dotenv().ok();
+        env::var("DOMAIN_BLOCKLIST_PATH").ok().map(PathBuf::from)
+    };
+
+    // KEY_ROTATION_GRACE_HOURS is how long a rotated-out signing key remains resolvable
+    pub static ref KEY_ROTATION_GRACE_HOURS: i64 = {
+        dotenv().ok();
+        env::var("KEY_ROTATION_GRACE_HOURS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(48)
+    };
+
+    // OUTBOX_BACKFILL_ITEMS is the number of posts retrieved from a newly followed actor's outbox
+    pub static ref OUTBOX_BACKFILL_ITEMS: usize = {
+        dotenv().ok();
+        env::var("OUTBOX_BACKFILL_ITEMS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(20)
+    };
+
+    // OUTBOX_BACKFILL_DAYS is the age beyond which outbox posts aren't backfilled
+    pub static ref OUTBOX_BACKFILL_DAYS: i64 = {
+        dotenv().ok();
+        env::var("OUTBOX_BACKFILL_DAYS")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(14)
+    };
+
+    // ADMIN_USERS is a comma-separated list of local usernames permitted to use the admin API
+    pub static ref ADMIN_USERS: Vec<String> = {
+        dotenv().ok();
+        env::var("ADMIN_USERS")
+            .map(|x| {
+                x.split(',')
+                    .map(str::trim)
+                    .filter(|username| !username.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
     // CUSTOM_INDEX_PATH allows deployers to provide a custom landing page at /
     pub static ref CUSTOM_INDEX_PATH: Option<PathBuf> = {
         dotenv().ok();
@@ -407,7 +532,7 @@ where
 
         let profile = guaranteed_actor(conn, None).await;
 
-        let collection = match signed_get(profile.clone(), replies_url, false).await {
+        let collection = match signed_get(conn, profile.clone(), replies_url, false).await {
             Ok(resp) if matches!(resp.status(), StatusCode::ACCEPTED | StatusCode::OK) => {
                 match resp.json::<Value>().await {
                     Ok(json) => serde_json::from_value::<ApCollection>(json).ok(),
@@ -421,7 +546,7 @@ where
             return self.clone();
         };
 
-        let mut stream = collection.stream_all(collection_fetcher());
+        let mut stream = collection.stream_all(collection_fetcher(conn).await);
 
         while let Some(Ok(ActivityPub::Object(ApObject::Note(note)))) = stream.next().await {
             let Some(note_id) = note.id.clone() else {
@@ -620,6 +745,124 @@ impl LoadEphemeral for ApActivity {
     }
 }
 
+/// Returns the notes, articles and questions in a serialized page, whether they are items of
+/// a collection or the objects of its Create and Announce activities
+fn page_objects(value: &mut Value) -> Vec<&mut Map<String, Value>> {
+    match value {
+        Value::Array(items) => items.iter_mut().flat_map(page_objects).collect(),
+        Value::Object(map) => {
+            if matches!(
+                map.get("type").and_then(Value::as_str),
+                Some("Note" | "Article" | "Question")
+            ) {
+                return vec![map];
+            }
+
+            map.iter_mut()
+                .filter(|(key, _)| {
+                    ["orderedItems", "items", "first", "object"].contains(&key.as_str())
+                })
+                .flat_map(|(_, value)| page_objects(value))
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn insert_ephemeral(object: &mut Map<String, Value>, key: &str, value: Value) {
+    if !object.get("ephemeral").is_some_and(Value::is_object) {
+        object.insert("ephemeral".to_string(), json!({}));
+    }
+
+    if let Some(ephemeral) = object.get_mut("ephemeral").and_then(Value::as_object_mut) {
+        ephemeral.insert(key.to_string(), value);
+    }
+}
+
+/// Loads the client-side data the published Ephemeral has no fields for into the notes,
+/// articles and questions of a page: `ephemeral.reactions` counts their reactions by emoji
+/// and `ephemeral.quote` carries the object each one quotes, with its authors. The data is
+/// loaded for the whole page at once.
+pub async fn load_page_ephemeral<C: DbRunner + Send + Sync, T: Serialize>(
+    conn: &C,
+    requester: Option<Actor>,
+    page: T,
+) -> anyhow::Result<Value> {
+    let mut page = serde_json::to_value(page)?;
+    let mut objects = page_objects(&mut page);
+    let requester = requester.map(|requester| requester.as_id);
+
+    let ids: Vec<String> = objects
+        .iter()
+        .filter_map(|object| object.get("id")?.as_str().map(str::to_string))
+        .collect();
+    let reactions = if ids.is_empty() {
+        HashMap::new()
+    } else {
+        get_reaction_counts(conn, ids, requester.clone()).await?
+    };
+
+    let quotes: Vec<Option<String>> = objects
+        .iter()
+        .map(|object| runner::quote::quote_of(&object.get("tag").cloned()))
+        .collect();
+
+    let quoted_ids: Vec<String> = quotes.iter().flatten().cloned().collect();
+    let quoted = if quoted_ids.is_empty() {
+        vec![]
+    } else {
+        get_objects_by_as_ids(conn, quoted_ids).await?
+    };
+
+    let author_ids: Vec<String> = quoted.iter().flat_map(Object::attributed_to).collect();
+    let authors = if author_ids.is_empty() {
+        vec![]
+    } else {
+        get_actors_by_as_ids(conn, author_ids).await?
+    };
+
+    let mut quoted_objects: HashMap<String, Value> = HashMap::new();
+    for object in quoted {
+        if !is_visible(conn, &object, requester.clone()).await {
+            continue;
+        }
+
+        let attributed_to: Vec<ApActorTerse> = authors
+            .iter()
+            .filter(|author| object.attributed_to().contains(&author.as_id))
+            .cloned()
+            .map(ApActorTerse::from)
+            .collect();
+
+        let as_id = object.as_id.clone();
+        let Some(Value::Object(mut quoted_object)) = ApObject::try_from(object)
+            .ok()
+            .and_then(|object| serde_json::to_value(object).ok())
+        else {
+            continue;
+        };
+
+        insert_ephemeral(&mut quoted_object, "attributedTo", json!(attributed_to));
+        quoted_objects.insert(as_id, Value::Object(quoted_object));
+    }
+
+    for (object, quote) in objects.iter_mut().zip(quotes) {
+        if let Some(counts) = object
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| reactions.get(id))
+        {
+            insert_ephemeral(object, "reactions", json!(counts));
+        }
+
+        if let Some(quoted_object) = quote.and_then(|quote| quoted_objects.get(&quote)) {
+            insert_ephemeral(object, "quote", quoted_object.clone());
+        }
+    }
+
+    Ok(page)
+}
+
 impl LoadEphemeral for ApActor {
     async fn load_ephemeral<C: DbRunner + Send + Sync>(
         &mut self,
@@ -3,6 +3,7 @@ use crate::db::DbType;
 use crate::helper::get_activity_ap_id_from_uuid;
 use crate::models::actors::{get_actor_by_as_id, Actor};
 use crate::models::coalesced_activity::CoalescedActivity;
+use crate::models::instances::get_silenced_domains;
 use crate::models::objects::{Object, ObjectType};
 use crate::schema::{activities, actors};
 use crate::server::InboxView;
@@ -10,9 +11,8 @@ use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use convert_case::{Case, Casing};
 use diesel::sql_types::{Array, Bool, Integer, Nullable, Text};
-use diesel::{prelude::*, sql_query};
+use diesel::{dsl::count, prelude::*, sql_query};
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
-use jdt_activity_pub::ApBlockType;
 use jdt_activity_pub::ApMoveType;
 use jdt_activity_pub::ApRejectType;
 use jdt_activity_pub::ApRemoveType;
@@ -24,6 +24,8 @@ use jdt_activity_pub::{
     ApInstrument, ApLike, ApLikeType, ApNote, ApObject, ApQuestion, ApReject, ApUndo, ApUndoType,
     ApUpdateType, Ephemeral,
 };
+use jdt_activity_pub::{ApAdd, ApAddType, ApRemove};
+use jdt_activity_pub::{ApBlock, ApBlockType};
 use jdt_activity_pub::{ApUpdate, PUBLIC_COLLECTION};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -48,6 +50,7 @@ pub enum ActivityType {
     Remove,
     Move,
     Reject,
+    Flag,
 }
 
 impl ActivityType {
@@ -102,6 +105,10 @@ impl ActivityType {
     pub fn is_reject(&self) -> bool {
         self == &ActivityType::Reject
     }
+
+    pub fn is_flag(&self) -> bool {
+        self == &ActivityType::Flag
+    }
 }
 
 impl fmt::Display for ActivityType {
@@ -156,6 +163,7 @@ impl TryFrom<String> for ActivityType {
             "remove" => Ok(ActivityType::Remove),
             "move" => Ok(ActivityType::Move),
             "reject" => Ok(ActivityType::Reject),
+            "flag" => Ok(ActivityType::Flag),
             _ => Err(anyhow!("unimplemented ActivityType")),
         }
     }
@@ -215,6 +223,12 @@ impl From<ApBlockType> for ActivityType {
     }
 }
 
+impl From<ApAddType> for ActivityType {
+    fn from(_: ApAddType) -> Self {
+        ActivityType::Add
+    }
+}
+
 impl From<ApRemoveType> for ActivityType {
     fn from(_: ApRemoveType) -> Self {
         ActivityType::Remove
@@ -308,6 +322,9 @@ pub struct TimelineFilters {
     /// Filter by object type (e.g., Article, Note, Question)
     /// When None, all types are returned
     pub object_type: Option<ObjectType>,
+    /// Actors whose activities are excluded, typically those on either side of a block
+    /// involving the viewer
+    pub blocked_actors: Vec<String>,
 }
 
 #[derive(
@@ -525,6 +542,19 @@ impl TryFrom<ApActivityTarget> for NewActivity {
             }
             .link_target(target)
             .clone()),
+            ApActivity::Block(block) => Ok(NewActivity {
+                kind: block.kind.into(),
+                uuid: uuid.clone(),
+                actor: block.actor.to_string(),
+                target_ap_id: Some(block.object),
+                revoked: false,
+                ap_id: block
+                    .id
+                    .map_or(Some(get_activity_ap_id_from_uuid(uuid)), Some),
+                ..Default::default()
+            }
+            .link_target(target)
+            .clone()),
             ApActivity::Accept(accept) => {
                 if let Some(ActivityTarget::Activity(follow)) = target.clone() {
                     Ok(NewActivity {
@@ -686,6 +716,19 @@ impl TryFrom<ApActivityTarget> for NewActivity {
                 }
                 .link_target(target)
                 .clone()),
+                MaybeReference::Actual(ApActivity::Block(block)) => Ok(NewActivity {
+                    kind: undo.kind.into(),
+                    uuid: uuid.clone(),
+                    actor: undo.actor.to_string(),
+                    target_ap_id: block.id,
+                    revoked: false,
+                    ap_id: undo
+                        .id
+                        .map_or(Some(get_activity_ap_id_from_uuid(uuid)), Some),
+                    ..Default::default()
+                }
+                .link_target(target)
+                .clone()),
                 _ => Err(anyhow!(
                     "Undo object not implemented in TryFrom<ApActivityTarget> for NewActivity"
                 )),
@@ -704,6 +747,19 @@ impl TryFrom<ApActivityTarget> for NewActivity {
             }
             .link_target(target)
             .clone()),
+            ApActivity::Add(add) => Ok(NewActivity {
+                kind: add.kind.into(),
+                uuid: uuid.clone(),
+                actor: add.actor.to_string(),
+                target_ap_id: add.object.reference(),
+                revoked: false,
+                ap_id: add
+                    .id
+                    .map_or(Some(get_activity_ap_id_from_uuid(uuid)), Some),
+                ..Default::default()
+            }
+            .link_target(target)
+            .clone()),
             ApActivity::Remove(remove) => Ok(NewActivity {
                 kind: remove.kind.into(),
                 uuid: uuid.clone(),
@@ -734,10 +790,6 @@ impl TryFrom<ApActivityTarget> for NewActivity {
             }
             .link_target(target)
             .clone()),
-
-            _ => Err(anyhow!(
-                "Unimplemented Activity type in TryFrom<ApActivityTarget> for NewActivity"
-            )),
         }
     }
 }
@@ -872,6 +924,13 @@ impl TryFromExtendedActivity for ApActivity {
                 target_actor,
             ))
             .map(|reject| ApActivity::Reject(Box::new(reject))),
+            ActivityType::Block => ApBlock::try_from_extended_activity((
+                activity,
+                target_activity,
+                target_object,
+                target_actor,
+            ))
+            .map(ApActivity::Block),
             ActivityType::Update => ApUpdate::try_from_extended_activity((
                 activity,
                 target_activity,
@@ -879,6 +938,20 @@ impl TryFromExtendedActivity for ApActivity {
                 target_actor,
             ))
             .map(ApActivity::Update),
+            ActivityType::Add => ApAdd::try_from_extended_activity((
+                activity,
+                target_activity,
+                target_object,
+                target_actor,
+            ))
+            .map(ApActivity::Add),
+            ActivityType::Remove => ApRemove::try_from_extended_activity((
+                activity,
+                target_activity,
+                target_object,
+                target_actor,
+            ))
+            .map(ApActivity::Remove),
             _ => {
                 log::error!(
                     "Failed to match implemented activity in TryFrom for ApActivity\nACTIVITY: {activity:#?}\nTARGET_ACTIVITY: {target_activity:#?}\nTARGET_OBJECT: {target_object:#?}\nTARGET_ACTOR {target_actor:#?}"
@@ -926,6 +999,82 @@ impl TryFromExtendedActivity for ApUpdate {
     }
 }
 
+impl TryFromExtendedActivity for ApBlock {
+    type Error = anyhow::Error;
+
+    fn try_from_extended_activity(
+        (activity, _target_activity, _target_object, _target_actor): ExtendedActivity,
+    ) -> Result<Self, Self::Error> {
+        if !activity.kind.is_block() {
+            return Err(anyhow!("activity is not a block"));
+        }
+
+        Ok(ApBlock {
+            context: Some(ApContext::default()),
+            kind: ApBlockType::default(),
+            actor: activity.actor.clone().into(),
+            id: activity.ap_id,
+            object: activity
+                .target_ap_id
+                .ok_or(anyhow!("Block must have a target_ap_id"))?,
+        })
+    }
+}
+
+// Add and Remove are only used for the featured collection, which the activity does not record
+fn featured_collection_of(actor: &str) -> String {
+    format!("{actor}/featured")
+}
+
+impl TryFromExtendedActivity for ApAdd {
+    type Error = anyhow::Error;
+
+    fn try_from_extended_activity(
+        (activity, _target_activity, _target_object, _target_actor): ExtendedActivity,
+    ) -> Result<Self, Self::Error> {
+        if !activity.kind.is_add() {
+            return Err(anyhow!("activity is not an add"));
+        }
+
+        Ok(ApAdd {
+            context: Some(ApContext::default()),
+            kind: ApAddType::default(),
+            id: activity.ap_id,
+            actor: activity.actor.clone().into(),
+            object: activity
+                .target_ap_id
+                .ok_or(anyhow!("Add must have a target_ap_id"))?
+                .into(),
+            target: Some(featured_collection_of(&activity.actor)),
+        })
+    }
+}
+
+impl TryFromExtendedActivity for ApRemove {
+    type Error = anyhow::Error;
+
+    fn try_from_extended_activity(
+        (activity, _target_activity, _target_object, _target_actor): ExtendedActivity,
+    ) -> Result<Self, Self::Error> {
+        if !activity.kind.is_remove() {
+            return Err(anyhow!("activity is not a remove"));
+        }
+
+        Ok(ApRemove {
+            context: Some(ApContext::default()),
+            kind: ApRemoveType::Remove,
+            id: activity.ap_id,
+            actor: activity.actor.clone().into(),
+            object: activity
+                .target_ap_id
+                .ok_or(anyhow!("Remove must have a target_ap_id"))?
+                .into(),
+            target: featured_collection_of(&activity.actor).into(),
+            ..Default::default()
+        })
+    }
+}
+
 impl TryFromExtendedActivity for ApAccept {
     type Error = anyhow::Error;
 
@@ -1173,6 +1322,13 @@ impl TryFromExtendedActivity for ApUndo {
                 id: activity.ap_id,
                 object: MaybeReference::Actual(ApActivity::Announce(announce)),
             }),
+            ApActivity::Block(block) => Ok(ApUndo {
+                context: Some(ApContext::default()),
+                kind: ApUndoType::default(),
+                actor: activity.actor.clone().into(),
+                id: activity.ap_id,
+                object: MaybeReference::Actual(ApActivity::Block(block)),
+            }),
             _ => {
                 log::error!("FAILED TO MATCH IMPLEMENTED UNDO: {activity:#?}");
                 Err(anyhow!("FAILED TO MATCH IMPLEMENTED UNDO"))
@@ -1254,6 +1410,8 @@ struct TimelineQueryParams {
     limit: i32,
     outbox_username: String,
     profile_actor_id: String,
+    hidden_actors: Vec<String>,
+    silenced_domains: Vec<String>,
 }
 
 impl Default for TimelineQueryParams {
@@ -1270,6 +1428,8 @@ impl Default for TimelineQueryParams {
             limit: 0,
             outbox_username: "NULL".to_string(),
             profile_actor_id: "NULL".to_string(),
+            hidden_actors: vec![],
+            silenced_domains: vec![],
         }
     }
 }
@@ -1335,6 +1495,7 @@ fn build_timeline_query<'a>(
         }
 
         params.hashtags.extend(filters.hashtags);
+        params.hidden_actors.extend(filters.blocked_actors);
         combined_excluded_words.extend(filters.excluded_words);
     }
 
@@ -1418,9 +1579,18 @@ pub async fn get_activities_coalesced<C: DbRunner>(
         return get_outbox(conn, limit, min, max, profile, filters).await;
     }
 
-    let (query_str, params) =
+    let (query_str, mut params) =
         build_timeline_query(&filters, limit, min, max, &profile, as_id, uuid, id);
 
+    // Silenced domains are kept out of public views
+    if params
+        .to_addresses
+        .iter()
+        .any(|address| PUBLIC_COLLECTION.contains(address))
+    {
+        params.silenced_domains = get_silenced_domains(conn).await?;
+    }
+
     conn.run(move |c| {
         if params.hashtags.is_empty() {
             // Binding for timeline_public_no_hashtags.sql
@@ -1434,6 +1604,8 @@ pub async fn get_activities_coalesced<C: DbRunner>(
                 .bind::<Bool, _>(params.order_asc)
                 .bind::<Integer, _>(params.limit)
                 .bind::<Text, _>(params.profile_actor_id)
+                .bind::<Array<Text>, _>(params.hidden_actors)
+                .bind::<Array<Text>, _>(params.silenced_domains)
                 .load::<CoalescedActivity>(c)
         } else {
             // Binding for timeline_public_with_hashtags.sql
@@ -1454,6 +1626,8 @@ pub async fn get_activities_coalesced<C: DbRunner>(
                 .bind::<Bool, _>(params.order_asc)
                 .bind::<Integer, _>(params.limit)
                 .bind::<Text, _>(params.profile_actor_id)
+                .bind::<Array<Text>, _>(params.hidden_actors)
+                .bind::<Array<Text>, _>(params.silenced_domains)
                 .load::<CoalescedActivity>(c)
         }
     })
@@ -1873,6 +2047,24 @@ pub async fn get_outbox_count_by_actor_id<C: DbRunner>(conn: &C, actor_id: i32)
     .await
 }
 
+/// Returns the number of local users, not counting the system user, who have created an
+/// activity since the cutoff
+pub async fn get_active_local_user_count<C: DbRunner>(
+    conn: &C,
+    since: DateTime<Utc>,
+) -> Result<i64> {
+    conn.run(move |c| {
+        activities::table
+            .inner_join(actors::table.on(activities::actor_id.eq(actors::id.nullable())))
+            .filter(actors::ek_username.is_not_null())
+            .filter(actors::ek_username.ne((*crate::SYSTEM_USER).clone()))
+            .filter(activities::created_at.ge(since))
+            .select(count(activities::actor_id).aggregate_distinct())
+            .get_result::<i64>(c)
+    })
+    .await
+}
+
 pub async fn update_target_object<C: DbRunner>(
     conn: &C,
     activity: Activity,
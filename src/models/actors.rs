@@ -10,11 +10,12 @@ use deadpool_diesel::postgres::Object as DbConnection;
 use diesel::prelude::*;
 use diesel::sql_query;
 use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use jdt_activity_pub::actor::ApAssertionMethod;
+use jdt_activity_pub::ApAddress;
 use jdt_activity_pub::{
     ApActor, ApActorTerse, ApActorType, ApCollection, ApContext, ApDateTime, ApInstrument,
-    ApInstrumentType, Ephemeral, MaybeReference,
+    ApInstrumentType, ApPublicKey, Ephemeral, MaybeReference,
 };
-use jdt_activity_pub::{ApAddress, MaybeMultiple};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
@@ -222,6 +223,10 @@ pub struct NewActor {
     pub ap_capabilities: Value,
     pub ap_manually_approves_followers: bool,
     pub ek_keys: Option<String>,
+    pub as_assertion_method: Value,
+    pub ek_ed25519_private_key: Option<String>,
+    // ApActor has no movedTo property, so this is taken from the actor document by callers
+    pub as_moved_to: Option<String>,
 }
 
 #[derive(
@@ -296,6 +301,14 @@ pub struct Actor {
     pub ek_mls_storage: Option<String>,
     pub ek_mls_storage_hash: Option<String>,
     pub ek_muted_terms: Value,
+    pub as_moved_to: Option<String>,
+    pub ek_featured_items: Value,
+    pub as_assertion_method: Value,
+    #[serde(skip_serializing)]
+    pub ek_ed25519_private_key: Option<String>,
+    pub ek_previous_public_key: Option<Value>,
+    pub ek_key_rotated_at: Option<DateTime<Utc>>,
+    pub ek_quotes_allowed: bool,
 }
 
 impl fmt::Display for Actor {
@@ -405,7 +418,11 @@ impl From<Actor> for ApActor {
             webfinger: Some(webfinger),
             ..Default::default()
         });
-        let assertion_method = MaybeMultiple::None;
+        let assertion_method =
+            serde_json::from_value::<Vec<ApAssertionMethod>>(actor.as_assertion_method)
+                .ok()
+                .filter(|methods| !methods.is_empty())
+                .into();
         let generator = None;
         let updated = None;
 
@@ -587,6 +604,14 @@ impl TryFrom<CoalescedActivity> for Actor {
             ek_mls_storage,
             ek_mls_storage_hash,
             ek_muted_terms,
+            // The coalesced activity queries do not carry the moved state or pinned objects
+            as_moved_to: None,
+            ek_featured_items: json!([]),
+            as_assertion_method: json!([]),
+            ek_ed25519_private_key: None,
+            ek_previous_public_key: None,
+            ek_key_rotated_at: None,
+            ek_quotes_allowed: true,
         })
     }
 }
@@ -595,6 +620,32 @@ impl Actor {
     pub fn is_stale(&self) -> bool {
         Utc::now() - self.updated_at > Duration::days(7)
     }
+
+    /// The ID of the actor's featured collection
+    pub fn featured(&self) -> Option<String> {
+        match self.as_featured.as_ref()? {
+            Value::String(id) => Some(id.clone()),
+            Value::Object(collection) => collection.get("id")?.as_str().map(String::from),
+            _ => None,
+        }
+    }
+
+    /// The IDs of the objects pinned to the actor's featured collection, newest first
+    pub fn featured_items(&self) -> Vec<String> {
+        serde_json::from_value(self.ek_featured_items.clone()).unwrap_or_default()
+    }
+
+    /// The key replaced by the actor's last key rotation, while it is still within the
+    /// grace period during which signatures made with it are accepted
+    pub fn previous_public_key(&self) -> Option<ApPublicKey> {
+        let rotated_at = self.ek_key_rotated_at?;
+
+        if Utc::now() - rotated_at > Duration::hours(*crate::KEY_ROTATION_GRACE_HOURS) {
+            return None;
+        }
+
+        serde_json::from_value(self.ek_previous_public_key.clone()?).ok()
+    }
 }
 
 impl TryFrom<ApActor> for NewActor {
@@ -634,6 +685,7 @@ impl TryFrom<ApActor> for NewActor {
         let as_also_known_as = json!(actor.also_known_as.multiple());
         let as_discoverable = actor.discoverable.unwrap_or_default();
         let ap_capabilities = actor.capabilities.map_or(json!({}), |x| json!(x));
+        let as_assertion_method = json!(actor.assertion_method.multiple());
 
         Ok(NewActor {
             as_context,
@@ -662,6 +714,7 @@ impl TryFrom<ApActor> for NewActor {
             as_also_known_as,
             as_discoverable,
             ap_capabilities,
+            as_assertion_method,
             ek_hashtags,
             ek_checked_at,
             ..Default::default()
@@ -798,6 +851,20 @@ pub async fn update_summary_by_username<C: DbRunner>(
     .await
 }
 
+/// Sets whether other actors may quote the user's posts
+pub async fn update_quotes_allowed_by_username<C: DbRunner>(
+    conn: &C,
+    username: String,
+    allowed: bool,
+) -> Result<Actor> {
+    conn.run(move |c| {
+        diesel::update(actors::table.filter(actors::ek_username.eq(username)))
+            .set(actors::ek_quotes_allowed.eq(allowed))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
 pub async fn update_password_by_username<C: DbRunner>(
     conn: &C,
     username: String,
@@ -856,6 +923,134 @@ pub async fn update_muted_terms_by_username<C: DbRunner>(
     .await
 }
 
+pub async fn get_also_known_as_by_username<C: DbRunner>(
+    conn: &C,
+    username: String,
+) -> Result<Vec<String>> {
+    conn.run(move |c: &mut PgConnection| {
+        actors::table
+            .filter(actors::ek_username.eq(username))
+            .select(actors::as_also_known_as)
+            .first::<Value>(c)
+            .map(|aliases| {
+                aliases
+                    .as_array()
+                    .unwrap_or(&vec![])
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            })
+    })
+    .await
+}
+
+pub async fn update_also_known_as_by_username<C: DbRunner>(
+    conn: &C,
+    username: String,
+    aliases: Vec<String>,
+) -> Result<Actor> {
+    let aliases_json = json!(aliases);
+
+    conn.run(move |c: &mut PgConnection| {
+        diesel::update(actors::table)
+            .filter(actors::ek_username.eq(username))
+            .set(actors::as_also_known_as.eq(aliases_json))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
+/// Returns the account an actor document names in its movedTo property
+pub fn moved_to_of(actor: &Value) -> Option<String> {
+    match actor.get("movedTo")? {
+        Value::String(moved_to) => Some(moved_to.clone()),
+        moved_to => moved_to.get("id")?.as_str().map(str::to_string),
+    }
+}
+
+/// Records that the actor has moved to the account identified by `moved_to`
+pub async fn update_moved_to_by_as_id<C: DbRunner>(
+    conn: &C,
+    as_id: String,
+    moved_to: String,
+) -> Result<Actor> {
+    conn.run(move |c: &mut PgConnection| {
+        diesel::update(actors::table)
+            .filter(actors::as_id.eq(as_id))
+            .set(actors::as_moved_to.eq(Some(moved_to)))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
+/// Replaces the objects pinned to the actor's featured collection
+pub async fn update_featured_items_by_as_id<C: DbRunner>(
+    conn: &C,
+    as_id: String,
+    items: Vec<String>,
+) -> Result<Actor> {
+    let items_json = json!(items);
+
+    conn.run(move |c: &mut PgConnection| {
+        diesel::update(actors::table)
+            .filter(actors::as_id.eq(as_id))
+            .set(actors::ek_featured_items.eq(items_json))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
+/// Local actors that were created before Ed25519 keys were generated for new users
+pub async fn get_local_actors_without_ed25519_key<C: DbRunner>(conn: &C) -> Result<Vec<Actor>> {
+    conn.run(move |c: &mut PgConnection| {
+        actors::table
+            .filter(actors::ek_username.is_not_null())
+            .filter(actors::ek_ed25519_private_key.is_null())
+            .get_results::<Actor>(c)
+    })
+    .await
+}
+
+pub async fn update_ed25519_key_by_as_id<C: DbRunner>(
+    conn: &C,
+    as_id: String,
+    private_key: String,
+    assertion_method: Value,
+) -> Result<Actor> {
+    conn.run(move |c: &mut PgConnection| {
+        diesel::update(actors::table)
+            .filter(actors::as_id.eq(as_id))
+            .set((
+                actors::ek_ed25519_private_key.eq(Some(private_key)),
+                actors::as_assertion_method.eq(assertion_method),
+            ))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
+/// Replaces a local actor's signing key. The key it replaces is kept as the previous key
+/// so that it remains resolvable for the rotation grace period.
+pub async fn update_public_key_by_username<C: DbRunner>(
+    conn: &C,
+    username: String,
+    private_key: String,
+    public_key: Value,
+) -> Result<Actor> {
+    conn.run(move |c: &mut PgConnection| {
+        diesel::update(actors::table)
+            .filter(actors::ek_username.eq(username))
+            .set((
+                actors::ek_previous_public_key.eq(actors::as_public_key.nullable()),
+                actors::ek_key_rotated_at.eq(Some(Utc::now())),
+                actors::ek_private_key.eq(Some(private_key)),
+                actors::as_public_key.eq(public_key),
+            ))
+            .get_result::<Actor>(c)
+    })
+    .await
+}
+
 pub async fn get_actor_by_key_id<C: DbRunner>(conn: &C, key_id: String) -> Result<Actor> {
     use diesel::sql_types::Text;
 
@@ -903,6 +1098,7 @@ pub async fn tombstone_actor_by_as_id<C: DbRunner>(conn: &C, as_id: String) -> R
                 actors::ek_keys.eq(None::<String>),
                 // Clear muted terms
                 actors::ek_muted_terms.eq(json!([])),
+                actors::ek_featured_items.eq(json!([])),
             ))
             .get_result(c)
     })
@@ -981,6 +1177,18 @@ pub async fn get_actor_by_as_id<C: DbRunner>(conn: &C, as_id: String) -> Result<
     .await
 }
 
+pub async fn get_actors_by_as_ids<C: DbRunner>(
+    conn: &C,
+    as_ids: Vec<String>,
+) -> Result<Vec<Actor>> {
+    conn.run(move |c| {
+        actors::table
+            .filter(actors::as_id.eq_any(as_ids))
+            .get_results::<Actor>(c)
+    })
+    .await
+}
+
 pub async fn get_follower_inboxes<C: DbRunner>(conn: &C, actor: Actor) -> Vec<ApAddress> {
     let mut inboxes: HashSet<ApAddress> = HashSet::new();
 
@@ -1002,6 +1210,18 @@ pub async fn guaranteed_actor<C: DbRunner>(conn: &C, profile: Option<Actor>) ->
     }
 }
 
+/// Returns the number of local users, not counting the system user
+pub async fn get_local_user_count<C: DbRunner>(conn: &C) -> Result<i64> {
+    conn.run(move |c| {
+        actors::table
+            .filter(actors::ek_username.is_not_null())
+            .filter(actors::ek_username.ne((*crate::SYSTEM_USER).clone()))
+            .count()
+            .get_result::<i64>(c)
+    })
+    .await
+}
+
 pub async fn get_actor_by_key_id_axum(conn: &DbConnection, key_id: String) -> Result<Actor> {
     conn.interact(move |c| {
         use diesel::sql_types::Text;
@@ -0,0 +1,168 @@
+use crate::db::runner::DbRunner;
+use crate::schema::blocks;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use jdt_activity_pub::ApBlock;
+use serde::{Deserialize, Serialize};
+
+use super::actors::get_actor_by_as_id;
+
+#[derive(Serialize, Deserialize, Insertable, Default, Debug, Clone)]
+#[diesel(table_name = blocks)]
+pub struct NewBlock {
+    pub blocker_ap_id: String,
+    pub blocked_ap_id: String,
+    pub block_activity_ap_id: Option<String>,
+    pub blocker_actor_id: Option<i32>,
+    pub blocked_actor_id: Option<i32>,
+}
+
+impl NewBlock {
+    pub async fn link<C: DbRunner>(mut self, conn: &C) -> NewBlock {
+        self.blocker_actor_id = get_actor_by_as_id(conn, self.blocker_ap_id.clone())
+            .await
+            .ok()
+            .map(|x| x.id);
+
+        self.blocked_actor_id = get_actor_by_as_id(conn, self.blocked_ap_id.clone())
+            .await
+            .ok()
+            .map(|x| x.id);
+
+        self.clone()
+    }
+}
+
+impl From<ApBlock> for NewBlock {
+    fn from(block: ApBlock) -> Self {
+        NewBlock {
+            blocker_ap_id: block.actor.to_string(),
+            blocked_ap_id: block.object,
+            block_activity_ap_id: block.id,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Identifiable, Queryable, AsChangeset, Serialize, Clone, Default, Debug)]
+#[diesel(table_name = blocks)]
+pub struct Block {
+    #[serde(skip_serializing)]
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub blocker_ap_id: String,
+    pub blocked_ap_id: String,
+    pub block_activity_ap_id: Option<String>,
+    pub blocker_actor_id: Option<i32>,
+    pub blocked_actor_id: Option<i32>,
+}
+
+/// Records a block. Blocking an actor that is already blocked replaces the Block activity
+/// on the existing record.
+pub async fn create_block<C: DbRunner>(conn: &C, block: NewBlock) -> Result<Block> {
+    conn.run(move |c| {
+        diesel::insert_into(blocks::table)
+            .values(&block)
+            .on_conflict((blocks::blocker_ap_id, blocks::blocked_ap_id))
+            .do_update()
+            .set((
+                blocks::block_activity_ap_id.eq(excluded(blocks::block_activity_ap_id)),
+                blocks::blocker_actor_id.eq(excluded(blocks::blocker_actor_id)),
+                blocks::blocked_actor_id.eq(excluded(blocks::blocked_actor_id)),
+            ))
+            .get_result(c)
+    })
+    .await
+}
+
+pub async fn get_block<C: DbRunner>(
+    conn: &C,
+    blocker_ap_id: String,
+    blocked_ap_id: String,
+) -> Result<Option<Block>> {
+    conn.run(move |c| {
+        blocks::table
+            .filter(blocks::blocker_ap_id.eq(blocker_ap_id))
+            .filter(blocks::blocked_ap_id.eq(blocked_ap_id))
+            .first(c)
+            .optional()
+    })
+    .await
+}
+
+pub async fn delete_block<C: DbRunner>(
+    conn: &C,
+    blocker_ap_id: String,
+    blocked_ap_id: String,
+) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::delete(
+            blocks::table
+                .filter(blocks::blocker_ap_id.eq(blocker_ap_id))
+                .filter(blocks::blocked_ap_id.eq(blocked_ap_id)),
+        )
+        .execute(c)
+    })
+    .await
+}
+
+/// Returns the ActivityPub IDs of the actors that have blocked the specified actor
+pub async fn get_blocker_ap_ids<C: DbRunner>(
+    conn: &C,
+    blocked_ap_id: String,
+) -> Result<Vec<String>> {
+    conn.run(move |c| {
+        blocks::table
+            .filter(blocks::blocked_ap_id.eq(blocked_ap_id))
+            .select(blocks::blocker_ap_id)
+            .get_results(c)
+    })
+    .await
+}
+
+/// Returns true if any of the specified actors has blocked the actor
+pub async fn is_blocked_by_any<C: DbRunner>(
+    conn: &C,
+    blocked_ap_id: String,
+    blocker_ap_ids: Vec<String>,
+) -> Result<bool> {
+    if blocker_ap_ids.is_empty() {
+        return Ok(false);
+    }
+
+    conn.run(move |c| {
+        diesel::select(diesel::dsl::exists(
+            blocks::table
+                .filter(blocks::blocked_ap_id.eq(blocked_ap_id))
+                .filter(blocks::blocker_ap_id.eq_any(blocker_ap_ids)),
+        ))
+        .get_result(c)
+    })
+    .await
+}
+
+/// Returns the ActivityPub IDs of the actors on the other side of a block involving the
+/// specified actor, in either direction. Content from these actors is hidden from the actor.
+pub async fn get_hidden_actor_ap_ids<C: DbRunner>(conn: &C, ap_id: String) -> Result<Vec<String>> {
+    conn.run(move |c| {
+        blocks::table
+            .filter(
+                blocks::blocker_ap_id
+                    .eq(ap_id.clone())
+                    .or(blocks::blocked_ap_id.eq(ap_id.clone())),
+            )
+            .select((blocks::blocker_ap_id, blocks::blocked_ap_id))
+            .get_results::<(String, String)>(c)
+            .map(|pairs| {
+                pairs
+                    .into_iter()
+                    .map(|(blocker, blocked)| if blocker == ap_id { blocked } else { blocker })
+                    .collect()
+            })
+    })
+    .await
+}
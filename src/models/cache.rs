@@ -1,5 +1,7 @@
 use crate::db::runner::DbRunner;
+use crate::helper::get_domain_from_url;
 use crate::models::actors::guaranteed_actor;
+use crate::models::instances::{is_domain_permitted, is_media_rejected};
 use crate::retriever::signed_get;
 use crate::schema::cache;
 use anyhow::{anyhow, Context, Result}; // Add Context
@@ -67,8 +69,21 @@ impl Cache for ApCollection {
     }
 }
 
+// Media attached to objects by authors on domains with the reject_media moderation level
+// isn't cached, wherever it is hosted
+async fn is_author_media_rejected<C: DbRunner>(conn: &C, author_ap_id: String) -> bool {
+    match get_domain_from_url(author_ap_id) {
+        Some(domain_name) => is_media_rejected(conn, domain_name).await,
+        None => false,
+    }
+}
+
 impl Cache for ApNote {
     async fn cache<C: DbRunner + Send + Sync>(&self, conn: &C) -> &Self {
+        if is_author_media_rejected(conn, self.attributed_to.to_string()).await {
+            return self;
+        }
+
         log::debug!("Checking for attachments");
         for attachment in self.attachment.multiple() {
             log::debug!("{attachment}");
@@ -101,6 +116,10 @@ impl Cache for ApNote {
 
 impl Cache for ApArticle {
     async fn cache<C: DbRunner + Send + Sync>(&self, conn: &C) -> &Self {
+        if is_author_media_rejected(conn, self.attributed_to.to_string()).await {
+            return self;
+        }
+
         log::debug!("Checking for attachments");
         for attachment in self.attachment.multiple() {
             log::debug!("{attachment}");
@@ -152,6 +171,10 @@ impl Cache for ApObject {
 
 impl Cache for ApQuestion {
     async fn cache<C: DbRunner + Send + Sync>(&self, conn: &C) -> &Self {
+        if is_author_media_rejected(conn, self.attributed_to.to_string()).await {
+            return self;
+        }
+
         if let MaybeMultiple::Multiple(attachments) = self.attachment.clone() {
             for attachment in attachments {
                 cache_content(conn, attachment.clone().try_into()).await;
@@ -361,6 +384,12 @@ impl From<ApImage> for NewCacheItem {
 
 impl Cache for ApActor {
     async fn cache<C: DbRunner + Send + Sync>(&self, conn: &C) -> &Self {
+        if let Some(id) = &self.id {
+            if is_author_media_rejected(conn, id.to_string()).await {
+                return self;
+            }
+        }
+
         if let MaybeMultiple::Multiple(tags) = self.tag.clone() {
             for tag in tags {
                 cache_content(conn, tag.try_into()).await;
@@ -553,6 +582,17 @@ pub async fn download_image<C: DbRunner + Send + Sync>(
     const MAX_ATTEMPTS: u32 = 3; // Total number of attempts
 
     log::debug!("Downloading image: {}", cache_item.url);
+
+    let domain_name = get_domain_from_url(cache_item.url.clone())
+        .ok_or(anyhow!("Invalid URL: {}", cache_item.url))?;
+    if !is_domain_permitted(conn, domain_name.clone()).await {
+        return Err(anyhow!("Federation with {domain_name} is not permitted"));
+    }
+
+    if is_media_rejected(conn, domain_name.clone()).await {
+        return Err(anyhow!("Media from {domain_name} is rejected"));
+    }
+
     let signing_actor = guaranteed_actor(conn, profile).await; // Resolve actor for signing once
 
     // Helper to save media data from a response to a cache file
@@ -588,7 +628,7 @@ pub async fn download_image<C: DbRunner + Send + Sync>(
     for attempt_num in 1..=MAX_ATTEMPTS {
         // --- Primary (reqwest) attempt ---
         let primary_result: Result<NewCacheItem, PrimaryAttemptFailure> = async {
-            match signed_get(signing_actor.clone(), cache_item.url.clone(), true).await {
+            match signed_get(conn, signing_actor.clone(), cache_item.url.clone(), true).await {
                 Ok(mut response) => {
                     let status = response.status();
                     log::debug!(
@@ -709,7 +749,7 @@ pub async fn download_image<C: DbRunner + Send + Sync>(
                         } else {
                             // Fallback: make a fresh request without the enhanced headers
                             log::debug!("Attempting fallback with basic request for {}", cache_item.url);
-                            let fallback_response = signed_get(signing_actor.clone(), cache_item.url.clone(),
+                            let fallback_response = signed_get(conn, signing_actor.clone(), cache_item.url.clone(),
                                                                false).await
                                 .context("Failed to get fallback response")
                                 .map_err(PrimaryAttemptFailure::NetworkOrOther)?;
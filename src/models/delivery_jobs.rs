@@ -0,0 +1,264 @@
+use crate::db::runner::DbRunner;
+use crate::schema::delivery_jobs;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Serialize, Deserialize, Default, Clone, Eq, PartialEq,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::DeliveryStatus"]
+pub enum DeliveryStatus {
+    #[default]
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[derive(Serialize, Deserialize, Insertable, Default, Debug, Clone)]
+#[diesel(table_name = delivery_jobs)]
+pub struct NewDeliveryJob {
+    pub activity_ap_id: String,
+    pub inbox: String,
+    pub sender_id: i32,
+    pub body: String,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Identifiable, Queryable, AsChangeset, Serialize, Clone, Default, Debug)]
+#[diesel(table_name = delivery_jobs)]
+pub struct DeliveryJob {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub activity_ap_id: String,
+    pub inbox: String,
+    pub sender_id: i32,
+    pub body: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub deferred_at: Option<DateTime<Utc>>,
+}
+
+// Keeps each insert well under the 65,535 bind parameters Postgres allows per statement
+const DELIVERY_JOB_INSERT_CHUNK: usize = 1000;
+
+/// Creates one job per (activity, inbox). The next_attempt_at on each job should be set
+/// to a lease expiration so that the retry task doesn't pick up a job while the initial
+/// delivery is in flight. Sending an activity again resets any existing job for it.
+///
+/// The jobs must not repeat an (activity, inbox) pair: Postgres rejects an upsert that
+/// would update the same row twice.
+pub async fn create_delivery_jobs<C: DbRunner>(
+    conn: &C,
+    jobs: Vec<NewDeliveryJob>,
+) -> Result<Vec<DeliveryJob>> {
+    conn.run(move |c| {
+        c.transaction(|c| {
+            let mut created = Vec::with_capacity(jobs.len());
+
+            for chunk in jobs.chunks(DELIVERY_JOB_INSERT_CHUNK) {
+                created.extend(
+                    diesel::insert_into(delivery_jobs::table)
+                        .values(chunk)
+                        .on_conflict((delivery_jobs::activity_ap_id, delivery_jobs::inbox))
+                        .do_update()
+                        .set((
+                            delivery_jobs::body.eq(excluded(delivery_jobs::body)),
+                            delivery_jobs::status.eq(DeliveryStatus::Pending),
+                            delivery_jobs::attempts.eq(0),
+                            delivery_jobs::next_attempt_at
+                                .eq(excluded(delivery_jobs::next_attempt_at)),
+                            delivery_jobs::last_error.eq(None::<String>),
+                            delivery_jobs::deferred_at.eq(None::<DateTime<Utc>>),
+                        ))
+                        .get_results::<DeliveryJob>(c)?,
+                );
+            }
+
+            Ok(created)
+        })
+    })
+    .await
+}
+
+/// Claims up to limit pending jobs that are due for another attempt. Claimed jobs have
+/// their next_attempt_at pushed out to lease_until, so a crashed worker's jobs are picked
+/// up again once the lease expires. A claimed job is no longer deferred.
+pub async fn claim_due_delivery_jobs<C: DbRunner>(
+    conn: &C,
+    limit: i64,
+    lease_until: DateTime<Utc>,
+) -> Result<Vec<DeliveryJob>> {
+    conn.run(move |c| {
+        c.transaction(|c| {
+            let ids = delivery_jobs::table
+                .select(delivery_jobs::id)
+                .filter(delivery_jobs::status.eq(DeliveryStatus::Pending))
+                .filter(delivery_jobs::next_attempt_at.le(Utc::now()))
+                .order(delivery_jobs::next_attempt_at.asc())
+                .limit(limit)
+                .for_update()
+                .skip_locked()
+                .load::<i32>(c)?;
+
+            diesel::update(delivery_jobs::table.filter(delivery_jobs::id.eq_any(ids)))
+                .set((
+                    delivery_jobs::next_attempt_at.eq(lease_until),
+                    delivery_jobs::deferred_at.eq(None::<DateTime<Utc>>),
+                ))
+                .get_results::<DeliveryJob>(c)
+        })
+    })
+    .await
+}
+
+pub async fn mark_delivery_delivered<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    status_code: i32,
+) -> Result<DeliveryJob> {
+    conn.run(move |c| {
+        diesel::update(delivery_jobs::table.find(id))
+            .set((
+                delivery_jobs::status.eq(DeliveryStatus::Delivered),
+                delivery_jobs::attempts.eq(delivery_jobs::attempts + 1),
+                delivery_jobs::last_attempt_at.eq(Utc::now()),
+                delivery_jobs::last_status_code.eq(status_code),
+                delivery_jobs::last_error.eq(None::<String>),
+            ))
+            .get_result::<DeliveryJob>(c)
+    })
+    .await
+}
+
+/// Records a failed attempt. When retry_at is provided the job remains pending until
+/// then; otherwise it is marked as permanently failed.
+pub async fn mark_delivery_failed<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    status_code: Option<i32>,
+    error: Option<String>,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<DeliveryJob> {
+    conn.run(move |c| {
+        let (status, next_attempt_at) = match retry_at {
+            Some(retry_at) => (DeliveryStatus::Pending, retry_at),
+            None => (DeliveryStatus::Failed, Utc::now()),
+        };
+
+        diesel::update(delivery_jobs::table.find(id))
+            .set((
+                delivery_jobs::status.eq(status),
+                delivery_jobs::attempts.eq(delivery_jobs::attempts + 1),
+                delivery_jobs::next_attempt_at.eq(next_attempt_at),
+                delivery_jobs::last_attempt_at.eq(Utc::now()),
+                delivery_jobs::last_status_code.eq(status_code),
+                delivery_jobs::last_error.eq(error),
+            ))
+            .get_result::<DeliveryJob>(c)
+    })
+    .await
+}
+
+/// Pushes a job's next attempt out to `until` without counting an attempt against it
+pub async fn defer_delivery_job<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    until: DateTime<Utc>,
+    reason: String,
+) -> Result<DeliveryJob> {
+    conn.run(move |c| {
+        diesel::update(delivery_jobs::table.find(id))
+            .set((
+                delivery_jobs::next_attempt_at.eq(until),
+                delivery_jobs::last_error.eq(Some(reason)),
+                delivery_jobs::deferred_at.eq(Utc::now()),
+            ))
+            .get_result::<DeliveryJob>(c)
+    })
+    .await
+}
+
+/// Makes the deferred jobs for inboxes on a domain due immediately, e.g. once an instance
+/// that was unreachable answers again. Jobs leased to a delivery attempt in flight are
+/// left alone.
+pub async fn resume_delivery_jobs_by_domain<C: DbRunner>(
+    conn: &C,
+    domain: String,
+) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::update(
+            delivery_jobs::table
+                .filter(delivery_jobs::status.eq(DeliveryStatus::Pending))
+                .filter(delivery_jobs::deferred_at.is_not_null())
+                .filter(
+                    delivery_jobs::inbox
+                        .like(format!("https://{}/%", escape_like(&domain)))
+                        .escape('\\'),
+                ),
+        )
+        .set((
+            delivery_jobs::next_attempt_at.eq(Utc::now()),
+            delivery_jobs::deferred_at.eq(None::<DateTime<Utc>>),
+        ))
+        .execute(c)
+    })
+    .await
+}
+
+// Domains are matched literally; LIKE would otherwise treat _ and % as wildcards
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Deletes delivered and permanently failed jobs last touched before the cutoff.
+pub async fn prune_delivery_jobs<C: DbRunner>(conn: &C, cutoff: DateTime<Utc>) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::delete(
+            delivery_jobs::table
+                .filter(delivery_jobs::status.ne(DeliveryStatus::Pending))
+                .filter(delivery_jobs::updated_at.lt(cutoff)),
+        )
+        .execute(c)
+    })
+    .await
+}
+
+/// Deletes the pending jobs for inboxes on a domain, so that nothing more is sent to it
+/// once it has been blocked.
+pub async fn delete_pending_delivery_jobs_by_domain_pattern<C: DbRunner>(
+    conn: &C,
+    domain_pattern: String,
+) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::delete(
+            delivery_jobs::table
+                .filter(delivery_jobs::status.eq(DeliveryStatus::Pending))
+                .filter(
+                    delivery_jobs::inbox
+                        .like(format!("https://{}/%", escape_like(&domain_pattern)))
+                        .escape('\\'),
+                ),
+        )
+        .execute(c)
+    })
+    .await
+}
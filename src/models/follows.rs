@@ -229,6 +229,30 @@ pub async fn get_follow<C: DbRunner>(
     conn.run(operation).await
 }
 
+/// Points an existing follow at a new leader, as when the leader has moved accounts. The
+/// follow is pending again until the new leader accepts `follow_activity_ap_id`.
+pub async fn move_follow<C: DbRunner>(
+    conn: &C,
+    follower_ap_id: String,
+    leader_ap_id: String,
+    new_leader: Actor,
+    follow_activity_ap_id: String,
+) -> Result<Follow> {
+    let operation = move |c: &mut diesel::PgConnection| {
+        use diesel::sql_types::{Integer, Text};
+
+        sql_query("UPDATE follows SET leader_ap_id = $1, leader_actor_id = $2, follow_activity_ap_id = $3, accepted = 'false', accept_activity_ap_id = NULL, rejected = 'false', reject_activity_ap_id = NULL WHERE follower_ap_id = $4 AND leader_ap_id = $5 RETURNING *")
+            .bind::<Text, _>(new_leader.as_id)
+            .bind::<Integer, _>(new_leader.id)
+            .bind::<Text, _>(follow_activity_ap_id)
+            .bind::<Text, _>(follower_ap_id)
+            .bind::<Text, _>(leader_ap_id)
+            .get_result(c)
+    };
+
+    conn.run(operation).await
+}
+
 pub async fn delete_follow<C: DbRunner>(
     conn: &C,
     follower_ap_id: String,
@@ -270,6 +294,22 @@ pub async fn get_leaders_by_follower_actor_id<C: DbRunner>(
     conn.run(operation).await
 }
 
+/// Returns every follow made by the actor, whether pending, accepted, or rejected
+pub async fn get_follows_by_follower_actor_id<C: DbRunner>(
+    conn: &C,
+    follower_actor_id: i32,
+) -> Result<Vec<(Follow, Option<Actor>)>> {
+    let operation = move |c: &mut diesel::PgConnection| {
+        use diesel::sql_types::Integer;
+
+        sql_query("SELECT f.*, a.* FROM follows f LEFT JOIN actors a ON (f.leader_ap_id = a.as_id) WHERE f.follower_actor_id = $1 ORDER BY f.created_at DESC")
+            .bind::<Integer, _>(follower_actor_id)
+            .get_results::<(Follow, Option<Actor>)>(c)
+    };
+
+    conn.run(operation).await
+}
+
 pub async fn get_leader_count_by_follower_actor_id<C: DbRunner>(
     conn: &C,
     follower_actor_id: i32,
@@ -1,5 +1,6 @@
 use crate::db::runner::DbRunner;
 use crate::db::DbType;
+use crate::nodeinfo::NodeInfo;
 use crate::schema::instances;
 use crate::schema::instances::dsl;
 use chrono::{DateTime, Utc};
@@ -10,6 +11,8 @@ use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
 use jdt_activity_pub::ApAddress;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
 
 // --- Sort-related types ---
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,6 +20,9 @@ pub enum SortField {
     DomainName,
     Blocked,
     LastMessageAt,
+    Software,
+    Users,
+    CrawledAt,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -32,6 +38,59 @@ pub struct SortParam {
 }
 // --- End of Sort-related types ---
 
+/// The HTTP signature format used when signing requests to an instance: RFC 9421
+/// `Signature-Input`/`Signature`, or the older draft-cavage `Signature` header
+#[derive(diesel_derive_enum::DbEnum, Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[ExistingTypePath = "crate::schema::sql_types::SignatureScheme"]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    Rfc9421,
+    Cavage,
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// A moderation level applied to a domain. `Silence` hides the domain from search and from
+/// the public timelines of users who don't follow its actors, `RejectMedia` stops its media
+/// from being cached, `RejectReports` drops its Flags, and `Suspend` blocks it entirely.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationLevel {
+    Silence,
+    RejectMedia,
+    RejectReports,
+    Suspend,
+}
+
+impl fmt::Display for ModerationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModerationLevel::Silence => write!(f, "silence"),
+            ModerationLevel::RejectMedia => write!(f, "reject_media"),
+            ModerationLevel::RejectReports => write!(f, "reject_reports"),
+            ModerationLevel::Suspend => write!(f, "suspend"),
+        }
+    }
+}
+
+impl TryFrom<String> for ModerationLevel {
+    type Error = anyhow::Error;
+
+    fn try_from(level: String) -> Result<Self, Self::Error> {
+        match level.to_lowercase().replace('-', "_").as_str() {
+            "silence" => Ok(ModerationLevel::Silence),
+            "reject_media" => Ok(ModerationLevel::RejectMedia),
+            "reject_reports" => Ok(ModerationLevel::RejectReports),
+            "suspend" => Ok(ModerationLevel::Suspend),
+            _ => Err(anyhow::anyhow!("unknown moderation level: {level}")),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Insertable, AsChangeset, Default, Debug, Clone)]
 #[diesel(table_name = instances)]
 pub struct NewInstance {
@@ -53,6 +112,41 @@ pub struct Instance {
     pub blocked: bool,
     pub last_message_at: DateTime<Utc>,
     pub shared_inbox: Option<String>,
+    pub delivery_success_count: i32,
+    pub delivery_failure_count: i32,
+    pub consecutive_failures: i32,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub unreachable: bool,
+    pub unreachable_since: Option<DateTime<Utc>>,
+    pub last_probed_at: Option<DateTime<Utc>>,
+    pub signature_scheme: Option<SignatureScheme>,
+    pub allowed: bool,
+    pub silenced: bool,
+    pub reject_media: bool,
+    pub reject_reports: bool,
+    pub public_comment: Option<String>,
+    #[serde(skip_serializing)]
+    pub private_comment: Option<String>,
+    pub software_name: Option<String>,
+    pub software_version: Option<String>,
+    pub user_count: Option<i64>,
+    pub active_user_count: Option<i64>,
+    pub open_registrations: Option<bool>,
+    pub crawled_at: Option<DateTime<Utc>>,
+}
+
+impl Instance {
+    pub fn moderation_levels(&self) -> Vec<ModerationLevel> {
+        [
+            (self.silenced, ModerationLevel::Silence),
+            (self.reject_media, ModerationLevel::RejectMedia),
+            (self.reject_reports, ModerationLevel::RejectReports),
+            (self.blocked, ModerationLevel::Suspend),
+        ]
+        .into_iter()
+        .filter_map(|(applied, level)| applied.then_some(level))
+        .collect()
+    }
 }
 
 pub async fn create_or_update_instance<C: DbRunner>(
@@ -87,6 +181,7 @@ pub async fn get_instance_inboxes<C: DbRunner>(conn: &C) -> Result<Vec<ApAddress
         let cutoff = Utc::now().naive_utc() - chrono::Duration::days(14);
         instances::table
             .filter(instances::blocked.eq(false))
+            .filter(instances::unreachable.eq(false))
             .filter(instances::shared_inbox.is_not_null())
             .filter(instances::last_message_at.gt(cutoff))
             .select(instances::shared_inbox.assume_not_null())
@@ -142,6 +237,30 @@ pub async fn get_all_instances_paginated<C: DbRunner>(
                             SortDirection::Asc => query.then_order_by(dsl::last_message_at.asc()),
                             SortDirection::Desc => query.then_order_by(dsl::last_message_at.desc()),
                         },
+                        SortField::Software => match p.direction {
+                            SortDirection::Asc => {
+                                query.then_order_by(dsl::software_name.asc().nulls_last())
+                            }
+                            SortDirection::Desc => {
+                                query.then_order_by(dsl::software_name.desc().nulls_last())
+                            }
+                        },
+                        SortField::Users => match p.direction {
+                            SortDirection::Asc => {
+                                query.then_order_by(dsl::user_count.asc().nulls_last())
+                            }
+                            SortDirection::Desc => {
+                                query.then_order_by(dsl::user_count.desc().nulls_last())
+                            }
+                        },
+                        SortField::CrawledAt => match p.direction {
+                            SortDirection::Asc => {
+                                query.then_order_by(dsl::crawled_at.asc().nulls_last())
+                            }
+                            SortDirection::Desc => {
+                                query.then_order_by(dsl::crawled_at.desc().nulls_last())
+                            }
+                        },
                     };
                 }
             } else {
@@ -231,6 +350,442 @@ pub async fn get_blocked_instances<C: DbRunner>(conn: &C) -> Vec<Instance> {
     .unwrap_or(vec![])
 }
 
+/// Adds a domain to or removes it from the allowlist used in ALLOWLIST_MODE. A domain that
+/// has not been seen yet is recorded so that it can be allowed before it first federates.
+pub async fn set_allow_status<C: DbRunner>(
+    conn: &C,
+    domain_name_val: String,
+    should_be_allowed: bool,
+) -> Result<Instance, anyhow::Error> {
+    conn.run(move |c| {
+        diesel::insert_into(dsl::instances)
+            .values((
+                dsl::domain_name.eq(domain_name_val),
+                dsl::allowed.eq(should_be_allowed),
+            ))
+            .on_conflict(dsl::domain_name)
+            .do_update()
+            .set((
+                dsl::allowed.eq(should_be_allowed),
+                dsl::updated_at.eq(Utc::now()),
+            ))
+            .get_result::<Instance>(c)
+    })
+    .await
+}
+
+pub async fn get_allowed_instances<C: DbRunner>(conn: &C) -> Vec<Instance> {
+    conn.run(move |c| {
+        instances::table
+            .filter(instances::allowed.eq(true))
+            .get_results::<Instance>(c)
+    })
+    .await
+    .unwrap_or(vec![])
+}
+
+/// Returns whether this server may federate with `domain_name_val`. Blocked domains are
+/// refused, and in ALLOWLIST_MODE so is any domain that isn't allowed. The check fails
+/// closed: a domain is refused if its status can't be determined.
+pub async fn is_domain_permitted<C: DbRunner>(conn: &C, domain_name_val: String) -> bool {
+    if domain_name_val == *crate::SERVER_NAME {
+        return true;
+    }
+
+    match get_instance_by_domain_name(conn, domain_name_val).await {
+        Ok(Some(instance)) => !instance.blocked && (instance.allowed || !*crate::ALLOWLIST_MODE),
+        Ok(None) => !*crate::ALLOWLIST_MODE,
+        Err(e) => {
+            log::error!("Failed to retrieve instance: {e}");
+            false
+        }
+    }
+}
+
+/// Returns the allowed domains when ALLOWLIST_MODE is set, or None when it isn't
+pub async fn get_allowed_domains<C: DbRunner>(
+    conn: &C,
+) -> Result<Option<HashSet<String>>, anyhow::Error> {
+    if !*crate::ALLOWLIST_MODE {
+        return Ok(None);
+    }
+
+    let domains = conn
+        .run(move |c| {
+            instances::table
+                .filter(instances::allowed.eq(true))
+                .filter(instances::blocked.eq(false))
+                .select(instances::domain_name)
+                .get_results::<String>(c)
+        })
+        .await?;
+
+    Ok(Some(domains.into_iter().collect()))
+}
+
+/// Replaces the moderation levels applied to a domain, recording the domain if it hasn't
+/// been seen yet. Comments that aren't given are left as they are; an empty comment clears
+/// the one recorded.
+pub async fn set_moderation<C: DbRunner>(
+    conn: &C,
+    domain_name_val: String,
+    levels: Vec<ModerationLevel>,
+    public_comment: Option<String>,
+    private_comment: Option<String>,
+) -> Result<Instance, anyhow::Error> {
+    let existing = get_instance_by_domain_name(conn, domain_name_val.clone()).await?;
+    let comment = |given: Option<String>, recorded: Option<String>| match given {
+        Some(given) if given.is_empty() => None,
+        Some(given) => Some(given),
+        None => recorded,
+    };
+    let public_comment = comment(
+        public_comment,
+        existing
+            .as_ref()
+            .and_then(|instance| instance.public_comment.clone()),
+    );
+    let private_comment = comment(
+        private_comment,
+        existing
+            .as_ref()
+            .and_then(|instance| instance.private_comment.clone()),
+    );
+
+    let values = (
+        dsl::blocked.eq(levels.contains(&ModerationLevel::Suspend)),
+        dsl::silenced.eq(levels.contains(&ModerationLevel::Silence)),
+        dsl::reject_media.eq(levels.contains(&ModerationLevel::RejectMedia)),
+        dsl::reject_reports.eq(levels.contains(&ModerationLevel::RejectReports)),
+        dsl::public_comment.eq(public_comment),
+        dsl::private_comment.eq(private_comment),
+    );
+
+    conn.run(move |c| {
+        diesel::insert_into(dsl::instances)
+            .values((dsl::domain_name.eq(domain_name_val), values.clone()))
+            .on_conflict(dsl::domain_name)
+            .do_update()
+            .set((values, dsl::updated_at.eq(Utc::now())))
+            .get_result::<Instance>(c)
+    })
+    .await
+}
+
+/// Returns the instances with any moderation level applied
+pub async fn get_moderated_instances<C: DbRunner>(conn: &C) -> Vec<Instance> {
+    conn.run(move |c| {
+        instances::table
+            .filter(
+                instances::blocked
+                    .eq(true)
+                    .or(instances::silenced.eq(true))
+                    .or(instances::reject_media.eq(true))
+                    .or(instances::reject_reports.eq(true)),
+            )
+            .order(instances::domain_name.asc())
+            .get_results::<Instance>(c)
+    })
+    .await
+    .unwrap_or(vec![])
+}
+
+pub async fn get_blocked_domains<C: DbRunner>(conn: &C) -> Result<Vec<String>, anyhow::Error> {
+    conn.run(move |c| {
+        instances::table
+            .filter(instances::blocked.eq(true))
+            .select(instances::domain_name)
+            .get_results::<String>(c)
+    })
+    .await
+}
+
+pub async fn get_silenced_domains<C: DbRunner>(conn: &C) -> Result<Vec<String>, anyhow::Error> {
+    conn.run(move |c| {
+        instances::table
+            .filter(instances::silenced.eq(true))
+            .select(instances::domain_name)
+            .get_results::<String>(c)
+    })
+    .await
+}
+
+/// Returns the domains whose content is kept out of search results: suspended and
+/// silenced domains
+pub async fn get_hidden_domains<C: DbRunner>(conn: &C) -> Result<Vec<String>, anyhow::Error> {
+    conn.run(move |c| {
+        instances::table
+            .filter(instances::blocked.eq(true).or(instances::silenced.eq(true)))
+            .select(instances::domain_name)
+            .get_results::<String>(c)
+    })
+    .await
+}
+
+/// Returns whether media from `domain_name_val` is rejected, as it is for suspended domains
+pub async fn is_media_rejected<C: DbRunner>(conn: &C, domain_name_val: String) -> bool {
+    get_instance_by_domain_name(conn, domain_name_val)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|instance| instance.reject_media || instance.blocked)
+}
+
+pub async fn is_reports_rejected<C: DbRunner>(conn: &C, domain_name_val: String) -> bool {
+    get_instance_by_domain_name(conn, domain_name_val)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|instance| instance.reject_reports || instance.blocked)
+}
+
+/// Records the outcome of a delivery to an instance. A delivery that received any
+/// response other than a server error resets the failure streak; once the streak reaches
+/// unreachable_threshold the instance is marked unreachable and skipped for delivery.
+/// Domains that have never contacted us are not tracked.
+pub async fn record_instance_delivery<C: DbRunner>(
+    conn: &C,
+    domain_name_val: String,
+    delivered: bool,
+    reachable: bool,
+    unreachable_threshold: i32,
+) -> Result<Option<Instance>, anyhow::Error> {
+    conn.run(move |c| {
+        c.transaction(|c| {
+            let Some(instance) = instances::table
+                .filter(instances::domain_name.eq(domain_name_val))
+                .first::<Instance>(c)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            let target = instances::table.find(instance.id);
+
+            if delivered {
+                diesel::update(target)
+                    .set((
+                        instances::delivery_success_count.eq(instances::delivery_success_count + 1),
+                        instances::last_success_at.eq(Utc::now()),
+                    ))
+                    .execute(c)?;
+            } else {
+                diesel::update(target)
+                    .set(
+                        instances::delivery_failure_count.eq(instances::delivery_failure_count + 1),
+                    )
+                    .execute(c)?;
+            }
+
+            if reachable {
+                diesel::update(target)
+                    .set(instances::consecutive_failures.eq(0))
+                    .get_result::<Instance>(c)
+                    .map(Some)
+            } else {
+                let instance = diesel::update(target)
+                    .set(instances::consecutive_failures.eq(instances::consecutive_failures + 1))
+                    .get_result::<Instance>(c)?;
+
+                if !instance.unreachable && instance.consecutive_failures >= unreachable_threshold {
+                    diesel::update(target)
+                        .set((
+                            instances::unreachable.eq(true),
+                            instances::unreachable_since.eq(Utc::now()),
+                        ))
+                        .get_result::<Instance>(c)
+                        .map(Some)
+                } else {
+                    Ok(Some(instance))
+                }
+            }
+        })
+    })
+    .await
+}
+
+/// Records the signature scheme an instance accepted a request signed with. Like delivery
+/// health, this is only tracked for domains that have contacted us.
+pub async fn record_instance_signature_scheme<C: DbRunner>(
+    conn: &C,
+    domain_name_val: String,
+    scheme: SignatureScheme,
+) -> Result<usize, anyhow::Error> {
+    conn.run(move |c| {
+        diesel::update(
+            instances::table
+                .filter(instances::domain_name.eq(domain_name_val))
+                .filter(
+                    instances::signature_scheme
+                        .is_null()
+                        .or(instances::signature_scheme.ne(scheme)),
+                ),
+        )
+        .set(instances::signature_scheme.eq(scheme))
+        .execute(c)
+    })
+    .await
+}
+
+/// Returns the signature schemes recorded for the instances among `domains`
+pub async fn get_signature_schemes_by_domain<C: DbRunner>(
+    conn: &C,
+    domains: Vec<String>,
+) -> Result<Vec<(String, SignatureScheme)>, anyhow::Error> {
+    conn.run(move |c| {
+        instances::table
+            .filter(instances::domain_name.eq_any(domains))
+            .filter(instances::signature_scheme.is_not_null())
+            .select((instances::domain_name, instances::signature_scheme))
+            .get_results::<(String, Option<SignatureScheme>)>(c)
+    })
+    .await
+    .map(|schemes| {
+        schemes
+            .into_iter()
+            .filter_map(|(domain, scheme)| scheme.map(|scheme| (domain, scheme)))
+            .collect()
+    })
+}
+
+/// Returns unreachable instances that have not been probed since the cutoff, leaving out
+/// those this server doesn't federate with.
+pub async fn get_unreachable_instances_for_probe<C: DbRunner>(
+    conn: &C,
+    probed_before: DateTime<Utc>,
+) -> Result<Vec<Instance>, anyhow::Error> {
+    conn.run(move |c| {
+        let mut query = instances::table
+            .filter(instances::unreachable.eq(true))
+            .filter(instances::blocked.eq(false))
+            .filter(
+                instances::last_probed_at
+                    .is_null()
+                    .or(instances::last_probed_at.lt(probed_before)),
+            )
+            .into_boxed();
+
+        // In ALLOWLIST_MODE only allowed instances are contacted
+        if *crate::ALLOWLIST_MODE {
+            query = query.filter(instances::allowed.eq(true));
+        }
+
+        query.get_results::<Instance>(c)
+    })
+    .await
+}
+
+pub async fn get_unreachable_domains<C: DbRunner>(
+    conn: &C,
+) -> Result<HashSet<String>, anyhow::Error> {
+    let domains = conn
+        .run(move |c| {
+            instances::table
+                .filter(instances::unreachable.eq(true))
+                .select(instances::domain_name)
+                .get_results::<String>(c)
+        })
+        .await?;
+
+    Ok(domains.into_iter().collect())
+}
+
+/// Records a probe of an unreachable instance, reviving it if the probe succeeded.
+pub async fn record_instance_probe<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    reachable: bool,
+) -> Result<Instance, anyhow::Error> {
+    conn.run(move |c| {
+        let target = instances::table.find(id);
+
+        if reachable {
+            diesel::update(target)
+                .set((
+                    instances::last_probed_at.eq(Utc::now()),
+                    instances::unreachable.eq(false),
+                    instances::unreachable_since.eq(None::<DateTime<Utc>>),
+                    instances::consecutive_failures.eq(0),
+                ))
+                .get_result::<Instance>(c)
+        } else {
+            diesel::update(target)
+                .set(instances::last_probed_at.eq(Utc::now()))
+                .get_result::<Instance>(c)
+        }
+    })
+    .await
+}
+
+/// Returns instances whose NodeInfo hasn't been crawled since the cutoff, least recently
+/// crawled first. Blocked and unreachable instances are skipped, as are instances that
+/// aren't allowed in ALLOWLIST_MODE.
+pub async fn get_instances_for_crawl<C: DbRunner>(
+    conn: &C,
+    crawled_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Instance>, anyhow::Error> {
+    conn.run(move |c| {
+        let mut query = instances::table
+            .filter(instances::blocked.eq(false))
+            .filter(instances::unreachable.eq(false))
+            .filter(instances::domain_name.ne((*crate::SERVER_NAME).clone()))
+            .filter(
+                instances::crawled_at
+                    .is_null()
+                    .or(instances::crawled_at.lt(crawled_before)),
+            )
+            .into_boxed();
+
+        // In ALLOWLIST_MODE only allowed instances are contacted
+        if *crate::ALLOWLIST_MODE {
+            query = query.filter(instances::allowed.eq(true));
+        }
+
+        query
+            .order(instances::crawled_at.asc().nulls_first())
+            .limit(limit)
+            .get_results::<Instance>(c)
+    })
+    .await
+}
+
+/// Records a NodeInfo crawl of an instance. A failed crawl only updates crawled_at, so
+/// that what was learned from an earlier crawl is kept.
+pub async fn record_instance_crawl<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    nodeinfo: Option<NodeInfo>,
+) -> Result<Instance, anyhow::Error> {
+    let values = nodeinfo.map(|nodeinfo| {
+        let software_version = Some(nodeinfo.software.version.clone())
+            .filter(|software_version| !software_version.is_empty());
+
+        (
+            instances::software_name.eq(Some(nodeinfo.software.name.to_lowercase())),
+            instances::software_version.eq(software_version),
+            instances::user_count.eq(nodeinfo.usage.users.total),
+            instances::active_user_count.eq(nodeinfo.usage.users.active_month),
+            instances::open_registrations.eq(Some(nodeinfo.open_registrations)),
+            instances::json.eq(serde_json::to_value(&nodeinfo).ok()),
+        )
+    });
+
+    conn.run(move |c| {
+        let target = instances::table.find(id);
+
+        match values {
+            Some(values) => diesel::update(target)
+                .set((values, instances::crawled_at.eq(Utc::now())))
+                .get_result::<Instance>(c),
+            None => diesel::update(target)
+                .set(instances::crawled_at.eq(Utc::now()))
+                .get_result::<Instance>(c),
+        }
+    })
+    .await
+}
+
 pub async fn create_or_update_instance_axum(
     conn: &DbConnection,
     instance: NewInstance,
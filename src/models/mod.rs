@@ -3,15 +3,20 @@ use objects::Object;
 
 pub mod activities;
 pub mod actors;
+pub mod blocks;
 pub mod cache;
 pub mod coalesced_activity;
+pub mod delivery_jobs;
 pub mod follows;
 pub mod instances;
 pub mod mls_group_conversations;
 pub mod mls_key_packages;
 pub mod notifications;
 pub mod objects;
+pub mod processing_queue;
 pub mod profiles;
+pub mod reactions;
+pub mod reports;
 pub mod unprocessable;
 pub mod vault;
 pub mod votes;
@@ -592,6 +592,21 @@ impl Object {
 
         false
     }
+
+    /// Every address the object is delivered to: to, cc, bto, bcc and audience
+    pub fn recipients(&self) -> Vec<String> {
+        [
+            &self.as_to,
+            &self.as_cc,
+            &self.as_bto,
+            &self.as_bcc,
+            &self.as_audience,
+        ]
+        .into_iter()
+        .flat_map(|field| MaybeMultiple::<ApAddress>::from(field.clone()).multiple())
+        .map(|address| address.to_string())
+        .collect()
+    }
 }
 
 impl TryFrom<Object> for ApNote {
@@ -817,6 +832,15 @@ pub async fn update_hashtags<C: DbRunner>(conn: &C, id: i32, hashtags: Value) ->
     .await
 }
 
+pub async fn update_tag<C: DbRunner>(conn: &C, id: i32, tag: Option<Value>) -> Result<Object> {
+    conn.run(move |c| {
+        diesel::update(objects::table.filter(objects::id.eq(id)))
+            .set(objects::as_tag.eq(tag))
+            .get_result::<Object>(c)
+    })
+    .await
+}
+
 pub async fn get_object<C: DbRunner>(conn: &C, id: i32) -> Result<Object> {
     conn.run(move |c| objects::table.find(id).first::<Object>(c))
         .await
@@ -831,6 +855,18 @@ pub async fn get_object_by_as_id<C: DbRunner>(conn: &C, as_id: String) -> Result
     .await
 }
 
+pub async fn get_objects_by_as_ids<C: DbRunner>(
+    conn: &C,
+    as_ids: Vec<String>,
+) -> Result<Vec<Object>> {
+    conn.run(move |c| {
+        objects::table
+            .filter(objects::as_id.eq_any(as_ids))
+            .get_results::<Object>(c)
+    })
+    .await
+}
+
 pub async fn get_object_by_uuid<C: DbRunner>(conn: &C, uuid: String) -> Result<Object> {
     conn.run(move |c| {
         objects::table
@@ -898,6 +934,22 @@ pub async fn delete_objects_by_attributed_to<C: DbRunner>(
     conn.run(operation).await
 }
 
+/// Returns the number of posts by local users, not counting deleted or encrypted posts
+pub async fn get_local_post_count<C: DbRunner>(conn: &C) -> Result<i64> {
+    conn.run(move |c| {
+        objects::table
+            .filter(objects::ek_profile_id.is_not_null())
+            .filter(objects::as_type.eq_any(vec![
+                ObjectType::Note,
+                ObjectType::Article,
+                ObjectType::Question,
+            ]))
+            .count()
+            .get_result::<i64>(c)
+    })
+    .await
+}
+
 // pub async fn delete_objects_by_domain_pattern(
 //     conn: Option<&Db>,
 //     domain_pattern: String,
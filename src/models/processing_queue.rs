@@ -0,0 +1,290 @@
+use crate::db::runner::DbRunner;
+use crate::schema::processing_queue;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{self, Debug};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Serialize, Deserialize, Default, Clone, Eq, PartialEq,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::QueueStatus"]
+pub enum QueueStatus {
+    #[default]
+    Pending,
+    Processing,
+    Complete,
+    Dead,
+}
+
+impl fmt::Display for QueueStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[derive(Serialize, Deserialize, Insertable, Default, Debug, Clone)]
+#[diesel(table_name = processing_queue)]
+pub struct NewQueuedActivity {
+    pub ap_id: Option<String>,
+    pub kind: String,
+    pub actor: String,
+    pub raw: Value,
+    pub object_ap_id: Option<String>,
+}
+
+impl From<Value> for NewQueuedActivity {
+    fn from(raw: Value) -> Self {
+        let as_string = |value: Option<&Value>| match value {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Object(o)) => o.get("id").and_then(Value::as_str).map(String::from),
+            _ => None,
+        };
+
+        NewQueuedActivity {
+            ap_id: as_string(raw.get("id")),
+            kind: as_string(raw.get("type")).unwrap_or_default(),
+            actor: as_string(raw.get("actor")).unwrap_or_default(),
+            object_ap_id: as_string(raw.get("object")),
+            raw,
+        }
+    }
+}
+
+#[derive(Identifiable, Queryable, AsChangeset, Serialize, Clone, Default, Debug)]
+#[diesel(table_name = processing_queue)]
+pub struct QueuedActivity {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub ap_id: Option<String>,
+    pub kind: String,
+    pub actor: String,
+    pub raw: Value,
+    pub status: QueueStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub object_ap_id: Option<String>,
+}
+
+/// Persists a verified inbound activity for asynchronous processing. Returns None when
+/// an activity with the same id is already queued (i.e., a remote redelivery). A redelivery
+/// of a dead-lettered activity returns it to the queue with a fresh attempt count.
+pub async fn enqueue_activity<C: DbRunner>(
+    conn: &C,
+    activity: NewQueuedActivity,
+) -> Result<Option<QueuedActivity>> {
+    conn.run(move |c| {
+        let requeue = diesel::insert_into(processing_queue::table)
+            .values(&activity)
+            .on_conflict(processing_queue::ap_id)
+            .do_update()
+            .set((
+                processing_queue::raw.eq(excluded(processing_queue::raw)),
+                processing_queue::status.eq(QueueStatus::Pending),
+                processing_queue::attempts.eq(0),
+                processing_queue::next_attempt_at.eq(Utc::now()),
+                processing_queue::locked_at.eq(None::<DateTime<Utc>>),
+            ));
+
+        diesel::query_dsl::methods::FilterDsl::filter(
+            requeue,
+            processing_queue::status.eq(QueueStatus::Dead),
+        )
+        .get_result::<QueuedActivity>(c)
+        .optional()
+    })
+    .await
+}
+
+diesel::alias!(processing_queue as queued_ahead: QueuedAhead);
+
+/// Claims the oldest pending activity that is due, marking it as processing and
+/// incrementing its attempt count. SKIP LOCKED allows multiple workers to drain the
+/// queue concurrently without contending for the same row.
+///
+/// Activities concerning the same object are processed in the order they arrived, so an
+/// Undo or Delete can't overtake the activity or object it refers to. An activity is not
+/// claimed while one it refers to, or an earlier one with the same object, is processing
+/// or is pending and due; activities waiting out a retry backoff don't hold others back.
+pub async fn claim_next_queued_activity<C: DbRunner>(conn: &C) -> Result<Option<QueuedActivity>> {
+    conn.run(move |c| {
+        c.transaction(|c| {
+            let now = Utc::now();
+
+            let ahead = queued_ahead
+                .filter(
+                    queued_ahead
+                        .field(processing_queue::ap_id)
+                        .eq(processing_queue::object_ap_id)
+                        .or(queued_ahead
+                            .field(processing_queue::object_ap_id)
+                            .eq(processing_queue::object_ap_id)),
+                )
+                .filter(
+                    queued_ahead
+                        .field(processing_queue::id)
+                        .ne(processing_queue::id),
+                )
+                .filter(
+                    queued_ahead
+                        .field(processing_queue::status)
+                        .eq(QueueStatus::Processing)
+                        .or(queued_ahead
+                            .field(processing_queue::status)
+                            .eq(QueueStatus::Pending)
+                            .and(
+                                queued_ahead
+                                    .field(processing_queue::next_attempt_at)
+                                    .le(now),
+                            )
+                            .and(
+                                queued_ahead
+                                    .field(processing_queue::id)
+                                    .lt(processing_queue::id),
+                            )),
+                );
+
+            let next = processing_queue::table
+                .filter(processing_queue::status.eq(QueueStatus::Pending))
+                .filter(processing_queue::next_attempt_at.le(now))
+                .filter(diesel::dsl::not(diesel::dsl::exists(ahead)))
+                .order(processing_queue::id.asc())
+                .for_update()
+                .skip_locked()
+                .first::<QueuedActivity>(c)
+                .optional()?;
+
+            match next {
+                Some(item) => diesel::update(processing_queue::table.find(item.id))
+                    .set((
+                        processing_queue::status.eq(QueueStatus::Processing),
+                        processing_queue::locked_at.eq(Utc::now()),
+                        processing_queue::attempts.eq(processing_queue::attempts + 1),
+                    ))
+                    .get_result::<QueuedActivity>(c)
+                    .map(Some),
+                None => Ok(None),
+            }
+        })
+    })
+    .await
+}
+
+pub async fn mark_queued_activity_complete<C: DbRunner>(
+    conn: &C,
+    id: i32,
+) -> Result<QueuedActivity> {
+    conn.run(move |c| {
+        diesel::update(processing_queue::table.find(id))
+            .set((
+                processing_queue::status.eq(QueueStatus::Complete),
+                processing_queue::locked_at.eq(None::<DateTime<Utc>>),
+                processing_queue::last_error.eq(None::<String>),
+            ))
+            .get_result::<QueuedActivity>(c)
+    })
+    .await
+}
+
+/// Records a processing failure. When retry_at is provided the activity is returned to
+/// the pending state to be picked up again at that time; otherwise it is dead-lettered.
+pub async fn mark_queued_activity_failed<C: DbRunner>(
+    conn: &C,
+    id: i32,
+    error: String,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<QueuedActivity> {
+    conn.run(move |c| {
+        let (status, next_attempt_at) = match retry_at {
+            Some(retry_at) => (QueueStatus::Pending, retry_at),
+            None => (QueueStatus::Dead, Utc::now()),
+        };
+
+        diesel::update(processing_queue::table.find(id))
+            .set((
+                processing_queue::status.eq(status),
+                processing_queue::next_attempt_at.eq(next_attempt_at),
+                processing_queue::locked_at.eq(None::<DateTime<Utc>>),
+                processing_queue::last_error.eq(Some(error)),
+            ))
+            .get_result::<QueuedActivity>(c)
+    })
+    .await
+}
+
+/// Returns activities left in the processing state (e.g., by a crash or restart) to the
+/// pending state so that they will be retried.
+pub async fn release_stale_queued_activities<C: DbRunner>(
+    conn: &C,
+    locked_before: DateTime<Utc>,
+) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::update(
+            processing_queue::table
+                .filter(processing_queue::status.eq(QueueStatus::Processing))
+                .filter(processing_queue::locked_at.lt(locked_before)),
+        )
+        .set((
+            processing_queue::status.eq(QueueStatus::Pending),
+            processing_queue::locked_at.eq(None::<DateTime<Utc>>),
+        ))
+        .execute(c)
+    })
+    .await
+}
+
+/// Deletes completed activities last touched before the cutoff. Dead-lettered activities
+/// are retained for inspection.
+pub async fn prune_completed_queued_activities<C: DbRunner>(
+    conn: &C,
+    cutoff: DateTime<Utc>,
+) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::delete(
+            processing_queue::table
+                .filter(processing_queue::status.eq(QueueStatus::Complete))
+                .filter(processing_queue::updated_at.lt(cutoff)),
+        )
+        .execute(c)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn new_queued_activity_reads_references_and_embedded_objects() {
+        let queued = NewQueuedActivity::from(json!({
+            "id": "https://remote.example/activities/1",
+            "type": "Create",
+            "actor": {"id": "https://remote.example/users/alice"},
+            "object": {"type": "Note"}
+        }));
+
+        assert_eq!(
+            queued.ap_id.as_deref(),
+            Some("https://remote.example/activities/1")
+        );
+        assert_eq!(queued.kind, "Create");
+        assert_eq!(queued.actor, "https://remote.example/users/alice");
+    }
+
+    #[test]
+    fn new_queued_activity_tolerates_missing_fields() {
+        let queued = NewQueuedActivity::from(json!({"type": ["Create"]}));
+
+        assert!(queued.ap_id.is_none());
+        assert!(queued.kind.is_empty());
+        assert!(queued.actor.is_empty());
+    }
+}
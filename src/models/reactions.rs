@@ -0,0 +1,263 @@
+use crate::db::runner::DbRunner;
+use crate::schema::reactions;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Jsonb, Nullable, Text};
+use diesel::upsert::excluded;
+use diesel::{sql_query, AsChangeset, Identifiable, Insertable, Queryable, QueryableByName};
+use jdt_activity_pub::{ApAddress, ApContext, ApEmoji, ApLike, MaybeMultiple};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::actors::get_actor_by_as_id;
+use super::objects::get_object_by_as_id;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ApEmojiReactType {
+    #[default]
+    EmojiReact,
+}
+
+/// An EmojiReact activity reacts to an object with a Unicode emoji or with a custom emoji,
+/// which is named in `content` and described by an Emoji in the tags.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ApEmojiReact {
+    #[serde(rename = "@context")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ApContext>,
+    #[serde(rename = "type")]
+    pub kind: ApEmojiReactType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub actor: ApAddress,
+    #[serde(skip_serializing_if = "MaybeMultiple::is_none")]
+    #[serde(default)]
+    pub to: MaybeMultiple<ApAddress>,
+    pub object: String,
+    pub content: String,
+    // Kept as JSON so that tags other than Emoji can't prevent the reaction from parsing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<Value>,
+}
+
+impl From<Reaction> for ApEmojiReact {
+    fn from(reaction: Reaction) -> Self {
+        ApEmojiReact {
+            context: Some(ApContext::activity_streams()),
+            kind: ApEmojiReactType::EmojiReact,
+            id: Some(reaction.ap_id),
+            actor: ApAddress::Address(reaction.actor_ap_id),
+            to: MaybeMultiple::None,
+            object: reaction.object_ap_id,
+            content: reaction.content,
+            tag: reaction.emoji.map(|emoji| json!([emoji])),
+        }
+    }
+}
+
+/// Returns the custom emoji named by a reaction's content, e.g. `:blobcat:`
+pub fn find_emoji(content: &str, tags: Option<&Value>) -> Option<ApEmoji> {
+    let name = content.trim_matches(':');
+    let tags = match tags? {
+        Value::Array(tags) => tags.clone(),
+        tag => vec![tag.clone()],
+    };
+
+    tags.into_iter()
+        .filter_map(|tag| serde_json::from_value::<ApEmoji>(tag).ok())
+        .find(|emoji| emoji.name.trim_matches(':') == name)
+}
+
+#[derive(Serialize, Deserialize, Insertable, Default, Debug, Clone)]
+#[diesel(table_name = reactions)]
+pub struct NewReaction {
+    pub uuid: String,
+    pub ap_id: String,
+    pub actor_ap_id: String,
+    pub actor_id: Option<i32>,
+    pub object_ap_id: String,
+    pub object_id: Option<i32>,
+    pub content: String,
+    pub emoji: Option<Value>,
+}
+
+impl NewReaction {
+    /// Reads the reaction carried by a Like. Misskey sets `_misskey_reaction` alongside
+    /// `content`, while Pleroma and Akkoma set `content` alone; plain likes have neither.
+    pub fn from_like(like: &ApLike, raw: &Value) -> Option<NewReaction> {
+        let content = raw
+            .get("_misskey_reaction")
+            .or_else(|| raw.get("content"))?
+            .as_str()?
+            .trim();
+
+        if content.is_empty() {
+            return None;
+        }
+
+        Some(NewReaction {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            ap_id: like.id.clone()?,
+            actor_ap_id: like.actor.to_string(),
+            object_ap_id: like.object.reference()?,
+            content: content.to_string(),
+            emoji: find_emoji(content, raw.get("tag")).map(|emoji| json!(emoji)),
+            ..Default::default()
+        })
+    }
+
+    pub async fn link<C: DbRunner>(mut self, conn: &C) -> NewReaction {
+        self.actor_id = get_actor_by_as_id(conn, self.actor_ap_id.clone())
+            .await
+            .ok()
+            .map(|x| x.id);
+
+        self.object_id = get_object_by_as_id(conn, self.object_ap_id.clone())
+            .await
+            .ok()
+            .map(|x| x.id);
+
+        self.clone()
+    }
+}
+
+impl TryFrom<ApEmojiReact> for NewReaction {
+    type Error = anyhow::Error;
+
+    fn try_from(react: ApEmojiReact) -> Result<Self> {
+        let content = react.content.trim().to_string();
+
+        if content.is_empty() {
+            return Err(anyhow!("EmojiReact has no emoji"));
+        }
+
+        Ok(NewReaction {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            ap_id: react.id.ok_or(anyhow!("EmojiReact has no ID"))?,
+            actor_ap_id: react.actor.to_string(),
+            object_ap_id: react.object,
+            emoji: find_emoji(&content, react.tag.as_ref()).map(|emoji| json!(emoji)),
+            content,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Identifiable, Queryable, AsChangeset, Serialize, Clone, Default, Debug)]
+#[diesel(table_name = reactions)]
+pub struct Reaction {
+    #[serde(skip_serializing)]
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub uuid: String,
+    pub ap_id: String,
+    pub actor_ap_id: String,
+    pub actor_id: Option<i32>,
+    pub object_ap_id: String,
+    pub object_id: Option<i32>,
+    pub content: String,
+    pub emoji: Option<Value>,
+}
+
+/// The number of actors who reacted to an object with one emoji
+#[derive(QueryableByName, Serialize, Clone, Debug)]
+pub struct ReactionCount {
+    #[diesel(sql_type = Text)]
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diesel(sql_type = Nullable<Jsonb>)]
+    pub emoji: Option<Value>,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+    /// ActivityPub ID of the reaction if the requester reacted with this emoji
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[diesel(sql_type = Nullable<Text>)]
+    pub reacted: Option<String>,
+}
+
+/// Records a reaction. Reacting again with the same emoji replaces the activity on the
+/// existing record.
+pub async fn create_reaction<C: DbRunner>(conn: &C, reaction: NewReaction) -> Result<Reaction> {
+    conn.run(move |c| {
+        diesel::insert_into(reactions::table)
+            .values(&reaction)
+            .on_conflict((
+                reactions::actor_ap_id,
+                reactions::object_ap_id,
+                reactions::content,
+            ))
+            .do_update()
+            .set((
+                reactions::uuid.eq(excluded(reactions::uuid)),
+                reactions::ap_id.eq(excluded(reactions::ap_id)),
+                reactions::emoji.eq(excluded(reactions::emoji)),
+            ))
+            .get_result(c)
+    })
+    .await
+}
+
+pub async fn get_reaction_by_ap_id<C: DbRunner>(conn: &C, ap_id: String) -> Result<Reaction> {
+    conn.run(move |c| {
+        reactions::table
+            .filter(reactions::ap_id.eq(ap_id))
+            .first::<Reaction>(c)
+    })
+    .await
+}
+
+pub async fn delete_reaction_by_ap_id<C: DbRunner>(conn: &C, ap_id: String) -> Result<usize> {
+    conn.run(move |c| {
+        diesel::delete(reactions::table.filter(reactions::ap_id.eq(ap_id))).execute(c)
+    })
+    .await
+}
+
+#[derive(QueryableByName)]
+struct ObjectReactionCount {
+    #[diesel(sql_type = Text)]
+    object_ap_id: String,
+    #[diesel(embed)]
+    count: ReactionCount,
+}
+
+/// Counts the reactions to each of the objects by emoji, in the order each emoji was first
+/// used. Custom emoji from different servers that share a name are counted apart.
+pub async fn get_reaction_counts<C: DbRunner>(
+    conn: &C,
+    object_ap_ids: Vec<String>,
+    requester: Option<String>,
+) -> Result<HashMap<String, Vec<ReactionCount>>> {
+    let counts = conn
+        .run(move |c| {
+            sql_query(
+                "SELECT object_ap_id, \
+                        content, \
+                        (array_agg(emoji ORDER BY created_at))[1] AS emoji, \
+                        COUNT(*) AS count, \
+                        MAX(ap_id) FILTER (WHERE actor_ap_id = $2) AS reacted \
+                 FROM reactions \
+                 WHERE object_ap_id = ANY($1) \
+                 GROUP BY object_ap_id, content, emoji->>'id' \
+                 ORDER BY MIN(created_at)",
+            )
+            .bind::<Array<Text>, _>(object_ap_ids)
+            .bind::<Nullable<Text>, _>(requester)
+            .load::<ObjectReactionCount>(c)
+        })
+        .await?;
+
+    let mut reactions: HashMap<String, Vec<ReactionCount>> = HashMap::new();
+    for count in counts {
+        reactions
+            .entry(count.object_ap_id)
+            .or_default()
+            .push(count.count);
+    }
+
+    Ok(reactions)
+}
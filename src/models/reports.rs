@@ -0,0 +1,238 @@
+use crate::db::runner::DbRunner;
+use crate::schema::reports;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::{AsChangeset, Identifiable, Insertable, Queryable};
+use jdt_activity_pub::{ApAddress, ApContext, MaybeMultiple};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt::{self, Debug};
+
+#[derive(
+    diesel_derive_enum::DbEnum, Debug, Serialize, Deserialize, Default, Clone, Eq, PartialEq,
+)]
+#[ExistingTypePath = "crate::schema::sql_types::ReportStatus"]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    #[default]
+    Open,
+    Resolved,
+}
+
+impl fmt::Display for ReportStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl TryFrom<String> for ReportStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(status: String) -> Result<Self, Self::Error> {
+        match status.to_lowercase().as_str() {
+            "open" => Ok(ReportStatus::Open),
+            "resolved" => Ok(ReportStatus::Resolved),
+            _ => Err(anyhow!("unknown report status: {status}")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ApFlagType {
+    #[default]
+    Flag,
+}
+
+/// A Flag activity reports actors and objects to the server responsible for them. Its
+/// object is the reported actor along with any of that actor's objects being reported.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ApFlag {
+    #[serde(rename = "@context")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<ApContext>,
+    #[serde(rename = "type")]
+    pub kind: ApFlagType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub actor: ApAddress,
+    pub object: MaybeMultiple<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Set by local clients to have the report sent on to the reported actor's server
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub forward: bool,
+}
+
+/// An admin's annotation on a report
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReportNote {
+    pub created_at: DateTime<Utc>,
+    pub author: Option<String>,
+    pub content: String,
+}
+
+/// Changes an admin can make to a report
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ReportAction {
+    Assign {
+        assignee: String,
+    },
+    Unassign,
+    Resolve,
+    Reopen,
+    Note {
+        content: String,
+        author: Option<String>,
+    },
+    Forward,
+}
+
+#[derive(Serialize, Deserialize, Insertable, Default, Debug, Clone)]
+#[diesel(table_name = reports)]
+pub struct NewReport {
+    pub uuid: String,
+    pub reporter_ap_id: String,
+    pub reporter_actor_id: Option<i32>,
+    pub target_ap_id: String,
+    pub target_actor_id: Option<i32>,
+    pub object_ap_ids: Value,
+    pub comment: Option<String>,
+    pub flag_ap_id: Option<String>,
+}
+
+#[derive(Identifiable, Queryable, AsChangeset, Serialize, Clone, Default, Debug)]
+#[diesel(table_name = reports)]
+pub struct Report {
+    pub id: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub uuid: String,
+    pub reporter_ap_id: String,
+    pub reporter_actor_id: Option<i32>,
+    pub target_ap_id: String,
+    pub target_actor_id: Option<i32>,
+    pub object_ap_ids: Value,
+    pub comment: Option<String>,
+    pub flag_ap_id: Option<String>,
+    pub forwarded: bool,
+    pub status: ReportStatus,
+    pub assigned_to: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub notes: Value,
+}
+
+impl Report {
+    pub fn object_ap_ids(&self) -> Vec<String> {
+        serde_json::from_value(self.object_ap_ids.clone()).unwrap_or_default()
+    }
+
+    pub fn notes(&self) -> Vec<ReportNote> {
+        serde_json::from_value(self.notes.clone()).unwrap_or_default()
+    }
+}
+
+/// Creates a report. A Flag that has already been recorded (a redelivery) is ignored and
+/// None is returned.
+pub async fn create_report<C: DbRunner>(conn: &C, report: NewReport) -> Result<Option<Report>> {
+    conn.run(move |c| {
+        diesel::insert_into(reports::table)
+            .values(&report)
+            .on_conflict(reports::flag_ap_id)
+            .do_nothing()
+            .get_result::<Report>(c)
+            .optional()
+    })
+    .await
+}
+
+/// Lists reports, newest first, optionally limited to those with the given status
+pub async fn get_reports<C: DbRunner>(
+    conn: &C,
+    status: Option<ReportStatus>,
+) -> Result<Vec<Report>> {
+    conn.run(move |c| {
+        let mut query = reports::table.into_boxed();
+
+        if let Some(status) = status {
+            query = query.filter(reports::status.eq(status));
+        }
+
+        query.order(reports::created_at.desc()).get_results(c)
+    })
+    .await
+}
+
+pub async fn get_report_by_uuid<C: DbRunner>(conn: &C, uuid: String) -> Result<Report> {
+    conn.run(move |c| reports::table.filter(reports::uuid.eq(uuid)).first(c))
+        .await
+}
+
+pub async fn update_report_assignee<C: DbRunner>(
+    conn: &C,
+    uuid: String,
+    assignee: Option<String>,
+) -> Result<Report> {
+    conn.run(move |c| {
+        diesel::update(reports::table.filter(reports::uuid.eq(uuid)))
+            .set(reports::assigned_to.eq(assignee))
+            .get_result(c)
+    })
+    .await
+}
+
+/// Sets the report's status, recording when it was resolved
+pub async fn update_report_status<C: DbRunner>(
+    conn: &C,
+    uuid: String,
+    status: ReportStatus,
+) -> Result<Report> {
+    let resolved_at = (status == ReportStatus::Resolved).then(Utc::now);
+
+    conn.run(move |c| {
+        diesel::update(reports::table.filter(reports::uuid.eq(uuid)))
+            .set((
+                reports::status.eq(status),
+                reports::resolved_at.eq(resolved_at),
+            ))
+            .get_result(c)
+    })
+    .await
+}
+
+pub async fn add_report_note<C: DbRunner>(
+    conn: &C,
+    uuid: String,
+    note: ReportNote,
+) -> Result<Report> {
+    let mut notes = get_report_by_uuid(conn, uuid.clone()).await?.notes();
+    notes.push(note);
+    let notes = json!(notes);
+
+    conn.run(move |c| {
+        diesel::update(reports::table.filter(reports::uuid.eq(uuid)))
+            .set(reports::notes.eq(notes))
+            .get_result(c)
+    })
+    .await
+}
+
+/// Records that the report was sent to the reported actor's server as `flag_ap_id`
+pub async fn set_report_forwarded<C: DbRunner>(
+    conn: &C,
+    uuid: String,
+    flag_ap_id: String,
+) -> Result<Report> {
+    conn.run(move |c| {
+        diesel::update(reports::table.filter(reports::uuid.eq(uuid)))
+            .set((
+                reports::forwarded.eq(true),
+                reports::flag_ap_id.eq(Some(flag_ap_id)),
+            ))
+            .get_result(c)
+    })
+    .await
+}
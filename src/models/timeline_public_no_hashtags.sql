@@ -15,6 +15,16 @@ WITH top_activities AS (
                 AND (ap_to ?| $3::text[] OR cc ?| $3::text[] OR actor = ANY($4::text[]))
                 AND (CASE WHEN NULLIF(NULLIF($5, 'NULL'), '') IS NOT NULL THEN created_at < $5::timestamptz ELSE TRUE END) -- max_date
                 AND (CASE WHEN NULLIF(NULLIF($6, 'NULL'), '') IS NOT NULL THEN created_at > $6::timestamptz ELSE TRUE END) -- min_date
+                AND NOT (actor = ANY($10::text[])) -- hidden_actors
+                AND NOT (
+                    split_part(actor, '/', 3) = ANY($11::text[])
+                    AND NOT EXISTS (
+                        SELECT 1 FROM follows f
+                        WHERE f.leader_ap_id = activities.actor
+                            AND f.accepted = true
+                            AND f.follower_actor_id = (CASE WHEN $9 <> 'NULL' THEN $9::integer END)
+                    )
+                ) -- silenced_domains, unless the viewer follows the actor
             ORDER BY created_at DESC
             LIMIT 500 -- Heuristic over-fetch. Increase if timelines have many boosts of few posts.
         ) a
@@ -24,6 +34,7 @@ WITH top_activities AS (
             COALESCE(o.as_content, '') !~* ('[[:<:]]#?(' || $1 || ')[[:>:]]') 
             AND ($2::boolean = false OR o.ek_uuid IS NOT NULL) -- is_local_view
             AND o.as_type IN ('note', 'question', 'article')
+            AND NOT (COALESCE(o.as_attributed_to, '[]'::jsonb) ?| $10::text[]) -- hidden_actors
         -- This ordering is crucial for DISTINCT ON to pick the latest activity per object.
         ORDER BY a.target_ap_id, a.created_at DESC
     ) AS latest_activities_per_object
@@ -262,19 +273,21 @@ ORDER BY
 -- 7: order_asc (Boolean)
 -- 8: limit (Integer)
 -- 9: profile_actor_id (Text)
+-- 10: hidden_actors (Text[])
+-- 11: silenced_domains (Text[])
 
 -- Example 1: Global Timeline (Unauthenticated)
--- \bind 'darf' FALSE '{"https://www.w3.org/ns/activitystreams#Public", "as:Public","Public"}' '{"https://enigmatick.social/users/jdt"}' NULL NULL FALSE 1 NULL
+-- \bind 'darf' FALSE '{"https://www.w3.org/ns/activitystreams#Public", "as:Public","Public"}' '{"https://enigmatick.social/users/jdt"}' NULL NULL FALSE 1 NULL '{}' '{}'
 -- \g
 
 -- Example 2: Local Timeline (Authenticated as user 7)
--- \bind '' TRUE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' NULL NULL FALSE 20 7
+-- \bind '' TRUE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' NULL NULL FALSE 20 7 '{}' '{}'
 -- \g
 
 -- Example 3: Global Timeline (Authenticated as user 7)
--- \bind '' FALSE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' NULL NULL FALSE 20 7
+-- \bind '' FALSE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' NULL NULL FALSE 20 7 '{}' '{}'
 -- \g
 
 -- Example 4: Direct Timeline (Authenticated as user 7)
--- \bind '' FALSE '{"https://enigmatick.social/user/jdt"}' '{"https://enigmatick.social/user/jdt"}' NULL NULL FALSE 3 7
+-- \bind '' FALSE '{"https://enigmatick.social/user/jdt"}' '{"https://enigmatick.social/user/jdt"}' NULL NULL FALSE 3 7 '{}' '{}'
 -- \g
@@ -22,6 +22,17 @@ WITH top_activities AS (
             AND ($2::boolean = false OR o.ek_uuid IS NOT NULL) -- is_local_view
             AND (CASE WHEN $6 <> 'NULL' THEN a.created_at < $6::timestamptz ELSE TRUE END) -- max_date
             AND (CASE WHEN $7 <> 'NULL' THEN a.created_at > $7::timestamptz ELSE TRUE END) -- min_date
+            AND NOT (a.actor = ANY($11::text[])) -- hidden_actors
+            AND NOT (COALESCE(o.as_attributed_to, '[]'::jsonb) ?| $11::text[]) -- hidden_actors
+            AND NOT (
+                split_part(a.actor, '/', 3) = ANY($12::text[])
+                AND NOT EXISTS (
+                    SELECT 1 FROM follows f
+                    WHERE f.leader_ap_id = a.actor
+                        AND f.accepted = true
+                        AND f.follower_actor_id = (CASE WHEN $10 <> 'NULL' THEN $10::integer END)
+                )
+            ) -- silenced_domains, unless the viewer follows the actor
         -- This ordering is crucial for DISTINCT ON to pick the latest activity per object
         ORDER BY a.target_ap_id, a.created_at DESC
     ) AS latest_activities_per_object
@@ -263,14 +274,16 @@ ORDER BY
 -- 8: order_asc (Boolean)
 -- 9: limit (Integer)
 -- 10: profile_actor_id (Text)
+-- 11: hidden_actors (Text[])
+-- 12: silenced_domains (Text[])
 
 -- Example 1: Global Timeline (Unauthenticated)
--- \bind '' FALSE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' '{}' '{"#activitypub"}' NULL NULL FALSE 20 NULL
+-- \bind '' FALSE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' '{}' '{"#activitypub"}' NULL NULL FALSE 20 NULL '{}' '{}'
 -- \g
 
 
 
 -- Example 2: Local Timeline (Authenticated as user 7)
--- \bind '' TRUE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' '{}' '{"#activitypub"}' NULL NULL FALSE 20 7
+-- \bind '' TRUE '{"https://www.w3.org/ns/activitystreams#Public","as:Public","Public"}' '{}' '{"#activitypub"}' NULL NULL FALSE 20 7 '{}' '{}'
 -- \g
 
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const NODEINFO_2_0_SCHEMA: &str = "http://nodeinfo.diaspora.software/ns/schema/2.0";
+pub const NODEINFO_2_1_SCHEMA: &str = "http://nodeinfo.diaspora.software/ns/schema/2.1";
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct NodeInfoLink {
+    pub rel: String,
+    pub href: String,
+}
+
+/// The document served at `/.well-known/nodeinfo`, linking to the supported NodeInfo versions
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct NodeInfoLinks {
+    pub links: Vec<NodeInfoLink>,
+}
+
+impl NodeInfoLinks {
+    /// Returns the link to the newest NodeInfo version this server understands
+    pub fn get_href(&self) -> Option<String> {
+        [NODEINFO_2_1_SCHEMA, NODEINFO_2_0_SCHEMA]
+            .iter()
+            .find_map(|schema| self.links.iter().find(|link| link.rel == *schema))
+            .map(|link| link.href.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct NodeInfoSoftware {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub repository: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub homepage: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct NodeInfoServices {
+    #[serde(default)]
+    pub inbound: Vec<String>,
+    #[serde(default)]
+    pub outbound: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfoUsers {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub total: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_month: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_halfyear: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfoUsage {
+    #[serde(default)]
+    pub users: NodeInfoUsers,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub local_posts: Option<i64>,
+}
+
+/// A NodeInfo 2.0 or 2.1 document. Fields other servers commonly omit are defaulted so
+/// that their documents can be read as well.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub version: String,
+    pub software: NodeInfoSoftware,
+    #[serde(default)]
+    pub protocols: Vec<String>,
+    #[serde(default)]
+    pub services: NodeInfoServices,
+    #[serde(default)]
+    pub open_registrations: bool,
+    #[serde(default)]
+    pub usage: NodeInfoUsage,
+    #[serde(default)]
+    pub metadata: Value,
+}
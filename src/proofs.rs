@@ -0,0 +1,358 @@
+use crate::db::runner::DbRunner;
+use crate::models::actors::Actor;
+use crate::retriever::get_actor;
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use jdt_activity_pub::actor::{ApAssertionMethod, ApAssertionMethodType};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+const DATA_INTEGRITY_CONTEXT: &str = "https://w3id.org/security/data-integrity/v1";
+const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+const PROOF_PURPOSE: &str = "assertionMethod";
+
+// Multicodec prefixes of Ed25519 public and private keys
+const ED25519_PUBLIC_PREFIX: [u8; 2] = [0xed, 0x01];
+const ED25519_PRIVATE_PREFIX: [u8; 2] = [0x80, 0x26];
+
+/// An Ed25519 key pair, each key multibase-encoded with its multicodec prefix
+pub struct Ed25519KeyPair {
+    pub private_key: String,
+    pub public_key: String,
+}
+
+pub fn generate_ed25519_key_pair() -> Ed25519KeyPair {
+    let signing_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+
+    Ed25519KeyPair {
+        private_key: encode_multibase(&ED25519_PRIVATE_PREFIX, signing_key.as_bytes()),
+        public_key: encode_multibase(
+            &ED25519_PUBLIC_PREFIX,
+            signing_key.verifying_key().as_bytes(),
+        ),
+    }
+}
+
+/// The ID of the Ed25519 key of the actor identified by `as_id`
+pub fn ed25519_key_id(as_id: &str) -> String {
+    format!("{as_id}#ed25519-key")
+}
+
+/// The Multikey published in an actor's assertionMethod
+pub fn assertion_method(as_id: &str, public_key: String) -> ApAssertionMethod {
+    ApAssertionMethod {
+        id: Some(ed25519_key_id(as_id)),
+        kind: ApAssertionMethodType::Multikey,
+        controller: Some(as_id.to_string()),
+        public_key_multibase: Some(public_key),
+    }
+}
+
+fn encode_multibase(prefix: &[u8], bytes: &[u8]) -> String {
+    format!("z{}", bs58::encode([prefix, bytes].concat()).into_string())
+}
+
+fn decode_multibase(value: &str) -> Result<Vec<u8>> {
+    let encoded = value
+        .strip_prefix('z')
+        .ok_or(anyhow!("unsupported multibase encoding"))?;
+
+    Ok(bs58::decode(encoded).into_vec()?)
+}
+
+fn decode_key(prefix: &[u8], value: &str) -> Result<[u8; 32]> {
+    let bytes = decode_multibase(value)?;
+    let key = bytes
+        .strip_prefix(prefix)
+        .ok_or(anyhow!("unexpected multicodec prefix"))?;
+
+    key.try_into()
+        .map_err(|_| anyhow!("invalid Ed25519 key length"))
+}
+
+/// JSON Canonicalization Scheme (RFC 8785) serialization. serde_json sorts object members
+/// and escapes strings the way JCS does; only non-integer numbers could be serialized
+/// differently, and ActivityPub documents don't use them.
+fn canonicalize(value: &Value) -> Vec<u8> {
+    serde_json::to_vec(value).unwrap_or_default()
+}
+
+/// The data signed by an eddsa-jcs-2022 proof: the hash of the proof options (which take
+/// the document's @context) followed by the hash of the document without its proof
+fn hash_data(document: &Map<String, Value>, mut proof_config: Map<String, Value>) -> Vec<u8> {
+    if let Some(context) = document.get("@context") {
+        proof_config.insert("@context".to_string(), context.clone());
+    }
+
+    [
+        Sha256::digest(canonicalize(&Value::Object(proof_config))),
+        Sha256::digest(canonicalize(&Value::Object(document.clone()))),
+    ]
+    .concat()
+}
+
+fn with_data_integrity_context(context: Value) -> Value {
+    match context {
+        Value::Array(mut contexts) => {
+            if !contexts.contains(&json!(DATA_INTEGRITY_CONTEXT)) {
+                contexts.push(json!(DATA_INTEGRITY_CONTEXT));
+            }
+            Value::Array(contexts)
+        }
+        Value::String(ref url) if url == DATA_INTEGRITY_CONTEXT => context,
+        context => json!([context, DATA_INTEGRITY_CONTEXT]),
+    }
+}
+
+/// Adds a FEP-8b32 proof made with the profile's Ed25519 key to `document`, replacing any
+/// existing proof. Documents from actors without an Ed25519 key are returned unchanged.
+pub fn add_proof(document: Value, profile: &Actor) -> Result<Value> {
+    let Some(private_key) = &profile.ek_ed25519_private_key else {
+        return Ok(document);
+    };
+
+    let Value::Object(mut document) = document else {
+        return Err(anyhow!("only JSON objects can be signed"));
+    };
+
+    let signing_key = SigningKey::from_bytes(&decode_key(&ED25519_PRIVATE_PREFIX, private_key)?);
+
+    document.remove("proof");
+    if let Some(context) = document.remove("@context") {
+        document.insert("@context".to_string(), with_data_integrity_context(context));
+    }
+
+    let mut proof = Map::from_iter([
+        ("type".to_string(), json!("DataIntegrityProof")),
+        ("cryptosuite".to_string(), json!(CRYPTOSUITE)),
+        (
+            "verificationMethod".to_string(),
+            json!(ed25519_key_id(&profile.as_id)),
+        ),
+        ("proofPurpose".to_string(), json!(PROOF_PURPOSE)),
+        (
+            "created".to_string(),
+            json!(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+    ]);
+
+    let signature = signing_key.sign(&hash_data(&document, proof.clone()));
+    proof.insert(
+        "proofValue".to_string(),
+        json!(format!(
+            "z{}",
+            bs58::encode(signature.to_bytes()).into_string()
+        )),
+    );
+    document.insert("proof".to_string(), Value::Object(proof));
+
+    Ok(Value::Object(document))
+}
+
+fn take_proof(document: &mut Map<String, Value>) -> Option<Map<String, Value>> {
+    let proof = match document.remove("proof")? {
+        Value::Array(proofs) => proofs.into_iter().next()?,
+        proof => proof,
+    };
+
+    match proof {
+        Value::Object(proof) => Some(proof),
+        _ => None,
+    }
+}
+
+fn is_supported(proof: &Map<String, Value>) -> bool {
+    proof.get("type").and_then(Value::as_str) == Some("DataIntegrityProof")
+        && proof.get("cryptosuite").and_then(Value::as_str) == Some(CRYPTOSUITE)
+}
+
+/// Whether `document` carries a proof in a cryptosuite that can be verified
+pub fn has_supported_proof(document: &Value) -> bool {
+    match document {
+        Value::Object(document) => {
+            take_proof(&mut document.clone()).is_some_and(|proof| is_supported(&proof))
+        }
+        _ => false,
+    }
+}
+
+/// A proof taken off the document it was made over
+struct UnpackedProof {
+    document: Map<String, Value>,
+    proof_config: Map<String, Value>,
+    verification_method: String,
+    signature: Signature,
+}
+
+fn unpack_proof(document: &Value) -> Result<UnpackedProof> {
+    let Value::Object(mut document) = document.clone() else {
+        return Err(anyhow!("document is not a JSON object"));
+    };
+
+    let mut proof_config = take_proof(&mut document).ok_or(anyhow!("document has no proof"))?;
+
+    if !is_supported(&proof_config) {
+        return Err(anyhow!("unsupported proof type"));
+    }
+
+    let field = |name: &str| {
+        proof_config
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    if field("proofPurpose").as_deref() != Some(PROOF_PURPOSE) {
+        return Err(anyhow!("unexpected proof purpose"));
+    }
+
+    let verification_method =
+        field("verificationMethod").ok_or(anyhow!("no verificationMethod"))?;
+    let proof_value = field("proofValue").ok_or(anyhow!("no proofValue"))?;
+    proof_config.remove("proofValue");
+
+    Ok(UnpackedProof {
+        document,
+        proof_config,
+        verification_method,
+        signature: Signature::from_slice(&decode_multibase(&proof_value)?)?,
+    })
+}
+
+impl UnpackedProof {
+    /// Checks the signature with a multibase-encoded Ed25519 public key
+    fn verify(self, public_key: &str) -> Result<()> {
+        VerifyingKey::from_bytes(&decode_key(&ED25519_PUBLIC_PREFIX, public_key)?)?.verify(
+            &hash_data(&self.document, self.proof_config),
+            &self.signature,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Verifies the FEP-8b32 proof on `document` and returns the ID of the actor controlling
+/// the key that made it. The actor is retrieved if it is not already known.
+pub async fn verify_proof<C: DbRunner>(conn: &C, document: &Value) -> Result<String> {
+    let proof = unpack_proof(document)?;
+    let verification_method = proof.verification_method.clone();
+
+    let controller = verification_method
+        .split_once('#')
+        .map_or(verification_method.as_str(), |(id, _)| id)
+        .to_string();
+    let actor = get_actor(conn, controller, None, true).await?;
+    let as_id = actor
+        .id
+        .clone()
+        .ok_or(anyhow!("actor has no id"))?
+        .to_string();
+
+    let public_key = actor
+        .assertion_method
+        .multiple()
+        .into_iter()
+        .find(|method| {
+            method.kind == ApAssertionMethodType::Multikey
+                && method.id.as_deref() == Some(verification_method.as_str())
+                && method
+                    .controller
+                    .as_ref()
+                    .is_none_or(|controller| *controller == as_id)
+        })
+        .and_then(|method| method.public_key_multibase)
+        .ok_or(anyhow!(
+            "{verification_method} is not an assertion method of {as_id}"
+        ))?;
+
+    proof.verify(&public_key)?;
+
+    Ok(as_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer() -> (Actor, String) {
+        let keys = generate_ed25519_key_pair();
+        let actor = Actor {
+            as_id: "https://example.com/user/alice".to_string(),
+            ek_ed25519_private_key: Some(keys.private_key),
+            ..Default::default()
+        };
+
+        (actor, keys.public_key)
+    }
+
+    fn note() -> Value {
+        json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "type": "Create",
+            "id": "https://example.com/activities/1",
+            "actor": "https://example.com/user/alice",
+            "object": {
+                "type": "Note",
+                "content": "caf\u{e9} \"quoted\" \u{1f600}",
+                "to": ["https://www.w3.org/ns/activitystreams#Public"]
+            }
+        })
+    }
+
+    #[test]
+    fn canonicalize_sorts_members_without_whitespace() {
+        let value = json!({"b": [3, {"z": 1, "a": null}], "a": "\u{e9}\n"});
+
+        assert_eq!(
+            String::from_utf8(canonicalize(&value)).unwrap(),
+            "{\"a\":\"\u{e9}\\n\",\"b\":[3,{\"a\":null,\"z\":1}]}"
+        );
+    }
+
+    #[test]
+    fn multibase_round_trips_keys() {
+        let keys = generate_ed25519_key_pair();
+
+        assert!(keys.public_key.starts_with("z6Mk"));
+        assert!(decode_key(&ED25519_PUBLIC_PREFIX, &keys.public_key).is_ok());
+        assert!(decode_key(&ED25519_PRIVATE_PREFIX, &keys.private_key).is_ok());
+        assert!(decode_key(&ED25519_PRIVATE_PREFIX, &keys.public_key).is_err());
+        assert!(decode_multibase("uAAAA").is_err());
+    }
+
+    #[test]
+    fn added_proof_verifies() {
+        let (actor, public_key) = signer();
+        let document = add_proof(note(), &actor).unwrap();
+
+        assert!(has_supported_proof(&document));
+        assert_eq!(
+            document["@context"],
+            json!([
+                "https://www.w3.org/ns/activitystreams",
+                DATA_INTEGRITY_CONTEXT
+            ])
+        );
+
+        let proof = unpack_proof(&document).unwrap();
+        assert_eq!(proof.verification_method, ed25519_key_id(&actor.as_id));
+        assert!(proof.verify(&public_key).is_ok());
+    }
+
+    #[test]
+    fn altered_document_fails_verification() {
+        let (actor, public_key) = signer();
+        let mut document = add_proof(note(), &actor).unwrap();
+        document["object"]["content"] = json!("changed");
+
+        assert!(unpack_proof(&document)
+            .unwrap()
+            .verify(&public_key)
+            .is_err());
+
+        let (_, other_key) = signer();
+        let document = add_proof(note(), &actor).unwrap();
+        assert!(unpack_proof(&document).unwrap().verify(&other_key).is_err());
+    }
+}
@@ -3,20 +3,31 @@ use crate::models::activities::{get_activities_coalesced, TimelineFilters};
 use anyhow::anyhow;
 use anyhow::{Context, Result};
 use jdt_activity_pub::{ActivityPub, ApActivity, ApCollection, ApObject, CollectionFetcher};
+use once_cell::sync::Lazy;
 use reqwest::Response;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use url::Url;
 
+use crate::helper::get_domain_from_url;
 use crate::models::actors::{
-    create_or_update_actor, get_actor_by_as_id, guaranteed_actor, Actor, FromActorAndLeader,
-    NewActor,
+    create_or_update_actor, get_actor_by_as_id, guaranteed_actor, moved_to_of, Actor,
+    FromActorAndLeader, NewActor,
 };
+use crate::models::blocks::get_hidden_actor_ap_ids;
 use crate::models::cache::Cache;
 use crate::models::follows::get_follow;
+use crate::models::instances::{
+    get_allowed_domains, get_blocked_domains, is_domain_permitted, SignatureScheme,
+};
 use crate::models::objects::{create_object, get_object_by_as_id, NewObject};
-use crate::signing::{sign, Method, SignParams};
+use crate::signing::{
+    record_signature_scheme, sign, signature_rejected, signature_schemes, Method, SignParams,
+};
 use crate::webfinger::WebFinger;
 use crate::{GetWebfinger, LoadEphemeral, WEBFINGER_RE};
-use jdt_activity_pub::ApActor;
+use jdt_activity_pub::{ApActor, ApPublicKey};
 
 pub async fn activities<C: DbRunner>(
     conn: &C,
@@ -24,12 +35,20 @@ pub async fn activities<C: DbRunner>(
     min: Option<i64>,
     max: Option<i64>,
     profile: Option<Actor>,
-    filters: TimelineFilters,
+    mut filters: TimelineFilters,
     base_url: Option<String>,
 ) -> ApObject {
     let server_url = format!("https://{}", *crate::SERVER_NAME);
     let base_url = base_url.unwrap_or(format!("{server_url}/inbox?page=true&limit={limit}"));
 
+    if let Some(profile) = &profile {
+        filters.blocked_actors.extend(
+            get_hidden_actor_ap_ids(conn, profile.as_id.clone())
+                .await
+                .unwrap_or_default(),
+        );
+    }
+
     let activities = get_activities_coalesced(
         conn,
         limit,
@@ -64,7 +83,7 @@ pub async fn get_remote_collection_page<C: DbRunner>(
     profile: Option<Actor>,
     url: String,
 ) -> Result<ApCollection> {
-    let response = signed_get(guaranteed_actor(conn, profile).await, url, false).await?;
+    let response = signed_get(conn, guaranteed_actor(conn, profile).await, url, false).await?;
 
     log::debug!("{response:?}");
 
@@ -79,7 +98,7 @@ pub async fn get_remote_collection<C: DbRunner>(
     profile: Option<Actor>,
     url: String,
 ) -> Result<ApCollection> {
-    let response = signed_get(guaranteed_actor(conn, profile).await, url, false).await?;
+    let response = signed_get(conn, guaranteed_actor(conn, profile).await, url, false).await?;
 
     let raw = response.text().await?;
     let page: ApCollection = serde_json::from_str(&raw).map_err(anyhow::Error::msg)?;
@@ -87,8 +106,8 @@ pub async fn get_remote_collection<C: DbRunner>(
     Ok(page.cache(conn).await.clone())
 }
 
-pub async fn get_ap_id_from_webfinger(acct: String) -> Result<String> {
-    let webfinger = get_remote_webfinger(acct).await?;
+pub async fn get_ap_id_from_webfinger<C: DbRunner>(conn: &C, acct: String) -> Result<String> {
+    let webfinger = get_remote_webfinger(conn, acct).await?;
 
     webfinger
         .links
@@ -107,7 +126,7 @@ pub async fn get_ap_id_from_webfinger(acct: String) -> Result<String> {
         .ok_or_else(|| anyhow!("Failed to find usable link"))
 }
 
-async fn get_remote_webfinger(handle: String) -> Result<WebFinger> {
+async fn get_remote_webfinger<C: DbRunner>(conn: &C, handle: String) -> Result<WebFinger> {
     let captures = WEBFINGER_RE
         .captures_iter(&handle)
         .next()
@@ -122,6 +141,10 @@ async fn get_remote_webfinger(handle: String) -> Result<WebFinger> {
     let username = captures.get(1).map_or("", |m| m.as_str());
     let server = captures.get(2).map_or("", |m| m.as_str());
 
+    if !is_domain_permitted(conn, server.to_string()).await {
+        return Err(anyhow!("Federation with {server} is not permitted"));
+    }
+
     let url = format!("https://{server}/.well-known/webfinger?resource=acct:{username}@{server}");
 
     let response = crate::HTTP_CLIENT
@@ -157,7 +180,7 @@ pub async fn get_object<C: DbRunner>(
         let actor = guaranteed_actor(conn, profile.clone()).await;
         log::debug!("Using actor for signed request: {}", actor.as_id);
 
-        let resp = signed_get(actor, id.clone(), false)
+        let resp = signed_get(conn, actor, id.clone(), false)
             .await
             .context(format!("Failed to perform signed GET for {}", id))?;
 
@@ -268,6 +291,7 @@ pub async fn process_remote_actor_retrieval<C: DbRunner>(
     id: String,
 ) -> Result<ApActor> {
     let response = signed_get(
+        conn,
         guaranteed_actor(conn, profile.clone()).await,
         id.clone(),
         false,
@@ -287,6 +311,9 @@ pub async fn process_remote_actor_retrieval<C: DbRunner>(
     let mut new_actor_data = NewActor::try_from(actor_from_remote.cache(conn).await.clone())
         .map_err(anyhow::Error::msg)?;
     new_actor_data.ek_webfinger = webfinger;
+    new_actor_data.as_moved_to = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|actor| moved_to_of(&actor));
 
     let actor_model = create_or_update_actor(conn, new_actor_data)
         .await
@@ -328,15 +355,172 @@ pub async fn get_actor<C: DbRunner>(
     }
 }
 
-pub async fn signed_get(profile: Actor, url: String, accept_any: bool) -> Result<Response> {
+// How long a key ID that couldn't be resolved is refused without another attempt
+const UNRESOLVED_KEY_TTL: Duration = Duration::from_secs(600);
+// The minimum spacing between key resolutions for any one instance
+const KEY_RESOLUTION_INTERVAL: Duration = Duration::from_secs(10);
+
+// Key IDs that could not be resolved, and when
+static UNRESOLVED_KEYS: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(Default::default);
+// When a key was last resolved for each instance
+static KEY_RESOLUTIONS: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(Default::default);
+
+/// Records an attempt to resolve the key, returning false if the key recently failed to
+/// resolve or a key on the same instance was resolved within KEY_RESOLUTION_INTERVAL
+fn claim_key_resolution(key_id: &str, host: &str) -> bool {
+    let now = Instant::now();
+
+    let mut unresolved = UNRESOLVED_KEYS.lock().unwrap();
+    unresolved.retain(|_, failed| now.duration_since(*failed) < UNRESOLVED_KEY_TTL);
+
+    if unresolved.contains_key(key_id) {
+        return false;
+    }
+
+    let mut resolutions = KEY_RESOLUTIONS.lock().unwrap();
+    resolutions.retain(|_, resolved| now.duration_since(*resolved) < KEY_RESOLUTION_INTERVAL);
+
+    if resolutions.contains_key(host) {
+        return false;
+    }
+
+    resolutions.insert(host.to_string(), now);
+    true
+}
+
+/// Resolves a key ID that isn't known locally to the key's owner and public key PEM. Only
+/// keys of already known remote actors are resolved: the owner is refetched, since an
+/// unrecognised key usually means it rotated its keys. A key that has been rotated out
+/// but can still be dereferenced on its own is taken from its key document.
+///
+/// Resolution is driven by unauthenticated requests, so keys that fail to resolve are
+/// remembered for a while and each instance's keys are resolved at a limited rate.
+pub async fn resolve_public_key<C: DbRunner>(conn: &C, key_id: String) -> Result<(Actor, String)> {
+    let Some(key_host) = host(&key_id).filter(|host| *host != *crate::SERVER_NAME) else {
+        return Err(anyhow!("{key_id} is not a remote key"));
+    };
+
+    if !claim_key_resolution(&key_id, &key_host) {
+        return Err(anyhow!("Not resolving {key_id} again yet"));
+    }
+
+    let resolved = fetch_public_key(conn, key_id.clone()).await;
+
+    if resolved.is_err() {
+        UNRESOLVED_KEYS
+            .lock()
+            .unwrap()
+            .insert(key_id, Instant::now());
+    }
+
+    resolved
+}
+
+fn host(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_string)
+}
+
+async fn fetch_public_key<C: DbRunner>(conn: &C, key_id: String) -> Result<(Actor, String)> {
+    let (owner, key_document) = match key_id.split_once('#') {
+        Some((owner, _)) => (owner.to_string(), None),
+        None => {
+            let response = signed_get(
+                conn,
+                guaranteed_actor(conn, None).await,
+                key_id.clone(),
+                false,
+            )
+            .await?;
+
+            if !response.status().is_success() {
+                return Err(anyhow!("Unable to retrieve {key_id}"));
+            }
+
+            let document = response.json::<serde_json::Value>().await?;
+
+            match serde_json::from_value::<ApPublicKey>(document.clone()) {
+                Ok(key) if key.id == key_id => (key.owner.clone(), Some(key)),
+                Ok(_) => return Err(anyhow!("{key_id} resolved to a different key")),
+                Err(_) => (
+                    document
+                        .get("id")
+                        .and_then(serde_json::Value::as_str)
+                        .ok_or(anyhow!("{key_id} resolved to a document without an id"))?
+                        .to_string(),
+                    None,
+                ),
+            }
+        }
+    };
+
+    if host(&owner) != host(&key_id) {
+        return Err(anyhow!("{key_id} is not owned by {owner}"));
+    }
+
+    get_actor_by_as_id(conn, owner.clone())
+        .await
+        .context("Key owner is not a known Actor")?;
+
+    let actor = process_remote_actor_retrieval(conn, None, owner.clone()).await?;
+
+    let public_key_pem = if actor.public_key.id == key_id {
+        actor.public_key.public_key_pem
+    } else {
+        key_document
+            .filter(|key| key.owner == owner)
+            .map(|key| key.public_key_pem)
+            .ok_or(anyhow!("{key_id} is not a key of {owner}"))?
+    };
+
+    Ok((get_actor_by_as_id(conn, owner).await?, public_key_pem))
+}
+
+/// Retrieves `url` with a signed GET, negotiating the signature scheme with the remote
+/// instance the same way deliveries do
+pub async fn signed_get<C: DbRunner>(
+    conn: &C,
+    profile: Actor,
+    url: String,
+    accept_any: bool,
+) -> Result<Response> {
+    let domain_name = get_domain_from_url(url.clone()).ok_or(anyhow!("Invalid URL: {url}"))?;
+    if !is_domain_permitted(conn, domain_name.clone()).await {
+        return Err(anyhow!("Federation with {domain_name} is not permitted"));
+    }
+
+    let schemes = signature_schemes(conn, &url).await;
+    let attempts = schemes.len();
+
+    for (attempt, scheme) in schemes.into_iter().enumerate() {
+        let response = signed_get_with(profile.clone(), &url, accept_any, scheme).await?;
+
+        if attempt + 1 < attempts && signature_rejected(response.status().as_u16()) {
+            log::debug!("{url} rejected a {scheme} signature; retrying");
+            continue;
+        }
+
+        if response.status().is_success() {
+            record_signature_scheme(conn, &url, scheme).await;
+        }
+
+        return Ok(response);
+    }
+
+    Err(anyhow!("No signature scheme to sign with"))
+}
+
+async fn signed_get_with(
+    profile: Actor,
+    url_str: &str,
+    accept_any: bool,
+    scheme: SignatureScheme,
+) -> Result<Response> {
     let accept = if accept_any {
         "*/*"
     } else {
         "application/activity+json"
     };
 
-    let url_str = &url.clone();
-
     let body = None;
     let method = Method::Get;
     let url = Url::parse(url_str)?;
@@ -346,14 +530,17 @@ pub async fn signed_get(profile: Actor, url: String, accept_any: bool) -> Result
         url,
         body,
         method,
+        scheme,
     })?;
 
     let mut request = crate::HTTP_CLIENT
         .get(url_str)
         .timeout(std::time::Duration::new(5, 0))
-        .header("Accept", accept)
-        .header("Signature", &signature.signature)
-        .header("Date", signature.date);
+        .header("Accept", accept);
+
+    for (name, value) in signature.headers() {
+        request = request.header(name, value);
+    }
 
     if accept_any {
         request = request
@@ -365,10 +552,33 @@ pub async fn signed_get(profile: Actor, url: String, accept_any: bool) -> Result
     request.send().await.map_err(anyhow::Error::msg)
 }
 
-pub fn collection_fetcher() -> CollectionFetcher {
-    Box::new(|url: &str| {
+/// Builds the fetcher that `stream_all` uses to retrieve collection pages. As with
+/// `signed_get`, pages on domains this server doesn't federate with are refused; the blocked
+/// and allowed domains are read once, when the fetcher is built.
+pub async fn collection_fetcher<C: DbRunner>(conn: &C) -> CollectionFetcher {
+    let blocked = get_blocked_domains(conn).await;
+    let allowed = get_allowed_domains(conn).await;
+
+    Box::new(move |url: &str| {
         let url = url.to_string();
+        let domain = get_domain_from_url(url.clone());
+        let permitted = domain.as_ref().is_some_and(|domain| {
+            *domain == *crate::SERVER_NAME
+                || (blocked
+                    .as_ref()
+                    .is_ok_and(|blocked| !blocked.contains(domain))
+                    && allowed.as_ref().is_ok_and(|allowed| {
+                        allowed
+                            .as_ref()
+                            .is_none_or(|allowed| allowed.contains(domain))
+                    }))
+        });
+
         Box::pin(async move {
+            if !permitted {
+                return Err(anyhow!("Federation with {url} is not permitted").into());
+            }
+
             crate::HTTP_CLIENT
                 .get(&url)
                 .header("Content-Type", "application/activity+json")
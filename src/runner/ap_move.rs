@@ -0,0 +1,306 @@
+use anyhow::{anyhow, Result};
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::{ApActivity, ApAddress, ApContext, ApFollow, ApMove, MaybeMultiple};
+
+use crate::{
+    db::runner::DbRunner,
+    events::EventChannels,
+    models::{
+        activities::{
+            create_activity, get_activity_by_ap_id, revoke_activity_by_apid, ActivityTarget,
+            ActivityType, NewActivity,
+        },
+        actors::{get_actor_by_as_id, update_moved_to_by_as_id, Actor},
+        follows::{delete_follow, get_follow, get_followers_by_actor_id, move_follow, Follow},
+    },
+    retriever::process_remote_actor_retrieval,
+};
+
+use super::{get_inboxes, send_activity_task, send_to_inboxes, TaskError};
+
+/// Records a Move of the local `profile` to the `target` account, which must list the profile
+/// in `alsoKnownAs`. The profile is marked as moved, which makes it read-only.
+///
+/// The Move is delivered and local followers are moved by `send_move_task`.
+pub async fn create_move<C: DbRunner>(conn: &C, profile: Actor, target: String) -> Result<ApMove> {
+    if profile.ek_username.is_none() {
+        return Err(anyhow!("Only local actors can be moved"));
+    }
+
+    if let Some(moved_to) = profile.as_moved_to {
+        return Err(anyhow!("{} has already moved to {moved_to}", profile.as_id));
+    }
+
+    let target = get_move_target(conn, target)
+        .await
+        .map_err(|_| anyhow!("Failed to retrieve Move target"))?;
+
+    if target.as_id == profile.as_id {
+        return Err(anyhow!("An actor cannot move to itself"));
+    }
+
+    let aliases: Vec<String> =
+        serde_json::from_value(target.as_also_known_as.clone()).unwrap_or_default();
+
+    if !aliases.contains(&profile.as_id) {
+        return Err(anyhow!(
+            "{} does not list {} in alsoKnownAs",
+            target.as_id,
+            profile.as_id
+        ));
+    }
+
+    let ap_move = build_move(&profile, target.as_id.clone(), None);
+
+    let activity = create_activity(
+        conn,
+        NewActivity::try_from((
+            ApActivity::Move(ap_move.clone()),
+            Some(ActivityTarget::from(profile.clone())),
+        ))?
+        .link_actor(conn)
+        .await,
+    )
+    .await?;
+
+    update_moved_to_by_as_id(conn, profile.as_id.clone(), target.as_id).await?;
+
+    Ok(ApMove {
+        id: activity.ap_id,
+        ..ap_move
+    })
+}
+
+/// Delivers a Move created by `create_move` to the local actor's followers and moves its local
+/// followers. `ap_ids` holds the `[source, target, move]` ActivityPub IDs.
+pub async fn send_move_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    ap_ids: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let (source, target, id) = match ap_ids.as_slice() {
+        [source, target, id] => (source.clone(), target.clone(), id.clone()),
+        _ => {
+            log::error!("Move delivery requires a source, target and Move: {ap_ids:?}");
+            return Err(TaskError::TaskFailed);
+        }
+    };
+
+    let profile = get_actor_by_as_id(&conn, source.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve Move source {source}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let activity = ApActivity::Move(build_move(&profile, target.clone(), Some(id)));
+    let inboxes = get_inboxes(&conn, activity.clone(), profile.clone()).await;
+
+    send_to_inboxes(&conn, inboxes, profile, activity)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to send Move: {e:#?}");
+            TaskError::TaskFailed
+        })?;
+
+    move_followers_task(pool, None, vec![source, target]).await
+}
+
+/// Builds the Move of a local actor to `target`, addressed to the actor's followers
+fn build_move(profile: &Actor, target: String, id: Option<String>) -> ApMove {
+    ApMove {
+        context: Some(ApContext::activity_streams()),
+        actor: profile.as_id.clone().into(),
+        object: profile.as_id.clone().into(),
+        target: target.into(),
+        id,
+        to: profile
+            .as_followers
+            .clone()
+            .map(|followers| MaybeMultiple::Single(ApAddress::Address(followers)))
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Processes an account migration. `ap_ids` holds the `[source, target]` pair from the Move
+/// activity.
+///
+/// The target must list the source in `alsoKnownAs`. When it does, the source is marked as
+/// moved and each local follower of the source is moved to the target: a Follow is sent to
+/// the target, the old Follow is undone and the `follows` row is pointed at the target.
+pub async fn move_followers_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    ap_ids: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let (source, target) = match ap_ids.as_slice() {
+        [source, target] => (source.clone(), target.clone()),
+        _ => {
+            log::error!("Move task requires a source and target: {ap_ids:?}");
+            return Err(TaskError::TaskFailed);
+        }
+    };
+
+    let source = get_actor_by_as_id(&conn, source.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve Move source {source}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let target = get_move_target(&conn, target).await?;
+
+    let aliases: Vec<String> =
+        serde_json::from_value(target.as_also_known_as.clone()).unwrap_or_default();
+
+    if !aliases.contains(&source.as_id) {
+        log::warn!(
+            "Ignoring Move of {} to {}: target does not list source in alsoKnownAs",
+            source.as_id,
+            target.as_id
+        );
+        return Ok(());
+    }
+
+    update_moved_to_by_as_id(&conn, source.as_id.clone(), target.as_id.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to mark {} as moved: {e}", source.as_id);
+            TaskError::TaskFailed
+        })?;
+
+    let followers = get_followers_by_actor_id(&conn, source.id, None)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve followers of {}: {e}", source.as_id);
+            TaskError::TaskFailed
+        })?;
+
+    for (follow, follower) in followers
+        .into_iter()
+        .filter(|(_, follower)| follower.ek_username.is_some())
+    {
+        let old_follow_ap_id = follow.follow_activity_ap_id.clone();
+
+        let ap_ids = match move_follower(&conn, follow, follower.clone(), &target).await {
+            Ok(ap_ids) => ap_ids,
+            Err(e) => {
+                log::error!("Failed to move {} to {}: {e}", follower.as_id, target.as_id);
+                continue;
+            }
+        };
+
+        if let Err(e) = send_activity_task(pool.clone(), None, ap_ids).await {
+            log::error!("Failed to send Move activities for {}: {e}", follower.as_id);
+            continue;
+        }
+
+        // The old Follow is revoked once its Undo has been sent
+        if let Some(old_follow_ap_id) = old_follow_ap_id {
+            if let Err(e) = revoke_activity_by_apid(&conn, old_follow_ap_id).await {
+                log::error!("Failed to revoke old Follow: {e}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Retrieves the Move target, refreshing remote actors so that `alsoKnownAs` is current
+async fn get_move_target<C: DbRunner>(conn: &C, target: String) -> Result<Actor, TaskError> {
+    if let Ok(actor) = get_actor_by_as_id(conn, target.clone()).await {
+        if actor.ek_username.is_some() {
+            return Ok(actor);
+        }
+    }
+
+    process_remote_actor_retrieval(conn, None, target.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve Move target {target}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    get_actor_by_as_id(conn, target.clone()).await.map_err(|e| {
+        log::error!("Failed to retrieve Move target {target}: {e}");
+        TaskError::TaskFailed
+    })
+}
+
+/// Moves a local follower's follow to the target, returning the ActivityPub IDs of the
+/// Follow and Undo activities to send.
+async fn move_follower<C: DbRunner>(
+    conn: &C,
+    follow: Follow,
+    follower: Actor,
+    target: &Actor,
+) -> Result<Vec<String>> {
+    let mut ap_ids = vec![];
+
+    if get_follow(conn, follower.as_id.clone(), target.as_id.clone())
+        .await
+        .is_ok()
+    {
+        // Already following the target; only the old follow needs to go
+        delete_follow(conn, follower.as_id.clone(), follow.leader_ap_id.clone()).await?;
+    } else {
+        let new_follow = ApFollow {
+            actor: follower.as_id.clone().into(),
+            object: target.as_id.clone().into(),
+            ..Default::default()
+        };
+
+        let new_follow = create_activity(
+            conn,
+            NewActivity::try_from((
+                ApActivity::Follow(new_follow),
+                Some(ActivityTarget::from(target.clone())),
+            ))?
+            .link_actor(conn)
+            .await,
+        )
+        .await?;
+
+        let new_follow_ap_id = new_follow
+            .ap_id
+            .ok_or(anyhow!("Follow activity has no ap_id"))?;
+
+        move_follow(
+            conn,
+            follower.as_id.clone(),
+            follow.leader_ap_id.clone(),
+            target.clone(),
+            new_follow_ap_id.clone(),
+        )
+        .await?;
+
+        ap_ids.push(new_follow_ap_id);
+    }
+
+    if let Some(old_follow_ap_id) = follow.follow_activity_ap_id {
+        if let Some((old_follow, _, _, _)) = get_activity_by_ap_id(conn, old_follow_ap_id).await? {
+            let undo = create_activity(
+                conn,
+                NewActivity::from((
+                    old_follow,
+                    ActivityType::Undo,
+                    ApAddress::Address(follower.as_id.clone()),
+                ))
+                .link_actor(conn)
+                .await,
+            )
+            .await?;
+
+            if let Some(undo_ap_id) = undo.ap_id {
+                ap_ids.push(undo_ap_id);
+            }
+        }
+    }
+
+    Ok(ap_ids)
+}
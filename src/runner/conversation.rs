@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use deadpool_diesel::postgres::Pool;
+use futures_lite::StreamExt;
+use jdt_activity_pub::{ActivityPub, ApActivity, ApCollection, Referenceable};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::db::runner::DbRunner;
+use crate::events::EventChannels;
+use crate::helper::get_domain_from_url;
+use crate::models::actors::{guaranteed_actor, Actor};
+use crate::models::instances::is_domain_permitted;
+use crate::models::objects::{get_object_by_as_id, Object};
+use crate::retriever::{collection_fetcher, signed_get};
+
+use super::note::{fetch_remote_object, handle_object};
+use super::TaskError;
+
+// How long a thread is left alone after a backfill before it may be fetched again
+const BACKFILL_CACHE_TTL: Duration = Duration::from_secs(600);
+// The minimum spacing between requests made to any one instance
+const INSTANCE_FETCH_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_ANCESTORS: usize = 50;
+const MAX_FETCHES: usize = 200;
+const MAX_COLLECTION_PAGES: usize = 10;
+
+// Threads backfilled within BACKFILL_CACHE_TTL, keyed by the requested ActivityPub ID
+static BACKFILLED: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(Default::default);
+// The earliest time the next request may be made to each instance
+static NEXT_FETCH: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(Default::default);
+
+/// Records a backfill of the thread, returning false if one was started within
+/// BACKFILL_CACHE_TTL
+fn claim_backfill(as_id: &str) -> bool {
+    let now = Instant::now();
+    let mut backfilled = BACKFILLED.lock().unwrap();
+    backfilled.retain(|_, started| now.duration_since(*started) < BACKFILL_CACHE_TTL);
+
+    if backfilled.contains_key(as_id) {
+        return false;
+    }
+
+    backfilled.insert(as_id.to_string(), now);
+    true
+}
+
+/// Waits until a request may be made to the domain, reserving the next slot so that
+/// concurrent backfills share the limit
+async fn wait_for_instance(domain: &str) {
+    let delay = {
+        let now = Instant::now();
+        let mut next_fetch = NEXT_FETCH.lock().unwrap();
+        next_fetch.retain(|_, next| *next > now);
+
+        let slot = next_fetch.get(domain).copied().unwrap_or(now);
+        next_fetch.insert(domain.to_string(), slot + INSTANCE_FETCH_INTERVAL);
+        slot.saturating_duration_since(now)
+    };
+
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Reads an ActivityPub ID from a property that may hold a reference, an embedded
+/// object, or an array of either
+fn reference(value: &Option<Value>) -> Option<String> {
+    match value.as_ref()? {
+        Value::String(id) => Some(id.clone()),
+        Value::Object(object) => object.get("id")?.as_str().map(str::to_string),
+        Value::Array(values) => reference(&values.first().cloned()),
+        _ => None,
+    }
+}
+
+fn item_reference(item: ActivityPub) -> Option<String> {
+    match item {
+        ActivityPub::Object(object) => object.reference(),
+        ActivityPub::Activity(ApActivity::Create(create)) => create.object.reference(),
+        ActivityPub::Reference(id) => Some(id),
+        _ => None,
+    }
+}
+
+struct Backfill<'a, C: DbRunner> {
+    conn: &'a C,
+    profile: Actor,
+    // Replies collections already walked; handle_object skips these as well
+    visited: HashSet<String>,
+    fetches: usize,
+    added: Vec<String>,
+}
+
+impl<C: DbRunner> Backfill<'_, C> {
+    /// Reserves a request to the domain of the URL, returning false if the URL is local,
+    /// the domain isn't permitted, or the fetch budget is spent
+    async fn reserve(&mut self, url: &str) -> bool {
+        let Some(domain) = get_domain_from_url(url.to_string()) else {
+            return false;
+        };
+
+        if domain == *crate::SERVER_NAME
+            || self.fetches >= MAX_FETCHES
+            || !is_domain_permitted(self.conn, domain.clone()).await
+        {
+            return false;
+        }
+
+        self.fetches += 1;
+        wait_for_instance(&domain).await;
+        true
+    }
+
+    /// Returns the object, retrieving it if it isn't stored
+    async fn object(&mut self, as_id: String) -> Option<Object> {
+        if let Ok(object) = get_object_by_as_id(self.conn, as_id.clone()).await {
+            return Some(object);
+        }
+
+        if !self.reserve(&as_id).await {
+            return None;
+        }
+
+        let object = fetch_remote_object(self.conn, as_id.clone(), self.profile.clone())
+            .await
+            .map_err(|e| log::debug!("Failed to backfill {as_id}: {e}"))
+            .ok()?;
+
+        // The replies are walked here, under the rate limit, rather than by handle_object
+        if let Some(replies) = reference(&object.as_replies) {
+            self.visited.insert(replies);
+        }
+
+        let object = handle_object(self.conn, object, &mut self.visited, 0)
+            .await
+            .map_err(|e| log::debug!("Failed to process {as_id}: {e}"))
+            .ok()?;
+
+        self.added.push(as_id);
+        Some(object)
+    }
+
+    /// Returns the IDs of the items in the collection at the URL
+    async fn collection(&mut self, url: String) -> Vec<String> {
+        if !self.reserve(&url).await {
+            return vec![];
+        }
+
+        let collection = match signed_get(self.conn, self.profile.clone(), url, false).await {
+            Ok(resp) if matches!(resp.status(), StatusCode::ACCEPTED | StatusCode::OK) => {
+                resp.json::<ApCollection>().await.ok()
+            }
+            _ => None,
+        };
+
+        let Some(collection) = collection else {
+            return vec![];
+        };
+
+        collection
+            .stream_all_with_limits(
+                collection_fetcher(self.conn).await,
+                MAX_COLLECTION_PAGES,
+                MAX_FETCHES,
+            )
+            .filter_map(|item| item.ok().and_then(item_reference))
+            .collect()
+            .await
+    }
+
+    /// Retrieves the objects in the root's `context` collection, returning false if the
+    /// context isn't a collection that can be retrieved
+    async fn context(&mut self, root: &Object) -> bool {
+        let Some(context) = reference(&root.as_context).filter(|id| id.starts_with("https://"))
+        else {
+            return false;
+        };
+
+        let items = self.collection(context).await;
+
+        for as_id in &items {
+            self.object(as_id.clone()).await;
+        }
+
+        !items.is_empty()
+    }
+
+    /// Walks down the `replies` collections from the root, retrieving missing replies
+    async fn replies(&mut self, root: Object) {
+        let mut queue = VecDeque::from([root]);
+
+        while let Some(object) = queue.pop_front() {
+            let Some(replies) = reference(&object.as_replies) else {
+                continue;
+            };
+
+            if !self.visited.insert(replies.clone()) {
+                continue;
+            }
+
+            for as_id in self.collection(replies).await {
+                if let Some(reply) = self.object(as_id).await {
+                    queue.push_back(reply);
+                }
+            }
+        }
+    }
+}
+
+/// Retrieves the posts missing from the thread containing the object: its ancestors
+/// through `inReplyTo`, then the root's `context` collection or, failing that, its
+/// `replies`. The params are the object's ID and the username of the local user who
+/// opened the thread; that user's clients are sent the IDs of the added posts through
+/// the event stream.
+pub async fn conversation_backfill_task(
+    pool: Pool,
+    channels: Option<EventChannels>,
+    params: Vec<String>,
+) -> Result<(), TaskError> {
+    let (as_id, username) = match params.as_slice() {
+        [as_id, username] => (as_id.clone(), username.clone()),
+        _ => {
+            log::error!("Conversation backfill requires an object and a user: {params:?}");
+            return Err(TaskError::TaskFailed);
+        }
+    };
+
+    if !claim_backfill(&as_id) {
+        log::debug!("Conversation recently backfilled: {as_id}");
+        return Ok(());
+    }
+
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let mut backfill = Backfill {
+        conn: &conn,
+        profile: guaranteed_actor(&conn, None).await,
+        visited: HashSet::new(),
+        fetches: 0,
+        added: vec![],
+    };
+
+    let Some(mut root) = backfill.object(as_id.clone()).await else {
+        return Ok(());
+    };
+
+    for _ in 0..MAX_ANCESTORS {
+        let Some(parent) = reference(&root.as_in_reply_to) else {
+            break;
+        };
+
+        match backfill.object(parent).await {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+
+    if !backfill.context(&root).await {
+        backfill.replies(root).await;
+    }
+
+    let added = backfill.added;
+    log::debug!("Backfilled {} posts into conversation {as_id}", added.len());
+
+    if let (Some(mut channels), false) = (channels, added.is_empty()) {
+        channels.send_to_user(
+            &username,
+            json!({
+                "type": "ConversationBackfill",
+                "conversation": as_id,
+                "objects": added,
+            })
+            .to_string(),
+        );
+    }
+
+    Ok(())
+}
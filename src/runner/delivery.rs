@@ -0,0 +1,239 @@
+use super::health::PROBE_INTERVAL_MINUTES;
+use super::{process_inbox, LogMessage, TaskError};
+use crate::db::runner::DbRunner;
+use crate::events::EventChannels;
+use crate::models::activities::add_log_by_as_id;
+use crate::models::actors::{get_actor, Actor};
+use crate::models::delivery_jobs::{
+    claim_due_delivery_jobs, defer_delivery_job, mark_delivery_delivered, mark_delivery_failed,
+    DeliveryJob,
+};
+use crate::models::instances::{
+    get_unreachable_domains, is_domain_permitted, record_instance_delivery,
+};
+use crate::signing::{record_signature_scheme, signature_schemes_by_url};
+use chrono::{DateTime, Duration, Utc};
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::ApAddress;
+use rand::Rng;
+use std::collections::HashMap;
+use url::Url;
+
+const RETRY_BATCH_SIZE: i64 = 50;
+const BASE_RETRY_DELAY_SECONDS: i64 = 60;
+const MAX_RETRY_DELAY_SECONDS: i64 = 12 * 60 * 60;
+
+/// How long a job is reserved while a delivery attempt is in flight
+pub fn delivery_lease() -> DateTime<Utc> {
+    Utc::now() + Duration::minutes(10)
+}
+
+/// Exponential backoff from one minute, capped at twelve hours, with up to 25% jitter
+/// so that retries to an instance that was down don't all land at once.
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    let delay = (BASE_RETRY_DELAY_SECONDS << exponent).min(MAX_RETRY_DELAY_SECONDS);
+    let jitter = rand::thread_rng().gen_range(0..=delay / 4);
+    Duration::seconds(delay + jitter)
+}
+
+// Client errors won't improve with retries, except for timeouts and rate limiting;
+// a code of -1 indicates a connection failure
+fn is_retryable(code: i32) -> bool {
+    !(400..500).contains(&code) || code == 408 || code == 429
+}
+
+pub fn inbox_domain(inbox: &str) -> Option<String> {
+    Url::parse(inbox)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Records the outcome of a delivery attempt on its job, scheduling a retry or marking
+/// the job permanently failed once DELIVERY_MAX_ATTEMPTS is reached. The instance's
+/// health counters are updated as well, along with the signature scheme it accepted.
+pub async fn record_delivery_attempt<C: DbRunner>(conn: &C, job: &DeliveryJob, log: &LogMessage) {
+    let code = log.code.unwrap_or(-1);
+    let delivered = (200..300).contains(&code);
+
+    if let Some(domain) = inbox_domain(&job.inbox) {
+        // Any response short of a server error shows that the instance is up
+        let reachable = code > 0 && code < 500;
+
+        if let Err(e) = record_instance_delivery(
+            conn,
+            domain.clone(),
+            delivered,
+            reachable,
+            *crate::INSTANCE_UNREACHABLE_THRESHOLD,
+        )
+        .await
+        {
+            log::error!("Failed to record delivery health for {domain}: {e}");
+        }
+    }
+
+    if let (true, Some(scheme)) = (delivered, log.signature) {
+        record_signature_scheme(conn, &job.inbox, scheme).await;
+    }
+
+    let result = if delivered {
+        mark_delivery_delivered(conn, job.id, code).await
+    } else {
+        let attempts = job.attempts + 1;
+        let retry_at = (is_retryable(code) && attempts < *crate::DELIVERY_MAX_ATTEMPTS)
+            .then(|| Utc::now() + retry_delay(attempts));
+
+        if retry_at.is_none() {
+            log::warn!(
+                "Delivery of {} to {} failed permanently after {attempts} attempts",
+                job.activity_ap_id,
+                job.inbox
+            );
+        }
+
+        mark_delivery_failed(
+            conn,
+            job.id,
+            (code > 0).then_some(code),
+            log.response.clone(),
+            retry_at,
+        )
+        .await
+    };
+
+    if let Err(e) = result {
+        log::error!("Failed to record delivery attempt for job {}: {e}", job.id);
+    }
+}
+
+/// Attempts redelivery of pending jobs whose backoff has elapsed. This is driven by the
+/// tasks scheduler; initial delivery happens in send_to_inboxes.
+pub async fn retry_deliveries_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    _params: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+    let client = crate::HTTP_CLIENT.clone();
+    let mut senders: HashMap<i32, Actor> = HashMap::new();
+
+    loop {
+        let jobs = claim_due_delivery_jobs(&conn, RETRY_BATCH_SIZE, delivery_lease())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to claim delivery jobs: {e}");
+                TaskError::TaskFailed
+            })?;
+
+        if jobs.is_empty() {
+            break;
+        }
+
+        log::info!("Retrying {} deliveries", jobs.len());
+
+        let unreachable = get_unreachable_domains(&conn).await.unwrap_or_default();
+        let inboxes: Vec<String> = jobs.iter().map(|job| job.inbox.clone()).collect();
+        let schemes = signature_schemes_by_url(&conn, &inboxes).await;
+
+        let mut handles = vec![];
+        for job in jobs {
+            // Don't spend attempts on instances that are known to be down; their jobs wait
+            // until the health probe finds the instance reachable again
+            if inbox_domain(&job.inbox).is_some_and(|domain| unreachable.contains(&domain)) {
+                // Instances that stay down are given up on, like any other failed delivery
+                if job.created_at < Utc::now() - Duration::days(*crate::DELIVERY_MAX_DEFERRAL_DAYS)
+                {
+                    log::warn!(
+                        "Delivery of {} to {} failed permanently: instance unreachable",
+                        job.activity_ap_id,
+                        job.inbox
+                    );
+
+                    if let Err(e) = mark_delivery_failed(
+                        &conn,
+                        job.id,
+                        None,
+                        Some("instance unreachable".to_string()),
+                        None,
+                    )
+                    .await
+                    {
+                        log::error!("Failed to record delivery failure for job {}: {e}", job.id);
+                    }
+                    continue;
+                }
+
+                if let Err(e) = defer_delivery_job(
+                    &conn,
+                    job.id,
+                    Utc::now() + Duration::minutes(PROBE_INTERVAL_MINUTES),
+                    "instance unreachable".to_string(),
+                )
+                .await
+                {
+                    log::error!("Failed to defer delivery job {}: {e}", job.id);
+                }
+                continue;
+            }
+
+            // Jobs queued before a domain was blocked or removed from the allowlist
+            if let Some(domain) = inbox_domain(&job.inbox) {
+                if !is_domain_permitted(&conn, domain).await {
+                    if let Err(e) = mark_delivery_failed(
+                        &conn,
+                        job.id,
+                        None,
+                        Some("federation not permitted".to_string()),
+                        None,
+                    )
+                    .await
+                    {
+                        log::error!("Failed to record delivery failure for job {}: {e}", job.id);
+                    }
+                    continue;
+                }
+            }
+
+            let sender = match senders.get(&job.sender_id) {
+                Some(sender) => sender.clone(),
+                None => match get_actor(&conn, job.sender_id).await {
+                    Ok(sender) => {
+                        senders.insert(job.sender_id, sender.clone());
+                        sender
+                    }
+                    Err(e) => {
+                        log::error!("Failed to retrieve sender for job {}: {e}", job.id);
+                        continue;
+                    }
+                },
+            };
+
+            let client = client.clone();
+            let schemes = schemes.get(&job.inbox).cloned().unwrap_or_default();
+            handles.push(tokio::spawn(async move {
+                let log = process_inbox(
+                    ApAddress::Address(job.inbox.clone()),
+                    job.body.clone(),
+                    sender,
+                    client,
+                    schemes,
+                )
+                .await;
+                (job, log)
+            }));
+        }
+
+        for handle in handles {
+            if let Ok((job, log)) = handle.await {
+                record_delivery_attempt(&conn, &job, &log).await;
+
+                if let Ok(entry) = serde_json::to_value(vec![&log]) {
+                    let _ = add_log_by_as_id(&conn, job.activity_ap_id.clone(), entry).await;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,522 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use deadpool_diesel::postgres::Pool;
+use serde::Serialize;
+
+use crate::{
+    db::runner::DbRunner,
+    events::EventChannels,
+    models::{
+        activities::delete_activities_by_domain_pattern,
+        actors::delete_actors_by_domain_pattern,
+        cache::delete_cache_items_by_server_pattern,
+        delivery_jobs::delete_pending_delivery_jobs_by_domain_pattern,
+        follows::delete_follows_by_domain_pattern,
+        instances::{
+            get_instance_by_domain_name, get_moderated_instances, set_moderation, Instance,
+            ModerationLevel,
+        },
+        objects::delete_objects_by_domain_pattern,
+    },
+};
+
+use super::TaskError;
+
+/// The header Mastodon writes to and expects in `domain_blocks.csv`
+const CSV_HEADER: &str =
+    "#domain,#severity,#reject_media,#reject_reports,#public_comment,#obfuscate";
+
+/// The severity of a Mastodon domain block. Mastodon models rejecting media and reports
+/// as separate flags, so `Noop` is used for domains that only have those applied.
+#[derive(Serialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Suspend,
+    Silence,
+    Noop,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Suspend => write!(f, "suspend"),
+            Severity::Silence => write!(f, "silence"),
+            Severity::Noop => write!(f, "noop"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Severity {
+    type Error = anyhow::Error;
+
+    fn try_from(severity: &str) -> Result<Self, Self::Error> {
+        match severity.trim().to_lowercase().as_str() {
+            "suspend" | "" => Ok(Severity::Suspend),
+            "silence" => Ok(Severity::Silence),
+            "noop" => Ok(Severity::Noop),
+            _ => Err(anyhow!("unknown severity: {severity}")),
+        }
+    }
+}
+
+/// A row of a Mastodon `domain_blocks.csv` file
+#[derive(Serialize, Clone, Debug)]
+pub struct DomainBlock {
+    pub domain: String,
+    pub severity: Severity,
+    pub reject_media: bool,
+    pub reject_reports: bool,
+    pub public_comment: Option<String>,
+    pub obfuscate: bool,
+}
+
+impl DomainBlock {
+    pub fn moderation_levels(&self) -> Vec<ModerationLevel> {
+        [
+            (self.severity == Severity::Silence, ModerationLevel::Silence),
+            (self.reject_media, ModerationLevel::RejectMedia),
+            (self.reject_reports, ModerationLevel::RejectReports),
+            (self.severity == Severity::Suspend, ModerationLevel::Suspend),
+        ]
+        .into_iter()
+        .filter_map(|(applied, level)| applied.then_some(level))
+        .collect()
+    }
+}
+
+impl From<Instance> for DomainBlock {
+    fn from(instance: Instance) -> Self {
+        let severity = if instance.blocked {
+            Severity::Suspend
+        } else if instance.silenced {
+            Severity::Silence
+        } else {
+            Severity::Noop
+        };
+
+        DomainBlock {
+            domain: instance.domain_name,
+            severity,
+            reject_media: instance.reject_media,
+            reject_reports: instance.reject_reports,
+            public_comment: instance.public_comment,
+            obfuscate: false,
+        }
+    }
+}
+
+/// A change to the moderation levels of a domain made, or to be made, by an import
+#[derive(Serialize, Clone, Debug)]
+pub struct DomainBlockChange {
+    pub domain: String,
+    pub before: Vec<ModerationLevel>,
+    pub after: Vec<ModerationLevel>,
+}
+
+impl DomainBlockChange {
+    pub fn is_change(&self) -> bool {
+        self.before != self.after
+    }
+
+    /// Whether the domain is suspended by this change and wasn't already
+    pub fn is_new_suspend(&self) -> bool {
+        self.after.contains(&ModerationLevel::Suspend)
+            && !self.before.contains(&ModerationLevel::Suspend)
+    }
+}
+
+/// The number of rows removed for a suspended domain by `purge_domain`
+#[derive(Default, Debug)]
+pub struct DomainPurge {
+    pub deliveries: usize,
+    pub objects: usize,
+    pub activities: usize,
+    pub follows: usize,
+    pub actors: usize,
+    pub cache_items: usize,
+}
+
+/// Removes the queued deliveries to a suspended domain and, unless `keep_content` is set,
+/// its objects, activities, follows, actors and cached media. A failed step is logged and
+/// counted as nothing removed so that the remaining steps still run.
+pub async fn purge_domain<C: DbRunner>(conn: &C, domain: &str, keep_content: bool) -> DomainPurge {
+    let mut purge = DomainPurge {
+        deliveries: delete_pending_delivery_jobs_by_domain_pattern(conn, domain.to_string())
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to delete queued deliveries to {domain}: {e}");
+                0
+            }),
+        ..Default::default()
+    };
+
+    if keep_content {
+        return purge;
+    }
+
+    purge.objects = delete_objects_by_domain_pattern(conn, domain.to_string())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to delete objects from {domain}: {e}");
+            0
+        });
+    purge.activities = delete_activities_by_domain_pattern(conn, domain.to_string())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to delete activities from {domain}: {e}");
+            0
+        });
+    purge.follows = delete_follows_by_domain_pattern(conn, domain.to_string())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to delete follows from {domain}: {e}");
+            0
+        });
+    purge.actors = delete_actors_by_domain_pattern(conn, domain.to_string())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to delete actors from {domain}: {e}");
+            0
+        });
+    purge.cache_items = delete_cache_items_by_server_pattern(conn, domain.to_string())
+        .await
+        .map(|items| items.len())
+        .unwrap_or_else(|e| {
+            log::error!("Failed to delete cache items from {domain}: {e}");
+            0
+        });
+
+    purge
+}
+
+// Splits a line into fields, handling quoted fields with escaped quotes. Quoted fields
+// spanning lines aren't supported; Mastodon doesn't write newlines into comments.
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Parses a Mastodon `domain_blocks.csv` file. Files without a header are read as a list
+/// of domains to suspend, as older versions of Mastodon export them.
+pub fn parse_domain_blocks(csv: &str) -> Result<Vec<DomainBlock>> {
+    let mut lines = csv
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .peekable();
+
+    let columns: Vec<String> = match lines.next_if(|line| line.starts_with('#')) {
+        Some(header) => parse_csv_line(header)
+            .into_iter()
+            .map(|column| column.trim().trim_start_matches('#').to_lowercase())
+            .collect(),
+        None => vec!["domain".to_string()],
+    };
+
+    let index = |name: &str| columns.iter().position(|column| column == name);
+    let domain_index = index("domain").ok_or(anyhow!("CSV has no #domain column"))?;
+
+    lines
+        .enumerate()
+        .map(|(number, line)| {
+            let fields = parse_csv_line(line);
+            let field = |name: &str| {
+                index(name)
+                    .and_then(|i| fields.get(i))
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+            };
+            let flag = |name: &str| field(name).is_some_and(|field| field == "true");
+
+            let domain = fields
+                .get(domain_index)
+                .map(|domain| domain.trim().to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .ok_or(anyhow!("Missing domain on row {}", number + 1))?;
+
+            Ok(DomainBlock {
+                domain,
+                severity: Severity::try_from(field("severity").unwrap_or_default())?,
+                reject_media: flag("reject_media"),
+                reject_reports: flag("reject_reports"),
+                public_comment: field("public_comment").map(str::to_string),
+                obfuscate: flag("obfuscate"),
+            })
+        })
+        .collect()
+}
+
+/// Writes domain blocks in the Mastodon `domain_blocks.csv` format
+pub fn format_domain_blocks(blocks: &[DomainBlock]) -> String {
+    let mut csv = format!("{CSV_HEADER}\n");
+
+    for block in blocks {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            escape_csv_field(&block.domain),
+            block.severity,
+            block.reject_media,
+            block.reject_reports,
+            escape_csv_field(block.public_comment.as_deref().unwrap_or_default()),
+            block.obfuscate
+        ));
+    }
+
+    csv
+}
+
+/// Returns the moderated instances as domain blocks for export
+pub async fn export_domain_blocks<C: DbRunner>(conn: &C) -> Vec<DomainBlock> {
+    get_moderated_instances(conn)
+        .await
+        .into_iter()
+        .map(DomainBlock::from)
+        .collect()
+}
+
+/// Applies the moderation levels and public comments in the domain blocks, returning the
+/// change made to each domain. With `dry_run` set nothing is changed and the changes that
+/// would be made are returned. Domains that aren't in the blocks are left as they are.
+pub async fn import_domain_blocks<C: DbRunner>(
+    conn: &C,
+    blocks: Vec<DomainBlock>,
+    dry_run: bool,
+) -> Result<Vec<DomainBlockChange>> {
+    let mut changes = vec![];
+
+    for block in blocks {
+        // Obfuscated domains (e.g., "exa*ple.com") can't be matched to an instance
+        if block.domain.contains('*') {
+            log::warn!("Skipping obfuscated domain: {}", block.domain);
+            continue;
+        }
+
+        let instance = get_instance_by_domain_name(conn, block.domain.clone()).await?;
+        let change = DomainBlockChange {
+            domain: block.domain.clone(),
+            before: instance
+                .as_ref()
+                .map(Instance::moderation_levels)
+                .unwrap_or_default(),
+            after: block.moderation_levels(),
+        };
+        let comment_changed = block.public_comment.is_some()
+            && instance.and_then(|instance| instance.public_comment) != block.public_comment;
+
+        if !dry_run && (change.is_change() || comment_changed) {
+            set_moderation(
+                conn,
+                block.domain.clone(),
+                change.after.clone(),
+                block.public_comment,
+                None,
+            )
+            .await?;
+
+            if change.is_new_suspend() {
+                purge_domain(conn, &block.domain, false).await;
+            }
+        }
+
+        changes.push(change);
+    }
+
+    Ok(changes)
+}
+
+/// Re-imports the blocklist at DOMAIN_BLOCKLIST_PATH so that a shared blocklist can be
+/// subscribed to. This is driven by the tasks scheduler.
+pub async fn import_domain_blocklist_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    _params: Vec<String>,
+) -> Result<(), TaskError> {
+    let path = match crate::DOMAIN_BLOCKLIST_PATH.as_ref() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let csv = tokio::fs::read_to_string(path).await.map_err(|e| {
+        log::error!("Failed to read blocklist {}: {e}", path.display());
+        TaskError::TaskFailed
+    })?;
+
+    let blocks = parse_domain_blocks(&csv).map_err(|e| {
+        log::error!("Failed to parse blocklist {}: {e}", path.display());
+        TaskError::TaskFailed
+    })?;
+
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let changes = import_domain_blocks(&conn, blocks, false)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to import blocklist {}: {e}", path.display());
+            TaskError::TaskFailed
+        })?;
+
+    for change in changes.iter().filter(|change| change.is_change()) {
+        log::info!(
+            "Blocklist import changed {} from {:?} to {:?}",
+            change.domain,
+            change.before,
+            change.after
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_keep_commas_and_escaped_quotes() {
+        assert_eq!(
+            parse_csv_line(r#"example.com,"spam, mostly","said ""hi""",true"#),
+            vec!["example.com", "spam, mostly", r#"said "hi""#, "true"]
+        );
+    }
+
+    #[test]
+    fn empty_fields_are_kept() {
+        assert_eq!(parse_csv_line("example.com,,"), vec!["example.com", "", ""]);
+    }
+
+    #[test]
+    fn rows_are_read_by_header_column() {
+        let csv = "#domain,#severity,#reject_media,#reject_reports,#public_comment,#obfuscate\n\
+                   Example.com,silence,true,false,\"spam, mostly\",false\n";
+
+        let blocks = parse_domain_blocks(csv).unwrap();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].domain, "example.com");
+        assert_eq!(blocks[0].severity, Severity::Silence);
+        assert!(blocks[0].reject_media);
+        assert!(!blocks[0].reject_reports);
+        assert_eq!(blocks[0].public_comment.as_deref(), Some("spam, mostly"));
+        assert!(!blocks[0].obfuscate);
+    }
+
+    #[test]
+    fn columns_may_be_reordered_or_missing() {
+        let csv = "#severity,#domain\nnoop,example.com\n";
+
+        let blocks = parse_domain_blocks(csv).unwrap();
+
+        assert_eq!(blocks[0].domain, "example.com");
+        assert_eq!(blocks[0].severity, Severity::Noop);
+        assert!(!blocks[0].reject_media);
+        assert_eq!(blocks[0].public_comment, None);
+    }
+
+    #[test]
+    fn files_without_a_header_suspend_each_domain() {
+        let blocks = parse_domain_blocks("example.com\n\nexample.org\n").unwrap();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].domain, "example.org");
+        assert!(blocks
+            .iter()
+            .all(|block| block.severity == Severity::Suspend));
+    }
+
+    #[test]
+    fn empty_severity_defaults_to_suspend() {
+        let blocks = parse_domain_blocks("#domain,#severity\nexample.com,\n").unwrap();
+
+        assert_eq!(blocks[0].severity, Severity::Suspend);
+        assert_eq!(
+            blocks[0].moderation_levels(),
+            vec![ModerationLevel::Suspend]
+        );
+    }
+
+    #[test]
+    fn obfuscated_domains_are_parsed_with_their_flag() {
+        let blocks = parse_domain_blocks("#domain,#obfuscate\nexa*ple.com,true\n").unwrap();
+
+        assert_eq!(blocks[0].domain, "exa*ple.com");
+        assert!(blocks[0].obfuscate);
+    }
+
+    #[test]
+    fn unknown_severities_and_missing_domains_are_errors() {
+        assert!(parse_domain_blocks("#domain,#severity\nexample.com,limit\n").is_err());
+        assert!(parse_domain_blocks("#domain,#severity\n,silence\n").is_err());
+        assert!(parse_domain_blocks("#severity\nsilence\n").is_err());
+    }
+
+    #[test]
+    fn formatted_blocks_parse_back_to_the_same_blocks() {
+        let blocks = vec![DomainBlock {
+            domain: "example.com".to_string(),
+            severity: Severity::Silence,
+            reject_media: true,
+            reject_reports: false,
+            public_comment: Some(r#"spam, "mostly""#.to_string()),
+            obfuscate: false,
+        }];
+
+        let csv = format_domain_blocks(&blocks);
+        assert!(csv.starts_with(CSV_HEADER));
+
+        let parsed = parse_domain_blocks(&csv).unwrap();
+        assert_eq!(parsed[0].domain, blocks[0].domain);
+        assert_eq!(parsed[0].severity, blocks[0].severity);
+        assert_eq!(parsed[0].reject_media, blocks[0].reject_media);
+        assert_eq!(parsed[0].public_comment, blocks[0].public_comment);
+    }
+
+    #[test]
+    fn only_new_suspends_are_purged() {
+        let change =
+            |before: Vec<ModerationLevel>, after: Vec<ModerationLevel>| DomainBlockChange {
+                domain: "example.com".to_string(),
+                before,
+                after,
+            };
+
+        assert!(change(vec![], vec![ModerationLevel::Suspend]).is_new_suspend());
+        assert!(change(
+            vec![ModerationLevel::Silence],
+            vec![ModerationLevel::Suspend]
+        )
+        .is_new_suspend());
+        assert!(!change(
+            vec![ModerationLevel::Suspend],
+            vec![ModerationLevel::Suspend, ModerationLevel::RejectMedia]
+        )
+        .is_new_suspend());
+        assert!(!change(vec![ModerationLevel::Suspend], vec![]).is_new_suspend());
+        assert!(!change(vec![], vec![ModerationLevel::Silence]).is_new_suspend());
+    }
+}
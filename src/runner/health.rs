@@ -0,0 +1,82 @@
+use super::TaskError;
+use crate::events::EventChannels;
+use crate::models::delivery_jobs::resume_delivery_jobs_by_domain;
+use crate::models::instances::{get_unreachable_instances_for_probe, record_instance_probe};
+use chrono::{Duration, Utc};
+use deadpool_diesel::postgres::Pool;
+
+pub(crate) const PROBE_INTERVAL_MINUTES: i64 = 30;
+const PROBE_TIMEOUT_SECONDS: u64 = 10;
+
+/// Probes instances that have been marked unreachable and revives those that answer,
+/// along with the deliveries deferred while they were down. Each instance is probed at
+/// most once per PROBE_INTERVAL_MINUTES.
+pub async fn probe_unreachable_instances_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    _params: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let instances = get_unreachable_instances_for_probe(
+        &conn,
+        Utc::now() - Duration::minutes(PROBE_INTERVAL_MINUTES),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to retrieve unreachable instances: {e}");
+        TaskError::TaskFailed
+    })?;
+
+    if instances.is_empty() {
+        return Ok(());
+    }
+
+    log::info!("Probing {} unreachable instances", instances.len());
+
+    let handles: Vec<_> = instances
+        .into_iter()
+        .map(|instance| {
+            tokio::spawn(async move {
+                let reachable = probe_instance(&instance.domain_name).await;
+                (instance, reachable)
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        if let Ok((instance, reachable)) = handle.await {
+            if let Err(e) = record_instance_probe(&conn, instance.id, reachable).await {
+                log::error!("Failed to record probe of {}: {e}", instance.domain_name);
+                continue;
+            }
+
+            if reachable {
+                log::info!("Instance {} is reachable again", instance.domain_name);
+
+                match resume_delivery_jobs_by_domain(&conn, instance.domain_name.clone()).await {
+                    Ok(0) => {}
+                    Ok(resumed) => {
+                        log::info!("Resumed {resumed} deliveries to {}", instance.domain_name)
+                    }
+                    Err(e) => log::error!(
+                        "Failed to resume deliveries to {}: {e}",
+                        instance.domain_name
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// NodeInfo discovery is served by nearly all fediverse software and is cheap to answer
+async fn probe_instance(domain: &str) -> bool {
+    crate::HTTP_CLIENT
+        .get(format!("https://{domain}/.well-known/nodeinfo"))
+        .timeout(std::time::Duration::from_secs(PROBE_TIMEOUT_SECONDS))
+        .send()
+        .await
+        .is_ok_and(|response| !response.status().is_server_error())
+}
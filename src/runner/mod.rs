@@ -5,8 +5,11 @@ use crate::events::EventChannels;
 use crate::models::activities::get_activity_by_ap_id;
 use crate::models::activities::TryFromExtendedActivity;
 use crate::models::actors::tombstone_actor_by_as_id;
+use crate::models::delivery_jobs::{create_delivery_jobs, DeliveryJob, NewDeliveryJob};
 use crate::models::objects::tombstone_object_by_as_id;
+use crate::proofs::add_proof;
 use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
 use deadpool_diesel::postgres::Pool;
 use futures_lite::Future;
 use reqwest::Client;
@@ -18,8 +21,15 @@ use url::Url;
 
 use crate::retriever::get_actor;
 use crate::{
-    models::{activities::add_log_by_as_id, actors::Actor, instances::get_instance_inboxes},
-    signing::{Method, SignParams},
+    models::{
+        activities::add_log_by_as_id,
+        actors::Actor,
+        blocks::get_blocker_ap_ids,
+        instances::{
+            get_allowed_domains, get_instance_inboxes, get_unreachable_domains, SignatureScheme,
+        },
+    },
+    signing::{signature_rejected, signature_schemes_by_url, Method, SignParams},
 };
 use jdt_activity_pub::MaybeReference;
 use jdt_activity_pub::{ApActivity, ApActor, ApAddress, ApObject};
@@ -27,9 +37,20 @@ use jdt_activity_pub::{ApActivity, ApActor, ApAddress, ApObject};
 use self::user::get_follower_inboxes;
 
 pub mod announce;
+pub mod ap_move;
 pub mod cache;
+pub mod conversation;
+pub mod delivery;
+pub mod domain_blocks;
+pub mod health;
+pub mod nodeinfo;
 pub mod note;
+pub mod outbox_backfill;
 pub mod question;
+pub mod quote;
+pub mod reaction;
+pub mod relay;
+pub mod report;
 pub mod search_index;
 pub mod user;
 
@@ -75,15 +96,53 @@ pub struct LogMessage {
     pub code: Option<i32>,
     pub request: Option<RequestInfo>,
     pub response: Option<String>,
+    /// The signature scheme of the final attempt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureScheme>,
 }
 
 use tokio::task::JoinHandle;
 
+/// Delivers `body` to `inbox`, trying each of `schemes` in turn until the recipient
+/// accepts the signature
 pub async fn process_inbox(
     inbox: ApAddress,
     body: String,
     profile: Actor,
     client: Client,
+    schemes: Vec<SignatureScheme>,
+) -> LogMessage {
+    let mut log = LogMessage {
+        code: Some(-1),
+        request: None,
+        response: Some("no signature scheme to sign with".to_string()),
+        signature: None,
+    };
+
+    let attempts = schemes.len();
+    for (attempt, scheme) in schemes.into_iter().enumerate() {
+        log = send_to_inbox(&inbox, body.clone(), profile.clone(), &client, scheme).await;
+
+        let rejected = log
+            .code
+            .is_some_and(|code| signature_rejected(code.try_into().unwrap_or_default()));
+
+        if !rejected || attempt + 1 == attempts {
+            break;
+        }
+
+        log::debug!("{inbox} rejected a {scheme} signature; retrying");
+    }
+
+    log
+}
+
+async fn send_to_inbox(
+    inbox: &ApAddress,
+    body: String,
+    profile: Actor,
+    client: &Client,
+    scheme: SignatureScheme,
 ) -> LogMessage {
     log::debug!("Sending to inbox: {inbox}");
     let url = match Url::parse(&inbox.to_string()) {
@@ -93,6 +152,7 @@ pub async fn process_inbox(
                 code: Some(-1),
                 request: None,
                 response: Some(e.to_string()),
+                signature: None,
             }
         }
     };
@@ -102,6 +162,7 @@ pub async fn process_inbox(
         url,
         body: Some(body.clone()),
         method: Method::Post,
+        scheme,
     }) {
         Ok(sig) => sig,
         Err(e) => {
@@ -109,20 +170,21 @@ pub async fn process_inbox(
                 code: Some(-1),
                 request: None,
                 response: Some(e.to_string()),
+                signature: Some(scheme),
             }
         }
     };
 
-    let request = client
+    let mut request = client
         .post(inbox.to_string())
         .timeout(std::time::Duration::new(10, 0))
-        .header("Date", signature.date)
-        .header("Digest", signature.digest.unwrap())
-        .header("Signature", &signature.signature)
-        .header("Content-Type", "application/activity+json")
-        .body(body)
-        .build()
-        .unwrap();
+        .header("Content-Type", "application/activity+json");
+
+    for (name, value) in signature.headers() {
+        request = request.header(name, value);
+    }
+
+    let request = request.body(body).build().unwrap();
 
     let client_info = request_builder_to_info(&request);
 
@@ -135,6 +197,7 @@ pub async fn process_inbox(
                 code: Some(code.into()),
                 request: Some(client_info),
                 response: resp.text().await.ok(),
+                signature: Some(scheme),
             }
         }
         Err(e) => {
@@ -143,6 +206,7 @@ pub async fn process_inbox(
                 code: Some(-1),
                 request: Some(client_info),
                 response: Some(e.to_string()),
+                signature: Some(scheme),
             }
         }
     }
@@ -157,20 +221,94 @@ async fn process_all_inboxes<C: DbRunner>(
 ) -> Result<(), anyhow::Error> {
     let client = crate::HTTP_CLIENT.clone();
 
-    let handles: Vec<JoinHandle<LogMessage>> = inboxes
-        .into_iter()
-        .map(|inbox| {
-            let client = client.clone();
-            let body = body.clone();
-            let profile = profile.clone();
+    // Attach a FEP-8b32 proof so that recipients can authenticate the activity when it
+    // reaches them through a relay or is forwarded
+    let body = match serde_json::from_str(&body)
+        .map_err(anyhow::Error::from)
+        .and_then(|document| add_proof(document, &profile))
+    {
+        Ok(document) => document.to_string(),
+        Err(e) => {
+            log::error!("Failed to add proof to {as_id}: {e}");
+            body
+        }
+    };
 
-            tokio::spawn(process_inbox(inbox, body, profile, client))
-        })
+    // An actor can be reached through several addresses that share an inbox
+    let mut seen = HashSet::new();
+    let inboxes: Vec<String> = inboxes
+        .into_iter()
+        .map(|inbox| inbox.to_string())
+        .filter(|inbox| seen.insert(inbox.clone()))
         .collect();
 
+    let mut schemes = signature_schemes_by_url(conn, &inboxes).await;
+
+    // Instances that have stopped answering aren't tried now; their jobs wait for the
+    // health probe to find them reachable again
+    let unreachable = get_unreachable_domains(conn).await.unwrap_or_default();
+    let is_unreachable = |inbox: &str| {
+        delivery::inbox_domain(inbox).is_some_and(|domain| unreachable.contains(&domain))
+    };
+    let deferred_until = Utc::now() + Duration::minutes(health::PROBE_INTERVAL_MINUTES);
+
+    // Persist a job for each inbox before the first attempt so that failures are retried;
+    // if that fails, the activity is still sent, just without retries
+    let jobs: Vec<(String, Option<DeliveryJob>)> = match create_delivery_jobs(
+        conn,
+        inboxes
+            .iter()
+            .map(|inbox| NewDeliveryJob {
+                activity_ap_id: as_id.clone(),
+                inbox: inbox.clone(),
+                sender_id: profile.id,
+                body: body.clone(),
+                next_attempt_at: if is_unreachable(inbox) {
+                    deferred_until
+                } else {
+                    delivery::delivery_lease()
+                },
+            })
+            .collect(),
+    )
+    .await
+    {
+        Ok(jobs) => jobs
+            .into_iter()
+            .map(|job| (job.inbox.clone(), Some(job)))
+            .collect(),
+        Err(e) => {
+            log::error!("Failed to create delivery jobs for {as_id}: {e}");
+            inboxes.into_iter().map(|inbox| (inbox, None)).collect()
+        }
+    };
+
+    // Deferred jobs are left for the probe; without a job there is no later retry, so the
+    // send is attempted even if the instance is unreachable
+    let jobs = jobs
+        .into_iter()
+        .filter(|(inbox, job)| job.is_none() || !is_unreachable(inbox));
+
+    let mut handles: Vec<JoinHandle<(Option<DeliveryJob>, LogMessage)>> = vec![];
+    for (inbox, job) in jobs {
+        let client = client.clone();
+        let profile = profile.clone();
+        let body = body.clone();
+        let schemes = schemes.remove(&inbox).unwrap_or_default();
+
+        handles.push(tokio::spawn(async move {
+            let log =
+                process_inbox(ApAddress::Address(inbox), body, profile, client, schemes).await;
+            (job, log)
+        }));
+    }
+
     let mut logs = Vec::new();
     for handle in handles {
-        if let Ok(log) = handle.await {
+        if let Ok((job, log)) = handle.await {
+            if let Some(job) = job {
+                delivery::record_delivery_attempt(conn, &job, &log).await;
+            }
             logs.push(log);
         }
     }
@@ -226,7 +364,23 @@ async fn handle_recipients<C: DbRunner>(
     let actor = ApActor::from(sender.clone());
 
     if address.is_public() {
-        inboxes.extend(get_instance_inboxes(conn).await?.into_iter());
+        // Public activities go to the sender's followers and relays; sending to every
+        // known instance is left to deployments that opt in with BROADCAST_TO_INSTANCES
+        if *crate::BROADCAST_TO_INSTANCES {
+            inboxes.extend(get_instance_inboxes(conn).await?.into_iter());
+        }
+
+        inboxes.extend(get_follower_inboxes(conn, sender.clone()).await);
+
+        // Only posts by local actors are delivered to subscribed relays
+        if sender.ek_username.is_some() {
+            inboxes.extend(
+                relay::get_relay_inboxes(conn)
+                    .await?
+                    .into_iter()
+                    .map(ApAddress::Address),
+            );
+        }
     } else if let Some(followers) = actor.followers {
         if address.to_string() == followers {
             inboxes.extend(get_follower_inboxes(conn, sender.clone()).await);
@@ -264,6 +418,16 @@ pub async fn get_inboxes<C: DbRunner>(
                 (None, None)
             }
         }
+        ApActivity::Block(activity) => (Some(vec![ApAddress::Address(activity.object)]), None),
+        ApActivity::Move(activity) => (activity.to.option(), activity.cc.option()),
+        // Featured collection changes go to the sender's followers
+        ApActivity::Add(_) | ApActivity::Remove(_) => (
+            sender
+                .as_followers
+                .clone()
+                .map(|followers| vec![ApAddress::Address(followers)]),
+            None,
+        ),
         ApActivity::Undo(activity) => {
             if let MaybeReference::Actual(ref target_activity) = activity.object {
                 match target_activity {
@@ -275,6 +439,9 @@ pub async fn get_inboxes<C: DbRunner>(
                         }
                     }
                     ApActivity::Like(like) => (like.to.option(), None),
+                    ApActivity::Block(block) => {
+                        (Some(vec![ApAddress::Address(block.object.clone())]), None)
+                    }
                     ApActivity::Announce(announce) => {
                         (announce.cc.option(), Some(vec![ApAddress::get_public()]))
                     }
@@ -294,8 +461,18 @@ pub async fn get_inboxes<C: DbRunner>(
         (None, None) => None,
     };
 
+    // Actors who have blocked the sender are not sent its activities
+    let blockers: HashSet<String> = get_blocker_ap_ids(conn, sender.as_id.clone())
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
     if let Some(consolidated) = consolidated {
-        for address in consolidated.iter() {
+        for address in consolidated
+            .iter()
+            .filter(|address| !blockers.contains(&address.to_string()))
+        {
             if let Err(e) = handle_recipients(conn, &mut inboxes, &sender, address).await {
                 log::error!("Error handling recipient {address}: {e:?}");
                 // Decide if you want to stop or continue. For now, we continue.
@@ -303,7 +480,23 @@ pub async fn get_inboxes<C: DbRunner>(
         }
     }
 
-    inboxes.into_iter().collect()
+    // In ALLOWLIST_MODE nothing is sent to instances off the allowlist, even on error
+    let allowed = get_allowed_domains(conn)
+        .await
+        .unwrap_or_else(|_| Some(HashSet::new()));
+
+    inboxes
+        .into_iter()
+        .filter(|inbox| {
+            let domain = delivery::inbox_domain(&inbox.to_string());
+
+            allowed.as_ref().is_none_or(|allowed| {
+                domain.is_some_and(|domain| {
+                    domain == *crate::SERVER_NAME || allowed.contains(&domain)
+                })
+            })
+        })
+        .collect()
 }
 
 #[derive(Eq, PartialEq, Clone, Debug)]
@@ -0,0 +1,101 @@
+use super::TaskError;
+use crate::events::EventChannels;
+use crate::models::instances::{get_instances_for_crawl, record_instance_crawl};
+use crate::nodeinfo::{NodeInfo, NodeInfoLinks};
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use deadpool_diesel::postgres::Pool;
+use url::Url;
+
+const CRAWL_INTERVAL_HOURS: i64 = 24;
+const CRAWL_BATCH_SIZE: i64 = 100;
+const CRAWL_TIMEOUT_SECONDS: u64 = 10;
+
+/// Fetches the NodeInfo of known instances, so that the software they run and their size
+/// are recorded. Each instance is crawled at most once per CRAWL_INTERVAL_HOURS.
+pub async fn crawl_nodeinfo_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    _params: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let instances = get_instances_for_crawl(
+        &conn,
+        Utc::now() - Duration::hours(CRAWL_INTERVAL_HOURS),
+        CRAWL_BATCH_SIZE,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to retrieve instances to crawl: {e}");
+        TaskError::TaskFailed
+    })?;
+
+    if instances.is_empty() {
+        return Ok(());
+    }
+
+    log::info!("Crawling NodeInfo of {} instances", instances.len());
+
+    let handles: Vec<_> = instances
+        .into_iter()
+        .map(|instance| {
+            tokio::spawn(async move {
+                let nodeinfo = fetch_nodeinfo(&instance.domain_name).await;
+                (instance, nodeinfo)
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        if let Ok((instance, nodeinfo)) = handle.await {
+            let nodeinfo = nodeinfo
+                .map_err(|e| log::debug!("Failed to crawl {}: {e}", instance.domain_name))
+                .ok();
+
+            if let Err(e) = record_instance_crawl(&conn, instance.id, nodeinfo).await {
+                log::error!("Failed to record crawl of {}: {e}", instance.domain_name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Retrieves an instance's NodeInfo through its `/.well-known/nodeinfo` discovery document
+pub async fn fetch_nodeinfo(domain: &str) -> Result<NodeInfo> {
+    let timeout = std::time::Duration::from_secs(CRAWL_TIMEOUT_SECONDS);
+
+    let links = crate::HTTP_CLIENT
+        .get(format!("https://{domain}/.well-known/nodeinfo"))
+        .header("Accept", "application/json")
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<NodeInfoLinks>()
+        .await?;
+
+    let href = links
+        .get_href()
+        .ok_or(anyhow!("No supported NodeInfo version"))?;
+
+    // The document must be on the instance itself, so that an instance can't direct the
+    // crawler at other hosts (e.g., internal addresses)
+    let url = Url::parse(&href)?;
+    if url.scheme() != "https" || url.host_str() != Some(domain) || url.port().is_some() {
+        return Err(anyhow!(
+            "NodeInfo of {domain} is not on the instance: {href}"
+        ));
+    }
+
+    Ok(crate::HTTP_CLIENT
+        .get(url)
+        .header("Accept", "application/json")
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<NodeInfo>()
+        .await?)
+}
@@ -17,6 +17,7 @@ use crate::{FetchReplies, ANCHOR_RE, HTTP_CLIENT};
 use jdt_activity_pub::{ApHashtag, ApObject, Metadata};
 use serde_json::{json, Value};
 
+use super::quote::{handle_quote, quote_of};
 use super::TaskError;
 
 pub async fn fetch_remote_object<C: DbRunner>(
@@ -24,7 +25,7 @@ pub async fn fetch_remote_object<C: DbRunner>(
     id: String,
     profile: Actor,
 ) -> Result<Object> {
-    let response = signed_get(profile, id, false).await?;
+    let response = signed_get(conn, profile, id, false).await?;
 
     match response.status() {
         StatusCode::ACCEPTED | StatusCode::OK => {
@@ -32,14 +33,7 @@ pub async fn fetch_remote_object<C: DbRunner>(
             let sanitized = sanitize_json_fields(json);
             let ap_object = serde_json::from_value::<ApObject>(sanitized)?;
 
-            let cached_object = match ap_object {
-                ApObject::Note(note) => NewObject::from(note.cache(conn).await.clone()),
-                ApObject::Question(question) => NewObject::from(question.cache(conn).await.clone()),
-                ApObject::Article(article) => NewObject::from(article.cache(conn).await.clone()),
-                _ => return Err(anyhow!("Unsupported ApObject type")),
-            };
-
-            create_object(conn, cached_object).await
+            create_remote_object(conn, ap_object).await
         }
         StatusCode::GONE => {
             log::debug!("Remote Object no longer exists at source");
@@ -55,6 +49,18 @@ pub async fn fetch_remote_object<C: DbRunner>(
     }
 }
 
+/// Caches the media of a retrieved Note, Question, or Article and stores it
+pub async fn create_remote_object<C: DbRunner>(conn: &C, ap_object: ApObject) -> Result<Object> {
+    let cached_object = match ap_object {
+        ApObject::Note(note) => NewObject::from(note.cache(conn).await.clone()),
+        ApObject::Question(question) => NewObject::from(question.cache(conn).await.clone()),
+        ApObject::Article(article) => NewObject::from(article.cache(conn).await.clone()),
+        _ => return Err(anyhow!("Unsupported ApObject type")),
+    };
+
+    create_object(conn, cached_object).await
+}
+
 // TODO: This is problematic for links that point to large files; the filter tries
 // to account for some of that, but that's not really a solution. Maybe a whitelist?
 // Size limit is now enforced at 10MB.
@@ -185,6 +191,10 @@ pub async fn handle_object<C: DbRunner>(
             .unwrap_or(object);
     }
 
+    if let Some(quoted) = quote_of(&object.as_tag) {
+        object = handle_quote(conn, object, quoted).await;
+    }
+
     let ap_object: ApObject = object.clone().try_into()?;
     let profile = guaranteed_actor(conn, None).await;
 
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::{
+    ActivityPub, ApActivity, ApCollection, ApCreate, ApObject, Collectible, MaybeReference,
+};
+
+use crate::db::runner::DbRunner;
+use crate::events::EventChannels;
+use crate::helper::get_domain_from_url;
+use crate::models::activities::{
+    create_activity, get_activity_by_ap_id, ActivityTarget, NewActivity,
+};
+use crate::models::actors::{get_actor_by_as_id, guaranteed_actor, Actor};
+use crate::models::objects::{get_object_by_as_id, Object};
+use crate::retriever::{get_remote_collection, get_remote_collection_page};
+
+use super::note::{create_remote_object, fetch_remote_object, handle_object};
+use super::TaskError;
+
+const MAX_PAGES: usize = 10;
+
+/// Retrieves the recent posts in a newly followed actor's outbox so that they appear in
+/// the home timeline, stopping after OUTBOX_BACKFILL_ITEMS posts or at the first post older
+/// than OUTBOX_BACKFILL_DAYS. Nothing is sent to the event stream, so clients aren't
+/// notified of the older posts.
+pub async fn backfill_outbox_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    actor_ids: Vec<String>,
+) -> Result<(), TaskError> {
+    let max_items = *crate::OUTBOX_BACKFILL_ITEMS;
+
+    if max_items == 0 {
+        return Ok(());
+    }
+
+    let actor_id = actor_ids.first().ok_or(TaskError::TaskFailed)?.clone();
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let actor = get_actor_by_as_id(&conn, actor_id.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve actor {actor_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let outbox = get_remote_collection(&conn, None, actor.as_outbox.clone())
+        .await
+        .map_err(|e| {
+            log::debug!("Failed to retrieve outbox of {actor_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let cutoff = Utc::now() - Duration::days(*crate::OUTBOX_BACKFILL_DAYS);
+    let profile = guaranteed_actor(&conn, None).await;
+    let mut page = next_page(&conn, outbox.first.clone())
+        .await
+        .or(Some(outbox));
+    let mut backfilled = 0;
+
+    'pages: for _ in 0..MAX_PAGES {
+        let Some(current) = page.take() else {
+            break;
+        };
+
+        for item in current.items().unwrap_or_default() {
+            if backfilled >= max_items {
+                break 'pages;
+            }
+
+            let ActivityPub::Activity(ApActivity::Create(create)) = item else {
+                continue;
+            };
+
+            if create
+                .published
+                .as_ref()
+                .is_some_and(|published| **published < cutoff)
+            {
+                break 'pages;
+            }
+
+            match backfill_create(&conn, &actor, profile.clone(), create).await {
+                Ok(true) => backfilled += 1,
+                Ok(false) => (),
+                Err(e) => log::debug!("Failed to backfill post from {actor_id}: {e}"),
+            }
+        }
+
+        page = next_page(&conn, current.next).await;
+    }
+
+    log::info!("Backfilled {backfilled} posts from {actor_id}");
+
+    Ok(())
+}
+
+async fn next_page<C: DbRunner>(
+    conn: &C,
+    page: MaybeReference<Box<ApCollection>>,
+) -> Option<ApCollection> {
+    match page {
+        MaybeReference::Actual(page) => Some(*page),
+        MaybeReference::Reference(url) => get_remote_collection_page(conn, None, url).await.ok(),
+        _ => None,
+    }
+}
+
+/// Stores the Create and its object, returning false if the Create was already stored
+async fn backfill_create<C: DbRunner>(
+    conn: &C,
+    actor: &Actor,
+    profile: Actor,
+    create: ApCreate,
+) -> Result<bool> {
+    let create_id = create.id.clone().ok_or(anyhow!("Create has no ID"))?;
+
+    if create.actor.to_string() != actor.as_id {
+        return Err(anyhow!("Create {create_id} is not by the outbox owner"));
+    }
+
+    if get_activity_by_ap_id(conn, create_id).await?.is_some() {
+        return Ok(false);
+    }
+
+    let object_id = create
+        .object
+        .reference()
+        .ok_or(anyhow!("Create has no object"))?;
+
+    let object = match get_object_by_as_id(conn, object_id.clone()).await {
+        Ok(object) => object,
+        Err(_) => retrieve_object(conn, actor, profile, create.clone(), object_id).await?,
+    };
+
+    let mut activity = NewActivity::try_from((
+        ApActivity::Create(create.clone()),
+        Some(ActivityTarget::from(object.clone())),
+    ))?
+    .link_actor(conn)
+    .await;
+    activity.raw = Some(serde_json::to_value(&create)?);
+
+    create_activity(conn, activity).await?;
+
+    // handle_object retrieves link metadata, hashtags, and the author just as it does for
+    // posts received in the inbox. Marking the post's replies as visited keeps it from
+    // walking the reply tree, which would go well beyond the backfill's limits.
+    let mut visited: HashSet<String> = replies_of(&object).into_iter().collect();
+    handle_object(conn, object, &mut visited, 0).await?;
+
+    Ok(true)
+}
+
+fn replies_of(object: &Object) -> Option<String> {
+    match ApObject::try_from(object.clone()).ok()? {
+        ApObject::Note(note) => note.replies.reference(),
+        ApObject::Article(article) => article.replies.reference(),
+        ApObject::Question(question) => question.replies.reference(),
+        _ => None,
+    }
+}
+
+/// Stores the object embedded in the Create when it comes from the actor's own server,
+/// and otherwise retrieves it from its origin
+async fn retrieve_object<C: DbRunner>(
+    conn: &C,
+    actor: &Actor,
+    profile: Actor,
+    create: ApCreate,
+    object_id: String,
+) -> Result<Object> {
+    let same_origin =
+        get_domain_from_url(object_id.clone()) == get_domain_from_url(actor.as_id.clone());
+
+    match create.object {
+        MaybeReference::Actual(
+            ap_object @ (ApObject::Note(_) | ApObject::Question(_) | ApObject::Article(_)),
+        ) if same_origin => create_remote_object(conn, ap_object).await,
+        _ => fetch_remote_object(conn, object_id, profile).await,
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Result};
+use jdt_activity_pub::attachment::ApLinkType;
+use jdt_activity_pub::{ApLink, ApObject, ApTag, MaybeMultiple, PUBLIC_COLLECTION};
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+
+use crate::db::runner::DbRunner;
+use crate::helper::get_domain_from_url;
+use crate::models::actors::{get_actor_by_as_id, guaranteed_actor, Actor};
+use crate::models::follows::get_follow;
+use crate::models::objects::{get_object_by_as_id, update_tag, Object};
+use crate::retriever::signed_get;
+use crate::server::sanitize_json_fields;
+
+use super::note::{create_remote_object, fetch_remote_object};
+
+/// The `rel` of FEP-e232 links to quoted objects, introduced by Misskey
+pub const QUOTE_REL: &str = "https://misskey-hub.net/ns#_misskey_quote";
+const QUOTE_MEDIA_TYPE: &str =
+    "application/ld+json; profile=\"https://www.w3.org/ns/activitystreams\"";
+
+// The properties Mastodon (quote, quoteUri), Pleroma and Akkoma (quoteUrl), and Misskey
+// (_misskey_quote) use to identify the quoted object
+const QUOTE_PROPERTIES: [&str; 4] = ["quote", "quoteUri", "quoteUrl", "_misskey_quote"];
+
+pub fn quote_link(href: String) -> ApTag {
+    ApTag::Link(ApLink {
+        kind: ApLinkType::Link,
+        href: Some(href.clone()),
+        media_type: Some(QUOTE_MEDIA_TYPE.to_string()),
+        name: Some(format!("RE: {href}")),
+        rel: MaybeMultiple::Single(QUOTE_REL.to_string()),
+        url: None,
+    })
+}
+
+fn quote_link_href(tag: &Value) -> Option<String> {
+    let is_quote = match tag.get("rel")? {
+        Value::String(rel) => rel == QUOTE_REL,
+        Value::Array(rels) => rels.iter().any(|rel| rel == QUOTE_REL),
+        _ => false,
+    };
+
+    is_quote
+        .then(|| tag.get("href")?.as_str().map(str::to_string))
+        .flatten()
+}
+
+/// Returns the ID of the object quoted through a link in the tags
+pub fn quote_of(tags: &Option<Value>) -> Option<String> {
+    match tags.as_ref()? {
+        Value::Array(tags) => tags.iter().find_map(quote_link_href),
+        tag => quote_link_href(tag),
+    }
+}
+
+fn quote_property(object: &Map<String, Value>) -> Option<String> {
+    QUOTE_PROPERTIES
+        .iter()
+        .find_map(|property| object.get(*property)?.as_str().map(str::to_string))
+}
+
+/// Returns the ID of the object quoted by a JSON object through a quote property or link
+pub fn quote_of_json(object: &Value) -> Option<String> {
+    object
+        .as_object()
+        .and_then(quote_property)
+        .or_else(|| quote_of(&object.get("tag").cloned()))
+}
+
+/// Adds a quote link to the tags of an object that identifies its quote through one of the
+/// properties ApNote doesn't retain, so that the quote is stored with the object
+pub fn normalize_quote(object: &mut Map<String, Value>) {
+    let Some(href) = quote_property(object) else {
+        return;
+    };
+
+    // Objects that already link their quote are left untouched so that proofs still verify
+    if quote_of(&object.get("tag").cloned()).is_some() {
+        return;
+    }
+
+    let mut tags = match object.remove("tag") {
+        Some(Value::Array(tags)) => tags,
+        Some(Value::Null) | None => vec![],
+        Some(tag) => vec![tag],
+    };
+    tags.push(json!(quote_link(href)));
+
+    object.insert("tag".to_string(), Value::Array(tags));
+}
+
+/// Returns the actors and collections an object's FEP-044f interaction policy lets quote
+/// it without approval, or None if the object has no policy
+fn quote_approvals(object: &Value) -> Option<Vec<String>> {
+    let can_quote = object.get("interactionPolicy")?.get("canQuote")?;
+
+    // GoToSocial named automaticApproval `always` before Mastodon adopted the policy
+    Some(
+        ["automaticApproval", "always"]
+            .iter()
+            .filter_map(|key| can_quote.get(*key))
+            .flat_map(|approved| match approved {
+                Value::Array(approved) => approved.clone(),
+                approved => vec![approved.clone()],
+            })
+            .filter_map(|approved| approved.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+/// Returns whether an object's FEP-044f interaction policy lets the actor quote it without
+/// approval. Objects without a policy may be quoted. An approved followers collection is
+/// taken to be the author's, and admits the actor if it follows the author.
+pub async fn is_quote_permitted<C: DbRunner>(conn: &C, object: &Value, actor: &str) -> bool {
+    let Some(approvals) = quote_approvals(object) else {
+        return true;
+    };
+
+    if approvals
+        .iter()
+        .any(|approved| approved == actor || PUBLIC_COLLECTION.contains(approved))
+    {
+        return true;
+    }
+
+    let authors = match object.get("attributedTo") {
+        Some(Value::Array(authors)) => authors.clone(),
+        Some(author) => vec![author.clone()],
+        None => vec![],
+    };
+
+    for author in authors {
+        let Some(author) = author
+            .as_str()
+            .or_else(|| author.get("id")?.as_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+
+        let Ok(author) = get_actor_by_as_id(conn, author).await else {
+            continue;
+        };
+
+        let approves_followers = author
+            .as_followers
+            .as_ref()
+            .is_some_and(|followers| approvals.contains(followers));
+
+        if approves_followers
+            && get_follow(conn, actor.to_string(), author.as_id)
+                .await
+                .is_ok_and(|follow| follow.accepted)
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn is_local(as_id: &str) -> bool {
+    get_domain_from_url(as_id.to_string()).as_deref() == Some(crate::SERVER_NAME.as_str())
+}
+
+// Returns whether the author of a local object allows the actor to quote it
+async fn is_local_quote_permitted<C: DbRunner>(
+    conn: &C,
+    as_id: String,
+    actor: &str,
+) -> Result<bool> {
+    let object = get_object_by_as_id(conn, as_id).await?;
+    let attributed_to = object
+        .attributed_to()
+        .first()
+        .ok_or(anyhow!("Failed to identify attribution"))?
+        .clone();
+    let author = get_actor_by_as_id(conn, attributed_to).await?;
+
+    Ok(author.as_id == actor || author.ek_quotes_allowed)
+}
+
+/// Returns whether the local actor may quote the object, retrieving remote objects to check
+/// their interaction policy and storing them so that the quote can be displayed
+pub async fn authorize_quote<C: DbRunner>(conn: &C, actor: &Actor, as_id: String) -> Result<bool> {
+    if is_local(&as_id) {
+        return is_local_quote_permitted(conn, as_id, &actor.as_id).await;
+    }
+
+    let response = signed_get(conn, actor.clone(), as_id.clone(), false).await?;
+
+    if !matches!(response.status(), StatusCode::ACCEPTED | StatusCode::OK) {
+        return Err(anyhow!("Failed to retrieve {as_id}: {}", response.status()));
+    }
+
+    let json = sanitize_json_fields(response.json::<Value>().await?);
+
+    if !is_quote_permitted(conn, &json, &actor.as_id).await {
+        return Ok(false);
+    }
+
+    if get_object_by_as_id(conn, as_id).await.is_err() {
+        create_remote_object(conn, serde_json::from_value::<ApObject>(json)?).await?;
+    }
+
+    Ok(true)
+}
+
+/// Retrieves the object quoted by an inbound object. Quotes of local users who don't allow
+/// their posts to be quoted are removed from the object.
+pub async fn handle_quote<C: DbRunner>(conn: &C, object: Object, quoted: String) -> Object {
+    let attributed_to = object.attributed_to().first().cloned().unwrap_or_default();
+
+    if is_local(&quoted) {
+        if is_local_quote_permitted(conn, quoted.clone(), &attributed_to)
+            .await
+            .unwrap_or(true)
+        {
+            return object;
+        }
+
+        log::info!("Removing quote of {quoted} from {}", object.as_id);
+
+        let tag = object.as_tag.clone().map(|tag| match tag {
+            Value::Array(tags) => Value::Array(
+                tags.into_iter()
+                    .filter(|tag| quote_link_href(tag).is_none())
+                    .collect(),
+            ),
+            tag if quote_link_href(&tag).is_some() => json!([]),
+            tag => tag,
+        });
+
+        return update_tag(conn, object.id, tag).await.unwrap_or(object);
+    }
+
+    if get_object_by_as_id(conn, quoted.clone()).await.is_err() {
+        let profile = guaranteed_actor(conn, None).await;
+
+        if let Err(e) = fetch_remote_object(conn, quoted.clone(), profile).await {
+            log::debug!("Failed to retrieve quoted object {quoted}: {e}");
+        }
+    }
+
+    object
+}
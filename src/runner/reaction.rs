@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::{ApActor, ApAddress, ApContext, MaybeMultiple};
+use serde_json::json;
+
+use crate::{
+    db::runner::DbRunner,
+    events::EventChannels,
+    helper::get_activity_ap_id_from_uuid,
+    models::{
+        actors::{get_actor_by_as_id, Actor},
+        objects::get_object_by_as_id,
+        reactions::{delete_reaction_by_ap_id, get_reaction_by_ap_id, ApEmojiReact, Reaction},
+    },
+};
+
+use super::{process_all_inboxes, TaskError};
+
+/// Sends the activity to the inbox of the author of the reaction's object
+async fn deliver<C: DbRunner>(
+    conn: &C,
+    reaction: &Reaction,
+    body: String,
+    ap_id: String,
+) -> Result<()> {
+    let author = get_author(conn, reaction).await?;
+    let ap_author = ApActor::from(author);
+    let inbox = ap_author
+        .endpoints
+        .map(|endpoints| endpoints.shared_inbox)
+        .unwrap_or(ap_author.inbox);
+
+    let profile = get_actor_by_as_id(conn, reaction.actor_ap_id.clone()).await?;
+
+    process_all_inboxes(vec![ApAddress::Address(inbox)], body, profile, conn, ap_id).await
+}
+
+async fn get_author<C: DbRunner>(conn: &C, reaction: &Reaction) -> Result<Actor> {
+    let object = get_object_by_as_id(conn, reaction.object_ap_id.clone()).await?;
+    let attributed_to = object
+        .attributed_to()
+        .first()
+        .cloned()
+        .ok_or(anyhow!("Reaction object has no author"))?;
+
+    get_actor_by_as_id(conn, attributed_to).await
+}
+
+async fn emoji_react<C: DbRunner>(conn: &C, reaction: Reaction) -> Result<ApEmojiReact> {
+    let author = get_author(conn, &reaction).await?;
+
+    Ok(ApEmojiReact {
+        to: MaybeMultiple::Single(ApAddress::Address(author.as_id)),
+        ..ApEmojiReact::from(reaction)
+    })
+}
+
+/// Delivers the local reactions identified by their ActivityPub IDs as EmojiReact
+pub async fn send_reaction_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    ap_ids: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    for ap_id in ap_ids {
+        let reaction = get_reaction_by_ap_id(&conn, ap_id.clone())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to retrieve reaction {ap_id}: {e}");
+                TaskError::TaskFailed
+            })?;
+
+        let react = emoji_react(&conn, reaction.clone()).await.map_err(|e| {
+            log::error!("Failed to build EmojiReact {ap_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+        let body = serde_json::to_string(&react).map_err(|_| TaskError::TaskFailed)?;
+
+        deliver(&conn, &reaction, body, ap_id.clone())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send EmojiReact {ap_id}: {e}");
+                TaskError::TaskFailed
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Withdraws a local reaction: `params` holds the reaction's ActivityPub ID and the UUID of
+/// the Undo. The reaction is deleted once the Undo, which embeds the EmojiReact, is sent.
+pub async fn send_reaction_undo_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    params: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let [ap_id, uuid] = params.as_slice() else {
+        return Err(TaskError::TaskFailed);
+    };
+
+    let reaction = get_reaction_by_ap_id(&conn, ap_id.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve reaction {ap_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let react = emoji_react(&conn, reaction.clone()).await.map_err(|e| {
+        log::error!("Failed to build EmojiReact {ap_id}: {e}");
+        TaskError::TaskFailed
+    })?;
+
+    let undo_ap_id = get_activity_ap_id_from_uuid(uuid.clone());
+    let undo = json!({
+        "@context": ApContext::activity_streams(),
+        "type": "Undo",
+        "id": undo_ap_id,
+        "actor": reaction.actor_ap_id,
+        "to": react.to,
+        "object": react,
+    });
+
+    deliver(&conn, &reaction, undo.to_string(), undo_ap_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to send Undo of {ap_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    delete_reaction_by_ap_id(&conn, ap_id.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete reaction {ap_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::{
+    ApActivity, ApActor, ApAddress, ApContext, ApCreate, ApFollow, ApObject, ApUndo, MaybeMultiple,
+    MaybeReference,
+};
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    db::runner::DbRunner,
+    events::EventChannels,
+    helper::get_domain_from_url,
+    models::{
+        activities::{
+            create_activity, get_activity_by_ap_id, revoke_activity_by_apid, ActivityTarget,
+            ActivityType, NewActivity,
+        },
+        actors::{get_actor_by_as_id, guaranteed_actor, Actor},
+        follows::{
+            create_follow, delete_follow, get_follow, get_follows_by_follower_actor_id, Follow,
+            NewFollow,
+        },
+        instances::is_domain_permitted,
+        objects::get_object_by_as_id,
+    },
+    retriever::get_actor,
+    runner::note::{fetch_remote_object, handle_object},
+};
+
+use super::{send_to_inboxes, TaskError};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayState {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+impl fmt::Display for RelayState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelayState::Pending => write!(f, "pending"),
+            RelayState::Accepted => write!(f, "accepted"),
+            RelayState::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// A relay subscription, which is a follow of the relay's actor by the system actor
+#[derive(Serialize, Clone, Debug)]
+pub struct Relay {
+    pub actor: String,
+    pub inbox: Option<String>,
+    pub state: RelayState,
+    pub follow: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<(Follow, Option<Actor>)> for Relay {
+    fn from((follow, actor): (Follow, Option<Actor>)) -> Self {
+        let state = if follow.rejected {
+            RelayState::Rejected
+        } else if follow.accepted {
+            RelayState::Accepted
+        } else {
+            RelayState::Pending
+        };
+
+        Relay {
+            actor: follow.leader_ap_id,
+            inbox: actor.map(relay_inbox),
+            state,
+            follow: follow.follow_activity_ap_id,
+            created_at: follow.created_at,
+        }
+    }
+}
+
+// Relays are sent to through their shared inbox where they advertise one
+fn relay_inbox(actor: Actor) -> String {
+    let actor = ApActor::from(actor);
+
+    actor
+        .endpoints
+        .map(|endpoints| endpoints.shared_inbox)
+        .unwrap_or(actor.inbox)
+}
+
+/// Resolves a relay from the URL of its actor. The inbox URL that relays commonly publish
+/// is also accepted, in which case the actor is looked for at `/actor` on the same host.
+async fn resolve_relay<C: DbRunner>(conn: &C, url: String) -> Result<Actor> {
+    let actor = match get_actor(conn, url.clone(), None, true).await {
+        Ok(actor) => actor,
+        Err(e) if url.ends_with("/inbox") => {
+            let actor_url = Url::parse(&url)?.join("/actor")?.to_string();
+            log::debug!("Failed to retrieve relay at {url} ({e}), trying {actor_url}");
+            get_actor(conn, actor_url, None, true).await?
+        }
+        Err(e) => return Err(e),
+    };
+
+    let id = actor
+        .id
+        .ok_or(anyhow!("Relay actor has no ID"))?
+        .to_string();
+    get_actor_by_as_id(conn, id).await
+}
+
+pub async fn get_relays<C: DbRunner>(conn: &C) -> Result<Vec<Relay>> {
+    let system = guaranteed_actor(conn, None).await;
+
+    Ok(get_follows_by_follower_actor_id(conn, system.id)
+        .await?
+        .into_iter()
+        .map(Relay::from)
+        .collect())
+}
+
+/// Returns the inboxes of relays that have accepted the subscription
+pub async fn get_relay_inboxes<C: DbRunner>(conn: &C) -> Result<Vec<String>> {
+    Ok(get_relays(conn)
+        .await?
+        .into_iter()
+        .filter(|relay| relay.state == RelayState::Accepted)
+        .filter_map(|relay| relay.inbox)
+        .collect())
+}
+
+pub async fn is_relay<C: DbRunner>(conn: &C, actor_ap_id: String) -> bool {
+    let system = guaranteed_actor(conn, None).await;
+
+    get_follow(conn, system.as_id, actor_ap_id)
+        .await
+        .is_ok_and(|follow| follow.accepted && !follow.rejected)
+}
+
+/// Subscribes to a relay by sending it a Follow from the system actor. The subscription is
+/// pending until the relay answers with an Accept or Reject.
+pub async fn subscribe<C: DbRunner>(conn: &C, url: String) -> Result<Relay> {
+    let relay = resolve_relay(conn, url).await?;
+
+    let domain_name = get_domain_from_url(relay.as_id.clone()).ok_or(anyhow!("Invalid URL"))?;
+    if !is_domain_permitted(conn, domain_name.clone()).await {
+        return Err(anyhow!("Federation with {domain_name} is not permitted"));
+    }
+
+    let system = guaranteed_actor(conn, None).await;
+
+    if let Ok(follow) = get_follow(conn, system.as_id.clone(), relay.as_id.clone()).await {
+        return Ok(Relay::from((follow, Some(relay))));
+    }
+
+    let follow = ApFollow {
+        actor: system.as_id.clone().into(),
+        to: MaybeMultiple::Single(relay.as_id.clone().into()),
+        object: MaybeReference::Reference(relay.as_id.clone()),
+        ..Default::default()
+    };
+
+    let activity = create_activity(
+        conn,
+        NewActivity::try_from((
+            ApActivity::Follow(follow.clone()),
+            Some(ActivityTarget::from(relay.clone())),
+        ))?
+        .link_actor(conn)
+        .await,
+    )
+    .await?;
+
+    let follow_record = create_follow(
+        conn,
+        NewFollow {
+            follower_ap_id: system.as_id.clone(),
+            leader_ap_id: relay.as_id.clone(),
+            follow_activity_ap_id: activity.ap_id.clone(),
+            follower_actor_id: Some(system.id),
+            leader_actor_id: Some(relay.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Relays expect the object of a subscription Follow to be the Public collection
+    let follow = ApFollow {
+        context: Some(ApContext::default()),
+        id: activity.ap_id,
+        object: MaybeReference::Reference(ApAddress::get_public().to_string()),
+        ..follow
+    };
+
+    send_to_inboxes(
+        conn,
+        vec![ApAddress::Address(relay_inbox(relay.clone()))],
+        system,
+        ApActivity::Follow(follow),
+    )
+    .await?;
+
+    Ok(Relay::from((follow_record, Some(relay))))
+}
+
+/// Ends a relay subscription by sending an Undo of its Follow and removing the follow
+pub async fn unsubscribe<C: DbRunner>(conn: &C, url: String) -> Result<()> {
+    let system = guaranteed_actor(conn, None).await;
+
+    let (follow, relay) = get_follows_by_follower_actor_id(conn, system.id)
+        .await?
+        .into_iter()
+        .find(|(follow, actor)| {
+            follow.leader_ap_id == url
+                || actor
+                    .clone()
+                    .is_some_and(|actor| actor.as_inbox == url || relay_inbox(actor) == url)
+        })
+        .ok_or(anyhow!("Relay subscription not found: {url}"))?;
+
+    if let Some(follow_ap_id) = follow.follow_activity_ap_id.clone() {
+        if let Some((activity, _, _, _)) = get_activity_by_ap_id(conn, follow_ap_id.clone()).await?
+        {
+            let undo = create_activity(
+                conn,
+                NewActivity::from((
+                    activity,
+                    ActivityType::Undo,
+                    ApAddress::Address(system.as_id.clone()),
+                ))
+                .link_actor(conn)
+                .await,
+            )
+            .await?;
+
+            if let Some(relay) = relay {
+                let undo = ApUndo {
+                    context: Some(ApContext::default()),
+                    kind: Default::default(),
+                    actor: system.as_id.clone().into(),
+                    id: undo.ap_id,
+                    object: MaybeReference::Actual(ApActivity::Follow(ApFollow {
+                        actor: system.as_id.clone().into(),
+                        id: Some(follow_ap_id.clone()),
+                        object: MaybeReference::Reference(ApAddress::get_public().to_string()),
+                        ..Default::default()
+                    })),
+                };
+
+                if let Err(e) = send_to_inboxes(
+                    conn,
+                    vec![ApAddress::Address(relay_inbox(relay))],
+                    system.clone(),
+                    ApActivity::Undo(Box::new(undo)),
+                )
+                .await
+                {
+                    log::warn!("Failed to send Undo to relay {}: {e}", follow.leader_ap_id);
+                }
+            }
+
+            revoke_activity_by_apid(conn, follow_ap_id).await?;
+        }
+    }
+
+    delete_follow(conn, system.as_id, follow.leader_ap_id).await?;
+
+    Ok(())
+}
+
+/// Unwraps Announces from subscribed relays into Creates of the announced objects, so that
+/// relayed posts appear in the global timeline as posts by their authors rather than shares
+/// by the relay. The ID of each Announce is kept on its Create to record how it arrived.
+pub async fn relayed_announce_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    params: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    let (announce_ap_id, object_ap_id) = match params.as_slice() {
+        [announce_ap_id, object_ap_id] => (announce_ap_id.clone(), object_ap_id.clone()),
+        _ => return Err(TaskError::TaskFailed),
+    };
+
+    if get_object_by_as_id(&conn, object_ap_id.clone())
+        .await
+        .is_ok()
+    {
+        log::debug!("Relayed object already known: {object_ap_id}");
+        return Ok(());
+    }
+
+    let domain_name = get_domain_from_url(object_ap_id.clone()).ok_or(TaskError::TaskFailed)?;
+    if !is_domain_permitted(&conn, domain_name.clone()).await {
+        log::debug!("Federation with {domain_name} is not permitted");
+        return Err(TaskError::Prohibited);
+    }
+
+    let profile = guaranteed_actor(&conn, None).await;
+
+    let object = fetch_remote_object(&conn, object_ap_id.clone(), profile)
+        .await
+        .map_err(|e| {
+            log::warn!("Failed to retrieve relayed Object {object_ap_id}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let object = handle_object(&conn, object, &mut HashSet::<String>::new(), 0)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to handle relayed Object: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let create = ApObject::try_from(object.clone())
+        .and_then(ApCreate::try_from)
+        .map_err(|e| {
+            log::debug!("Relayed Object can not be unwrapped: {e}");
+            TaskError::TaskFailed
+        })?;
+
+    let activity = NewActivity::try_from((
+        ApActivity::Create(ApCreate {
+            id: Some(announce_ap_id),
+            ..create
+        }),
+        Some(ActivityTarget::from(object)),
+    ))
+    .map_err(|e| {
+        log::error!("Failed to build Activity: {e}");
+        TaskError::TaskFailed
+    })?
+    .link_actor(&conn)
+    .await;
+
+    create_activity(&conn, activity).await.map_err(|e| {
+        log::error!("Failed to create Activity: {e}");
+        TaskError::TaskFailed
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use deadpool_diesel::postgres::Pool;
+use jdt_activity_pub::{ApActor, ApAddress, ApContext, MaybeMultiple};
+use serde_json::json;
+
+use crate::{
+    db::runner::DbRunner,
+    events::EventChannels,
+    helper::get_activity_ap_id_from_uuid,
+    models::{
+        activities::{create_activity, ActivityType, NewActivity},
+        actors::{get_actor_by_as_id, get_actor_by_username, guaranteed_actor, Actor},
+        objects::get_object_by_as_id,
+        reports::{
+            add_report_note, create_report, get_report_by_uuid, set_report_forwarded,
+            update_report_assignee, update_report_status, ApFlag, ApFlagType, NewReport, Report,
+            ReportAction, ReportNote, ReportStatus,
+        },
+    },
+};
+
+use super::{process_all_inboxes, TaskError};
+
+/// Files a report by the local `profile` from a Flag posted to its outbox. The report is
+/// forwarded to the reported actor's server by `send_flag_task` when the Flag asks for it.
+pub async fn file_report<C: DbRunner>(conn: &C, profile: Actor, flag: ApFlag) -> Result<Report> {
+    let (target, objects) = resolve_flag_object(conn, flag.object.multiple()).await?;
+
+    if target.as_id == profile.as_id {
+        return Err(anyhow!("An actor cannot report itself"));
+    }
+
+    create_report(
+        conn,
+        NewReport {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            reporter_ap_id: profile.as_id,
+            reporter_actor_id: Some(profile.id),
+            target_ap_id: target.as_id,
+            target_actor_id: Some(target.id),
+            object_ap_ids: json!(objects),
+            comment: flag.content,
+            flag_ap_id: None,
+        },
+    )
+    .await?
+    .ok_or(anyhow!("Failed to create report"))
+}
+
+/// Records a Flag received from a remote server in the reports queue. A Flag that has
+/// already been recorded is ignored and None is returned.
+pub async fn receive_report<C: DbRunner>(conn: &C, flag: ApFlag) -> Result<Option<Report>> {
+    let (target, objects) = resolve_flag_object(conn, flag.object.multiple()).await?;
+    let reporter = get_actor_by_as_id(conn, flag.actor.to_string()).await.ok();
+
+    create_report(
+        conn,
+        NewReport {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            reporter_ap_id: flag.actor.to_string(),
+            reporter_actor_id: reporter.map(|reporter| reporter.id),
+            target_ap_id: target.as_id,
+            target_actor_id: Some(target.id),
+            object_ap_ids: json!(objects),
+            comment: flag.content,
+            flag_ap_id: flag.id,
+        },
+    )
+    .await
+}
+
+/// Resolves the IDs in a Flag's object to the reported actor and the IDs of the reported
+/// objects, which must all be attributed to that actor
+async fn resolve_flag_object<C: DbRunner>(
+    conn: &C,
+    ids: Vec<String>,
+) -> Result<(Actor, Vec<String>)> {
+    let mut target: Option<Actor> = None;
+    let mut objects = vec![];
+
+    for id in ids {
+        let actor = if let Ok(actor) = get_actor_by_as_id(conn, id.clone()).await {
+            actor
+        } else {
+            let object = get_object_by_as_id(conn, id.clone())
+                .await
+                .map_err(|_| anyhow!("Unknown report object: {id}"))?;
+            let attributed_to = object
+                .attributed_to()
+                .first()
+                .cloned()
+                .ok_or(anyhow!("Reported object has no author: {id}"))?;
+
+            objects.push(object.as_id);
+            get_actor_by_as_id(conn, attributed_to).await?
+        };
+
+        match &target {
+            Some(target) if target.as_id != actor.as_id => {
+                return Err(anyhow!("A report must concern a single actor"));
+            }
+            Some(_) => {}
+            None => target = Some(actor),
+        }
+    }
+
+    target
+        .map(|target| (target, objects))
+        .ok_or(anyhow!("Report does not name an actor"))
+}
+
+/// Applies an admin's change to the report identified by `uuid`
+pub async fn update_report<C: DbRunner>(
+    conn: &C,
+    uuid: String,
+    action: ReportAction,
+) -> Result<Report> {
+    match action {
+        ReportAction::Assign { assignee } => {
+            get_actor_by_username(conn, assignee.clone())
+                .await
+                .map_err(|_| anyhow!("Unknown local user: {assignee}"))?;
+            update_report_assignee(conn, uuid, Some(assignee)).await
+        }
+        ReportAction::Unassign => update_report_assignee(conn, uuid, None).await,
+        ReportAction::Resolve => update_report_status(conn, uuid, ReportStatus::Resolved).await,
+        ReportAction::Reopen => update_report_status(conn, uuid, ReportStatus::Open).await,
+        ReportAction::Note { content, author } => {
+            add_report_note(
+                conn,
+                uuid,
+                ReportNote {
+                    created_at: Utc::now(),
+                    author,
+                    content,
+                },
+            )
+            .await
+        }
+        ReportAction::Forward => forward_report(conn, get_report_by_uuid(conn, uuid).await?).await,
+    }
+}
+
+/// Sends the report to the reported actor's server as a Flag from the system actor, so that
+/// the reporter is not disclosed
+pub async fn forward_report<C: DbRunner>(conn: &C, report: Report) -> Result<Report> {
+    if report.forwarded {
+        return Err(anyhow!("Report has already been forwarded"));
+    }
+
+    let target = get_actor_by_as_id(conn, report.target_ap_id.clone()).await?;
+
+    if target.ek_username.is_some() {
+        return Err(anyhow!("Reports about local actors are not forwarded"));
+    }
+
+    let profile = guaranteed_actor(conn, None).await;
+    let ap_target = ApActor::from(target.clone());
+    let inbox = ap_target
+        .endpoints
+        .map(|endpoints| endpoints.shared_inbox)
+        .unwrap_or(ap_target.inbox);
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    let flag_ap_id = get_activity_ap_id_from_uuid(uuid.clone());
+
+    let mut object = vec![target.as_id.clone()];
+    object.extend(report.object_ap_ids());
+
+    let flag = ApFlag {
+        context: Some(ApContext::activity_streams()),
+        kind: ApFlagType::Flag,
+        id: Some(flag_ap_id.clone()),
+        actor: profile.as_id.clone().into(),
+        object: MaybeMultiple::Multiple(object),
+        content: report.comment.clone(),
+        forward: false,
+    };
+
+    create_activity(
+        conn,
+        NewActivity {
+            kind: ActivityType::Flag,
+            uuid,
+            actor: profile.as_id.clone(),
+            ap_to: Some(json!([target.as_id])),
+            target_ap_id: Some(target.as_id.clone()),
+            target_actor_id: Some(target.id),
+            ap_id: Some(flag_ap_id.clone()),
+            raw: Some(json!(flag)),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let body = serde_json::to_string(&flag)?;
+    process_all_inboxes(
+        vec![ApAddress::Address(inbox)],
+        body,
+        profile,
+        conn,
+        flag_ap_id.clone(),
+    )
+    .await?;
+
+    set_report_forwarded(conn, report.uuid, flag_ap_id).await
+}
+
+/// Forwards the reports identified by the UUIDs in `uuids`
+pub async fn send_flag_task(
+    pool: Pool,
+    _channels: Option<EventChannels>,
+    uuids: Vec<String>,
+) -> Result<(), TaskError> {
+    let conn = pool.get().await.map_err(|_| TaskError::TaskFailed)?;
+
+    for uuid in uuids {
+        let report = get_report_by_uuid(&conn, uuid.clone()).await.map_err(|e| {
+            log::error!("Failed to retrieve report {uuid}: {e}");
+            TaskError::TaskFailed
+        })?;
+
+        forward_report(&conn, report).await.map_err(|e| {
+            log::error!("Failed to forward report {uuid}: {e}");
+            TaskError::TaskFailed
+        })?;
+    }
+
+    Ok(())
+}
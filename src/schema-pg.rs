@@ -9,6 +9,10 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "actor_type"))]
     pub struct ActorType;
 
+    #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
+    #[diesel(postgres_type(name = "delivery_status"))]
+    pub struct DeliveryStatus;
+
     #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
     #[diesel(postgres_type(name = "notification_type"))]
     pub struct NotificationType;
@@ -16,6 +20,18 @@ pub mod sql_types {
     #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
     #[diesel(postgres_type(name = "object_type"))]
     pub struct ObjectType;
+
+    #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
+    #[diesel(postgres_type(name = "queue_status"))]
+    pub struct QueueStatus;
+
+    #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
+    #[diesel(postgres_type(name = "report_status"))]
+    pub struct ReportStatus;
+
+    #[derive(diesel::sql_types::SqlType, diesel::query_builder::QueryId)]
+    #[diesel(postgres_type(name = "signature_scheme"))]
+    pub struct SignatureScheme;
 }
 
 diesel::table! {
@@ -101,6 +117,26 @@ diesel::table! {
         ek_mls_storage -> Nullable<Text>,
         ek_mls_storage_hash -> Nullable<Text>,
         ek_muted_terms -> Jsonb,
+        as_moved_to -> Nullable<Text>,
+        ek_featured_items -> Jsonb,
+        as_assertion_method -> Jsonb,
+        ek_ed25519_private_key -> Nullable<Text>,
+        ek_previous_public_key -> Nullable<Jsonb>,
+        ek_key_rotated_at -> Nullable<Timestamptz>,
+        ek_quotes_allowed -> Bool,
+    }
+}
+
+diesel::table! {
+    blocks (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        blocker_ap_id -> Text,
+        blocked_ap_id -> Text,
+        block_activity_ap_id -> Nullable<Text>,
+        blocker_actor_id -> Nullable<Int4>,
+        blocked_actor_id -> Nullable<Int4>,
     }
 }
 
@@ -119,6 +155,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::DeliveryStatus;
+
+    delivery_jobs (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        activity_ap_id -> Text,
+        inbox -> Text,
+        sender_id -> Int4,
+        body -> Text,
+        status -> DeliveryStatus,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_attempt_at -> Nullable<Timestamptz>,
+        last_status_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        deferred_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     encrypted_sessions (id) {
         id -> Int4,
@@ -175,6 +233,9 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::SignatureScheme;
+
     instances (id) {
         id -> Int4,
         created_at -> Timestamptz,
@@ -184,6 +245,26 @@ diesel::table! {
         blocked -> Bool,
         last_message_at -> Timestamptz,
         shared_inbox -> Nullable<Text>,
+        delivery_success_count -> Int4,
+        delivery_failure_count -> Int4,
+        consecutive_failures -> Int4,
+        last_success_at -> Nullable<Timestamptz>,
+        unreachable -> Bool,
+        unreachable_since -> Nullable<Timestamptz>,
+        last_probed_at -> Nullable<Timestamptz>,
+        signature_scheme -> Nullable<SignatureScheme>,
+        allowed -> Bool,
+        silenced -> Bool,
+        reject_media -> Bool,
+        reject_reports -> Bool,
+        public_comment -> Nullable<Text>,
+        private_comment -> Nullable<Text>,
+        software_name -> Nullable<Text>,
+        software_version -> Nullable<Text>,
+        user_count -> Nullable<Int8>,
+        active_user_count -> Nullable<Int8>,
+        open_registrations -> Nullable<Bool>,
+        crawled_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -337,18 +418,23 @@ diesel::table! {
 }
 
 diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::QueueStatus;
+
     processing_queue (id) {
         id -> Int4,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
-        profile_id -> Int4,
-        ap_id -> Varchar,
-        ap_to -> Jsonb,
-        cc -> Nullable<Jsonb>,
-        attributed_to -> Varchar,
-        kind -> Varchar,
-        ap_object -> Jsonb,
-        processed -> Bool,
+        ap_id -> Nullable<Text>,
+        kind -> Text,
+        actor -> Text,
+        raw -> Jsonb,
+        status -> QueueStatus,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        locked_at -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+        object_ap_id -> Nullable<Text>,
     }
 }
 
@@ -368,6 +454,46 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    reactions (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        uuid -> Text,
+        ap_id -> Text,
+        actor_ap_id -> Text,
+        actor_id -> Nullable<Int4>,
+        object_ap_id -> Text,
+        object_id -> Nullable<Int4>,
+        content -> Text,
+        emoji -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ReportStatus;
+
+    reports (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        uuid -> Text,
+        reporter_ap_id -> Text,
+        reporter_actor_id -> Nullable<Int4>,
+        target_ap_id -> Text,
+        target_actor_id -> Nullable<Int4>,
+        object_ap_ids -> Jsonb,
+        comment -> Nullable<Text>,
+        flag_ap_id -> Nullable<Text>,
+        forwarded -> Bool,
+        status -> ReportStatus,
+        assigned_to -> Nullable<Text>,
+        resolved_at -> Nullable<Timestamptz>,
+        notes -> Jsonb,
+    }
+}
+
 diesel::table! {
     unprocessable (id) {
         id -> Int4,
@@ -390,14 +516,19 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(delivery_jobs -> actors (sender_id));
 diesel::joinable!(mls_group_conversations -> actors (actor_id));
 diesel::joinable!(olm_one_time_keys -> actors (profile_id));
+diesel::joinable!(reactions -> actors (actor_id));
+diesel::joinable!(reactions -> objects (object_id));
 diesel::joinable!(vault -> activities (activity_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     activities,
     actors,
+    blocks,
     cache,
+    delivery_jobs,
     encrypted_sessions,
     followers,
     follows,
@@ -412,7 +543,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     olm_one_time_keys,
     olm_sessions,
     processing_queue,
+    reactions,
     remote_encrypted_sessions,
+    reports,
     unprocessable,
     vault,
 );
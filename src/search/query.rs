@@ -1,7 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tantivy::collector::TopDocs;
-use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, RegexQuery, TermQuery};
 use tantivy::schema::*;
 use tantivy::{Index, IndexReader};
 
@@ -14,6 +14,8 @@ pub struct SearchContext {
     pub is_admin: bool,
     /// List of blocked actor IDs
     pub blocked_actors: Vec<String>,
+    /// List of domains hidden by moderation
+    pub hidden_domains: Vec<String>,
 }
 
 impl Default for SearchContext {
@@ -22,6 +24,7 @@ impl Default for SearchContext {
             user_id: None,
             is_admin: false,
             blocked_actors: Vec::new(),
+            hidden_domains: Vec::new(),
         }
     }
 }
@@ -77,6 +80,21 @@ pub struct SearchResults {
     pub actors: Vec<ActorSearchResult>,
 }
 
+/// Builds queries that exclude documents whose IDs are on any of the given domains
+fn hidden_domain_queries(
+    as_id_field: Field,
+    domains: &[String],
+) -> Result<Vec<(Occur, Box<dyn Query>)>> {
+    domains
+        .iter()
+        .map(|domain| {
+            let pattern = format!("https?://{}/.*", regex::escape(domain));
+            let query: Box<dyn Query> = Box::new(RegexQuery::from_pattern(&pattern, as_id_field)?);
+            Ok((Occur::MustNot, query))
+        })
+        .collect()
+}
+
 /// Search objects index
 /// Uses the provided cached reader to avoid creating new mmap handles per search
 pub fn search_objects(
@@ -170,6 +188,10 @@ pub fn search_objects(
         ));
     }
 
+    // Exclude content from hidden domains
+    let as_id_field = schema.get_field("as_id").unwrap();
+    boolean_queries.extend(hidden_domain_queries(as_id_field, &context.hidden_domains)?);
+
     // Exclude Tombstone objects (deleted content)
     let type_field = schema.get_field("type_facet").unwrap();
     let tombstone_facet = Facet::from("/Tombstone");
@@ -183,7 +205,6 @@ pub fn search_objects(
 
     // Extract results - different handling based on sort order
     let id_field = schema.get_field("id").unwrap();
-    let as_id_field = schema.get_field("as_id").unwrap();
     let type_field = schema.get_field("object_type").unwrap();
 
     let mut results = Vec::new();
@@ -278,7 +299,7 @@ pub fn search_actors(
     index: &Index,
     reader: &IndexReader,
     query_str: &str,
-    _context: &SearchContext,
+    context: &SearchContext,
     filters: &SearchFilters,
     limit: usize,
     offset: usize,
@@ -318,6 +339,19 @@ pub fn search_actors(
         Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
     ));
 
+    // Exclude blocked actors
+    let as_id_field = schema.get_field("as_id").unwrap();
+    for blocked_id in &context.blocked_actors {
+        let term = Term::from_field_text(as_id_field, blocked_id);
+        boolean_queries.push((
+            Occur::MustNot,
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+        ));
+    }
+
+    // Exclude actors from hidden domains
+    boolean_queries.extend(hidden_domain_queries(as_id_field, &context.hidden_domains)?);
+
     // Filter by local only if specified
     if filters.local_only {
         let local_field = schema.get_field("is_local").unwrap();
@@ -335,7 +369,6 @@ pub fn search_actors(
 
     // Extract results
     let id_field = schema.get_field("id").unwrap();
-    let as_id_field = schema.get_field("as_id").unwrap();
     let type_field = schema.get_field("actor_type").unwrap();
 
     let mut results = Vec::new();
@@ -60,16 +60,16 @@ mod tests {
     #[test]
     fn test_objects_schema_creation() {
         let schema = create_objects_schema();
-        assert!(schema.get_field("id").is_some());
-        assert!(schema.get_field("content").is_some());
-        assert!(schema.get_field("author_id").is_some());
+        assert!(schema.get_field("id").is_ok());
+        assert!(schema.get_field("content").is_ok());
+        assert!(schema.get_field("author_id").is_ok());
     }
 
     #[test]
     fn test_actors_schema_creation() {
         let schema = create_actors_schema();
-        assert!(schema.get_field("id").is_some());
-        assert!(schema.get_field("username").is_some());
-        assert!(schema.get_field("is_discoverable").is_some());
+        assert!(schema.get_field("id").is_ok());
+        assert!(schema.get_field("username").is_ok());
+        assert!(schema.get_field("is_discoverable").is_ok());
     }
 }
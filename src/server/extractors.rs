@@ -1,15 +1,20 @@
 use crate::{
-    blocklist::Permitted,
+    blocklist::{BlockList, Permitted},
     models::{
         actors::{get_actor_by_key_id_axum, get_actor_by_username_axum},
-        instances::{create_or_update_instance_axum, Instance},
+        instances::{
+            create_or_update_instance_axum, record_instance_signature_scheme, Instance,
+            SignatureScheme,
+        },
     },
+    retriever::{get_actor, resolve_public_key},
     server::AppState,
     signing::{
-        build_verify_string, verify_signature_crypto, Signed, VerificationError, VerificationType,
-        VerifyMapParams, VerifyParams,
+        build_signature_base, build_verify_string, content_digest_to_digest, get_key_id,
+        parse_signature_input, verify, verify_signature_crypto, Signed, VerificationError,
+        VerificationType, VerifyMapParams, VerifyParams,
     },
-    ASSIGNMENT_RE, DOMAIN_RE,
+    DOMAIN_RE,
 };
 use anyhow::{anyhow, Result};
 use axum::{
@@ -21,7 +26,6 @@ use axum::{
 use deadpool_diesel::postgres::Object as DbConnection;
 use jdt_activity_pub::ApActor;
 use serde_json::json;
-use std::collections::HashMap;
 use std::ops::Deref;
 
 fn get_header(parts: &Parts, header_name: &str) -> Option<String> {
@@ -32,6 +36,38 @@ fn get_header(parts: &Parts, header_name: &str) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Looks up the value of an RFC 9421 covered component. Multiple header field values
+/// are combined as a comma-separated list.
+fn get_component(parts: &Parts, name: &str) -> Option<String> {
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+
+    match name {
+        "@method" => Some(parts.method.to_string()),
+        "@target-uri" => Some(format!("https://{}{path_and_query}", *crate::SERVER_NAME)),
+        "@authority" => Some(crate::SERVER_NAME.to_lowercase()),
+        "@scheme" => Some("https".to_string()),
+        "@request-target" => Some(path_and_query.to_string()),
+        "@path" => Some(parts.uri.path().to_string()),
+        "@query" => Some(format!("?{}", parts.uri.query().unwrap_or_default())),
+        name if name.starts_with('@') => None,
+        name => {
+            let values = parts
+                .headers
+                .get_all(name)
+                .iter()
+                .map(|value| value.to_str().map(str::trim))
+                .collect::<Result<Vec<&str>, _>>()
+                .ok()?;
+
+            (!values.is_empty()).then(|| values.join(", "))
+        }
+    }
+}
+
 // 1. Define the new wrapper struct for the Axum extractor.
 #[derive(Debug)]
 pub struct AxumSigned(pub Signed);
@@ -45,16 +81,15 @@ impl Deref for AxumSigned {
     }
 }
 
-async fn update_instance_axum(conn: &DbConnection, signature: String) -> Result<Instance> {
-    let mut signature_map = HashMap::<String, String>::new();
-    for cap in ASSIGNMENT_RE.captures_iter(&signature) {
-        signature_map.insert(cap[1].to_string(), cap[2].to_string());
-    }
-    let key_id = signature_map
-        .get("keyId")
-        .ok_or(anyhow!("keyId not found in signature_map"))?;
+/// Records the instance that sent a verified request. An instance that signs with RFC 9421
+/// is taken to accept RFC 9421 signatures as well.
+async fn update_instance_axum(
+    conn: &DbConnection,
+    key_id: String,
+    scheme: SignatureScheme,
+) -> Result<Instance> {
     let domain_name = DOMAIN_RE
-        .captures(key_id)
+        .captures(&key_id)
         .ok_or(anyhow!("failed to retrieve key_id"))?[1]
         .to_string();
 
@@ -65,7 +100,14 @@ async fn update_instance_axum(conn: &DbConnection, signature: String) -> Result<
             .map(|endpoints| endpoints.shared_inbox)
     });
 
-    create_or_update_instance_axum(conn, (domain_name, shared_inbox).into()).await
+    let instance =
+        create_or_update_instance_axum(conn, (domain_name.clone(), shared_inbox).into()).await?;
+
+    if scheme == SignatureScheme::Rfc9421 {
+        record_instance_signature_scheme(conn, domain_name, scheme).await?;
+    }
+
+    Ok(instance)
 }
 
 async fn verify_axum(
@@ -95,7 +137,7 @@ async fn verify_axum(
         verify_signature_crypto(&public_key_pem, &signature_str, &verify_string)?;
 
         Ok(VerificationType::Local((Box::from(profile), params.digest)))
-    } else if let Ok(actor) = get_actor_by_key_id_axum(conn, key_id).await {
+    } else if let Ok(actor) = get_actor_by_key_id_axum(conn, key_id.clone()).await {
         let ap_actor = ApActor::from(actor.clone());
         let public_key_pem = ap_actor.clone().public_key.public_key_pem;
 
@@ -104,6 +146,12 @@ async fn verify_axum(
             Box::new(ap_actor),
             params.digest,
         )))
+    } else if let Ok((actor, public_key_pem)) = resolve_public_key(conn, key_id).await {
+        verify_signature_crypto(&public_key_pem, &signature_str, &verify_string)?;
+        Ok(VerificationType::Remote((
+            Box::new(ApActor::from(actor)),
+            params.digest,
+        )))
     } else {
         Err(VerificationError::ActorNotFound(params.into()))
     }
@@ -151,13 +199,25 @@ impl FromRequestParts<AppState> for AxumSigned {
         let path = path.trim_end_matches('&');
         let request_target = format!("{} {}", method.to_lowercase(), path);
 
+        // RFC 9421 signatures carry their creation time in Signature-Input
+        let signature_input = get_header(parts, "signature-input");
+
         let date = match get_header(parts, "date").or_else(|| get_header(parts, "enigmatick-date"))
         {
             Some(val) => val,
+            None if signature_input.is_some() => String::new(),
             None => return Ok(AxumSigned(Signed(false, VerificationType::None))),
         };
 
-        let digest = get_header(parts, "digest");
+        // An RFC 9421 digest is only trusted when the signature covers it
+        let digest = match &signature_input {
+            Some(signature_input) => parse_signature_input(signature_input)
+                .is_ok_and(|input| input.components.iter().any(|c| c == "content-digest"))
+                .then(|| get_header(parts, "content-digest"))
+                .flatten()
+                .and_then(|content_digest| content_digest_to_digest(&content_digest)),
+            None => get_header(parts, "digest"),
+        };
         let user_agent = get_header(parts, "user-agent");
         let content_length = get_header(parts, "content-length");
         let content_type = get_header(parts, "content-type");
@@ -169,8 +229,26 @@ impl FromRequestParts<AppState> for AxumSigned {
             1 => {
                 let signature = signature_vec[0].to_str().unwrap_or("").to_string();
 
+                let signature_base = match &signature_input {
+                    Some(signature_input) => Some(
+                        build_signature_base(signature_input, |name| get_component(parts, name))
+                            .map_err(|e| {
+                                log::debug!("Signature verification failed: {e}");
+                                SignedRejection::SignatureInvalid
+                            })?,
+                    ),
+                    None => None,
+                };
+
+                let scheme = if signature_input.is_some() {
+                    SignatureScheme::Rfc9421
+                } else {
+                    SignatureScheme::Cavage
+                };
+                let key_id = get_key_id(&signature, signature_input.as_deref());
+
                 let verify_params = VerifyMapParams {
-                    signature: signature.clone(),
+                    signature,
                     request_target,
                     host,
                     date,
@@ -178,6 +256,8 @@ impl FromRequestParts<AppState> for AxumSigned {
                     content_type,
                     content_length,
                     user_agent,
+                    signature_input,
+                    signature_base,
                 };
 
                 log::debug!("{verify_params}");
@@ -185,7 +265,9 @@ impl FromRequestParts<AppState> for AxumSigned {
                 match verify_axum(&conn, verify_params.clone()).await {
                     Ok(t) => {
                         log::debug!("Signature verification successful");
-                        let _ = update_instance_axum(&conn, signature).await;
+                        if let Some(key_id) = key_id {
+                            let _ = update_instance_axum(&conn, key_id, scheme).await;
+                        }
                         Ok(AxumSigned(Signed(true, t)))
                     }
                     Err(e) => match e {
@@ -225,13 +307,10 @@ impl FromRequestParts<AppState> for Permitted {
         };
 
         if let Ok(signature) = signature_header.to_str() {
-            let mut signature_map = HashMap::<String, String>::new();
-            for cap in ASSIGNMENT_RE.captures_iter(signature) {
-                signature_map.insert(cap[1].to_string(), cap[2].to_string());
-            }
+            let signature_input = get_header(parts, "signature-input");
 
-            if let Some(key_id) = signature_map.get("keyId") {
-                if let Some(domain_match) = DOMAIN_RE.captures(key_id) {
+            if let Some(key_id) = get_key_id(signature, signature_input.as_deref()) {
+                if let Some(domain_match) = DOMAIN_RE.captures(&key_id) {
                     let remote_domain = domain_match[1].to_string();
 
                     if state.block_list.is_blocked(remote_domain.clone()) {
@@ -245,3 +324,75 @@ impl FromRequestParts<AppState> for Permitted {
         Ok(Permitted(true))
     }
 }
+
+/// Applies the fetch policy to an ActivityPub GET and returns the ID of the requesting
+/// actor, if the request was signed. Requests signed by a blocked instance are refused,
+/// and in secure mode so are requests without a valid signature. A signature from an
+/// actor that isn't known yet is verified after retrieving the actor.
+pub async fn authorize_fetch(
+    conn: &DbConnection,
+    block_list: &BlockList,
+    signed: &Signed,
+) -> Result<Option<String>, StatusCode> {
+    let requester = match signed {
+        Signed(true, VerificationType::Local((profile, _))) => Some(profile.as_id.clone()),
+        Signed(true, VerificationType::Remote((actor, _))) => {
+            actor.id.as_ref().map(|id| id.to_string())
+        }
+        Signed(false, VerificationType::Deferred(params)) => {
+            verify_deferred(conn, block_list, *params.clone()).await?
+        }
+        _ => None,
+    };
+
+    if let Some(requester) = &requester {
+        if let Some(domain_match) = DOMAIN_RE.captures(requester) {
+            if block_list.is_blocked(domain_match[1].to_string()) {
+                log::warn!("Blocking fetch by {requester}");
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+    } else if *crate::SECURE_MODE {
+        log::debug!("Refusing unsigned fetch in secure mode");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    Ok(requester)
+}
+
+async fn verify_deferred(
+    conn: &DbConnection,
+    block_list: &BlockList,
+    params: VerifyMapParams,
+) -> Result<Option<String>, StatusCode> {
+    let Some(key_id) = get_key_id(&params.signature, params.signature_input.as_deref()) else {
+        return Ok(None);
+    };
+
+    // Blocked instances are refused before their actors are retrieved
+    if let Some(domain_match) = DOMAIN_RE.captures(&key_id) {
+        if block_list.is_blocked(domain_match[1].to_string()) {
+            log::warn!("Blocking fetch signed by {key_id}");
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    let actor_id = key_id
+        .split_once('#')
+        .map_or(key_id.as_str(), |(id, _)| id)
+        .to_string();
+
+    if let Err(e) = get_actor(conn, actor_id, None, true).await {
+        log::debug!("Unable to retrieve signer of fetch: {e}");
+        return Ok(None);
+    }
+
+    match verify(conn, params).await {
+        Ok(VerificationType::Remote((actor, _))) => Ok(actor.id.map(|id| id.to_string())),
+        Ok(_) => Ok(None),
+        Err(e) => {
+            log::debug!("Deferred fetch signature verification failed: {e}");
+            Ok(None)
+        }
+    }
+}
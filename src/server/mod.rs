@@ -14,8 +14,10 @@ pub mod extractors;
 mod retriever;
 mod routes;
 
+pub use routes::inbox::queue::spawn_inbox_workers;
 pub use routes::inbox::sanitize_json_fields;
 pub use routes::inbox::InboxView;
+pub use routes::objects::is_visible;
 
 // This struct will hold all shared state for the Axum part of the application.
 #[derive(Clone)]
@@ -56,6 +58,12 @@ pub async fn start() {
         search_index,
     };
 
+    // Drain the inbound activity queue in the background
+    spawn_inbox_workers(app_state.clone());
+
+    // Pick up instance block and allowlist changes made outside the server
+    app_state.block_list.spawn_reload(app_state.db_pool.clone());
+
     // Build the Axum router. We will add migrated routes here.
     // For now, a simple test route proves it's working.
 
@@ -111,10 +119,19 @@ pub async fn start() {
             get(routes::webfinger::axum_webfinger),
         )
         .route("/install", get(routes::instance::install_script))
+        .route(
+            "/.well-known/nodeinfo",
+            get(routes::nodeinfo::nodeinfo_links),
+        )
+        .route("/nodeinfo/{version}", get(routes::nodeinfo::nodeinfo))
         .route(
             "/api/{version}/instance",
             get(routes::instance::instance_information),
         )
+        .route(
+            "/api/{version}/instance/domain_blocks",
+            get(routes::instance::domain_moderation),
+        )
         // Encryption routes
         .route(
             "/api/instruments",
@@ -126,7 +143,12 @@ pub async fn start() {
             "/user/{username}",
             get(routes::user::person_get).post(routes::user::person_post),
         )
+        .route(
+            "/user/{username}/main-key/{key}",
+            get(routes::user::public_key_get),
+        )
         .route("/user/{username}/liked", get(routes::user::liked_get))
+        .route("/user/{username}/featured", get(routes::user::featured_get))
         .route(
             "/user/{username}/followers",
             get(routes::user::get_followers),
@@ -141,6 +163,10 @@ pub async fn start() {
             "/api/user/{username}/update/summary",
             post(routes::user::update_summary),
         )
+        .route(
+            "/api/user/{username}/update/quotes",
+            post(routes::user::update_quotes),
+        )
         .route(
             "/api/user/{username}/avatar",
             post(routes::user::upload_avatar),
@@ -190,6 +216,11 @@ pub async fn start() {
             "/api/user/{username}/remote/outbox",
             get(routes::remote::remote_outbox),
         )
+        .route("/api/remote/featured", get(routes::remote::remote_featured))
+        .route(
+            "/api/user/{username}/remote/featured",
+            get(routes::remote::remote_featured),
+        )
         .route(
             "/api/user/{username}/remote/keys",
             get(routes::remote::remote_keys),
@@ -204,7 +235,20 @@ pub async fn start() {
             "/api/user/{username}/muted-terms",
             get(routes::admin::get_muted_terms).post(routes::admin::manage_muted_terms),
         )
+        .route(
+            "/api/user/{username}/aliases",
+            get(routes::admin::get_aliases).post(routes::admin::manage_aliases),
+        )
         .route("/api/admin/memory", get(routes::admin::memory_stats))
+        .route(
+            "/api/admin/relays",
+            get(routes::admin::get_relays_api).post(routes::admin::manage_relays_api),
+        )
+        .route("/api/admin/reports", get(routes::admin::get_reports_api))
+        .route(
+            "/api/admin/reports/{uuid}",
+            get(routes::admin::get_report_api).post(routes::admin::update_report_api),
+        )
         // Client routes
         .route("/login", get(routes::client::client_login))
         .route("/signup", get(routes::client::client_signup))
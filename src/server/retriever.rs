@@ -2,7 +2,13 @@ use crate::db::runner::DbRunner;
 use crate::models::activities::get_activities_coalesced;
 use crate::models::activities::{get_outbox_count_by_actor_id, TimelineFilters};
 use crate::models::actors::Actor;
-use jdt_activity_pub::{ActivityPub, ApActivity, ApCollection, ApCollectionParams, ApObject};
+use crate::models::blocks::get_hidden_actor_ap_ids;
+use crate::models::objects::get_object_by_as_id;
+use crate::server::routes::objects::is_visible;
+use jdt_activity_pub::{
+    ActivityPub, ApActivity, ApCollection, ApCollectionParams, ApCollectionType, ApContext,
+    ApObject,
+};
 pub async fn outbox_collection<C: DbRunner>(
     conn: &C,
     profile: Actor,
@@ -24,15 +30,54 @@ pub async fn outbox_collection<C: DbRunner>(
     }))
 }
 
+/// Builds the actor's featured collection from the stored pinned objects, newest first.
+/// Pinned objects that are no longer stored or that `requester` may not see are skipped.
+pub async fn featured_collection<C: DbRunner>(
+    conn: &C,
+    actor: Actor,
+    requester: Option<String>,
+) -> ApCollection {
+    let mut ordered_items = vec![];
+
+    for item in actor.featured_items() {
+        if let Ok(object) = get_object_by_as_id(conn, item).await {
+            if !is_visible(conn, &object, requester.clone()).await {
+                continue;
+            }
+
+            if let Ok(object) = ApObject::try_from(object) {
+                ordered_items.push(ActivityPub::Object(object));
+            }
+        }
+    }
+
+    ApCollection {
+        context: Some(ApContext::activity_streams()),
+        kind: ApCollectionType::OrderedCollection,
+        id: actor.featured(),
+        total_items: Some(ordered_items.len() as i64),
+        ordered_items: Some(ordered_items),
+        ..Default::default()
+    }
+}
+
 pub async fn activities(
     conn: &impl DbRunner,
     limit: i32,
     min: Option<i64>,
     max: Option<i64>,
     requester: Option<Actor>,
-    filters: TimelineFilters,
+    mut filters: TimelineFilters,
     base_url: String,
 ) -> ApObject {
+    if let Some(requester) = &requester {
+        filters.blocked_actors.extend(
+            get_hidden_actor_ap_ids(conn, requester.as_id.clone())
+                .await
+                .unwrap_or_default(),
+        );
+    }
+
     //let server_url = format!("https://{}", *crate::SERVER_NAME);
     //let base_url = base_url.unwrap_or(format!("{server_url}/inbox?page=true&limit={limit}"));
 
@@ -1,18 +1,25 @@
 use crate::{
     admin::{self, NewUser},
-    models::actors::{
-        get_actor_by_as_id, get_muted_terms_by_username, guaranteed_actor,
-        update_muted_terms_by_username, Actor,
+    models::{
+        actors::{
+            get_also_known_as_by_username, get_muted_terms_by_username,
+            update_also_known_as_by_username, update_muted_terms_by_username, Actor,
+        },
+        reports::{get_report_by_uuid, get_reports, Report, ReportAction, ReportStatus},
     },
     retriever::get_actor,
+    runner::{
+        self,
+        relay::{get_relays, Relay},
+        user::send_actor_update_task,
+    },
     server::{extractors::AxumSigned, AppState},
 };
 use axum::{
-    extract::{rejection::JsonRejection, ConnectInfo, Path, State},
+    extract::{rejection::JsonRejection, ConnectInfo, Path, Query, State},
     http::StatusCode,
     Json,
 };
-use jdt_activity_pub::{ApActor, ApFollow, MaybeMultiple};
 use serde::Deserialize;
 use std::net::SocketAddr;
 
@@ -29,6 +36,47 @@ pub struct MutedTermsAction {
     pub terms: Vec<String>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AliasesActionType {
+    Add,
+    Remove,
+}
+
+#[derive(Deserialize)]
+pub struct AliasesAction {
+    pub action: AliasesActionType,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelaysActionType {
+    Add,
+    Remove,
+}
+
+#[derive(Deserialize)]
+pub struct RelaysAction {
+    pub action: RelaysActionType,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReportsQuery {
+    pub status: Option<String>,
+}
+
+// Only the local users named in ADMIN_USERS may use the admin API
+fn admin_profile(signed: &AxumSigned) -> Result<Actor, StatusCode> {
+    let profile = signed.profile().ok_or(StatusCode::UNAUTHORIZED)?;
+
+    match &profile.ek_username {
+        Some(username) if crate::ADMIN_USERS.contains(username) => Ok(profile),
+        _ => Err(StatusCode::FORBIDDEN),
+    }
+}
+
 pub async fn create_user(
     State(state): State<AppState>,
     user: Result<Json<NewUser>, JsonRejection>,
@@ -67,34 +115,13 @@ pub async fn relay_post(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let profile = guaranteed_actor(&conn, None).await;
-
-    let actor = if let Ok(actor) = get_actor_by_as_id(&conn, actor_id.clone()).await {
-        Some(ApActor::from(actor))
-    } else {
-        (get_actor(&conn, actor_id, None, true).await).ok()
-    };
-
-    let inbox = if let Some(actor) = actor.clone() {
-        if let Some(endpoints) = actor.endpoints {
-            Some(endpoints.shared_inbox)
-        } else {
-            Some(actor.inbox)
-        }
-    } else {
-        None
-    };
-
-    if let (Some(_inbox), Some(actor)) = (inbox, actor) {
-        let _follow = ApFollow {
-            actor: profile.as_id.into(),
-            to: MaybeMultiple::Single(actor.id.unwrap_or_default()),
-            ..Default::default()
-        };
-        // The original logic doesn't do anything with `_follow`, so we replicate that.
-    }
-
-    Ok(StatusCode::ACCEPTED)
+    runner::relay::subscribe(&conn, actor_id)
+        .await
+        .map(|_| StatusCode::ACCEPTED)
+        .map_err(|e| {
+            log::warn!("Failed to subscribe to relay: {e}");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })
 }
 
 pub async fn get_muted_terms(
@@ -132,6 +159,11 @@ pub async fn manage_muted_terms(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // An account that has moved is read-only
+    if profile.as_moved_to.is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     let action = action.map_err(|_| StatusCode::BAD_REQUEST)?.0;
 
     let conn = state
@@ -163,6 +195,97 @@ pub async fn manage_muted_terms(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
+pub async fn get_aliases(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(username): Path<String>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let profile = signed.profile().ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if profile.ek_username != Some(username.clone()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    get_also_known_as_by_username(&conn, username)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Adds or removes `alsoKnownAs` aliases for the profile and federates the updated actor.
+/// Aliases are resolved before they are added so that only existing actors are listed.
+pub async fn manage_aliases(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(username): Path<String>,
+    action: Result<Json<AliasesAction>, JsonRejection>,
+) -> Result<StatusCode, StatusCode> {
+    let profile = signed.profile().ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if profile.ek_username != Some(username.clone()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // An account that has moved is read-only
+    if profile.as_moved_to.is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let action = action.map_err(|_| StatusCode::BAD_REQUEST)?.0;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut all_aliases = get_also_known_as_by_username(&conn, username.clone())
+        .await
+        .unwrap_or_default();
+
+    match action.action {
+        AliasesActionType::Add => {
+            for alias in action.aliases {
+                let actor = get_actor(&conn, alias.clone(), Some(profile.clone()), true)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to resolve alias {alias}: {e:#?}");
+                        StatusCode::BAD_REQUEST
+                    })?;
+
+                let alias = actor.id.map(|id| id.to_string()).unwrap_or(alias);
+
+                if alias == profile.as_id {
+                    return Err(StatusCode::BAD_REQUEST);
+                }
+
+                if !all_aliases.contains(&alias) {
+                    all_aliases.push(alias);
+                }
+            }
+        }
+        AliasesActionType::Remove => {
+            all_aliases.retain(|alias| !action.aliases.contains(alias));
+        }
+    }
+
+    let actor = update_also_known_as_by_username(&conn, username, all_aliases)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(uuid) = actor.ek_uuid {
+        runner::run(send_actor_update_task, state.db_pool, None, vec![uuid]).await;
+    }
+
+    Ok(StatusCode::OK)
+}
+
 /// Get memory statistics (when memory-profiling feature is enabled)
 #[cfg(feature = "memory-profiling")]
 pub async fn memory_stats() -> Result<Json<serde_json::Value>, StatusCode> {
@@ -221,3 +344,136 @@ pub async fn memory_stats() -> Result<Json<serde_json::Value>, StatusCode> {
         "error": "Memory profiling not enabled. Build with --features memory-profiling"
     })))
 }
+
+pub async fn get_reports_api(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Query(query): Query<ReportsQuery>,
+) -> Result<Json<Vec<Report>>, StatusCode> {
+    admin_profile(&signed)?;
+
+    let status = query
+        .status
+        .map(ReportStatus::try_from)
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    get_reports(&conn, status).await.map(Json).map_err(|e| {
+        log::error!("Failed to retrieve reports: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn get_report_api(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(uuid): Path<String>,
+) -> Result<Json<Report>, StatusCode> {
+    admin_profile(&signed)?;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    get_report_by_uuid(&conn, uuid)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+pub async fn update_report_api(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(uuid): Path<String>,
+    action: Result<Json<ReportAction>, JsonRejection>,
+) -> Result<Json<Report>, StatusCode> {
+    let profile = admin_profile(&signed)?;
+    let action = match action.map_err(|_| StatusCode::BAD_REQUEST)?.0 {
+        // Notes made through the API are attributed to the admin making them
+        ReportAction::Note { content, .. } => ReportAction::Note {
+            content,
+            author: profile.ek_username,
+        },
+        action => action,
+    };
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    get_report_by_uuid(&conn, uuid.clone())
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    runner::report::update_report(&conn, uuid, action)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            log::warn!("Failed to update report: {e}");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })
+}
+
+pub async fn get_relays_api(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+) -> Result<Json<Vec<Relay>>, StatusCode> {
+    admin_profile(&signed)?;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    get_relays(&conn).await.map(Json).map_err(|e| {
+        log::error!("Failed to retrieve relays: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn manage_relays_api(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    action: Result<Json<RelaysAction>, JsonRejection>,
+) -> Result<Json<Vec<Relay>>, StatusCode> {
+    admin_profile(&signed)?;
+    let action = action.map_err(|_| StatusCode::BAD_REQUEST)?.0;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match action.action {
+        RelaysActionType::Add => runner::relay::subscribe(&conn, action.url)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                log::warn!("Failed to subscribe to relay: {e}");
+                StatusCode::UNPROCESSABLE_ENTITY
+            })?,
+        RelaysActionType::Remove => runner::relay::unsubscribe(&conn, action.url)
+            .await
+            .map_err(|e| {
+                log::warn!("Failed to unsubscribe from relay: {e}");
+                StatusCode::NOT_FOUND
+            })?,
+    }
+
+    get_relays(&conn).await.map(Json).map_err(|e| {
+        log::error!("Failed to retrieve relays: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -8,9 +8,10 @@ use crate::{
             create_activity, get_activity_by_ap_id, ActivityTarget, NewActivity,
             TryFromExtendedActivity,
         },
-        follows::mark_follow_accepted,
+        actors::guaranteed_actor,
+        follows::{get_follow, mark_follow_accepted},
     },
-    runner::{self, TaskError},
+    runner::{self, outbox_backfill::backfill_outbox_task, TaskError},
     server::AppState,
 };
 use deadpool_diesel::postgres::Pool;
@@ -129,9 +130,21 @@ async fn process(
             let leader_ap_id = accept.actor.to_string();
             let accept_ap_id = accept.id.clone().ok_or(TaskError::TaskFailed)?;
 
-            mark_follow_accepted(&conn, follower_ap_id, leader_ap_id, accept_ap_id).await;
+            let newly_accepted = get_follow(&conn, follower_ap_id.clone(), leader_ap_id.clone())
+                .await
+                .is_ok_and(|follow| !follow.accepted);
+            let system_follow = follower_ap_id == guaranteed_actor(&conn, None).await.as_id;
+
+            mark_follow_accepted(&conn, follower_ap_id, leader_ap_id.clone(), accept_ap_id).await;
 
             log::info!("Leader established: {accept}");
+
+            // The leader's recent posts are retrieved so that the home timeline doesn't
+            // only show what they post from now on. The system actor's follows are relay
+            // subscriptions, which have no home timeline to fill.
+            if newly_accepted && !system_follow {
+                runner::run(backfill_outbox_task, pool.clone(), None, vec![leader_ap_id]).await;
+            }
         } else {
             log::error!(
                 "Target of Accept activity {as_id} is not a Follow activity, but a {:?}",
@@ -1,4 +1,100 @@
 use super::Inbox;
-use jdt_activity_pub::ApAdd;
+use crate::{
+    db::runner::DbRunner,
+    models::{
+        activities::{create_activity, ActivityTarget, NewActivity},
+        actors::{get_actor_by_as_id, guaranteed_actor, update_featured_items_by_as_id},
+        objects::get_object_by_as_id,
+    },
+    runner::note::fetch_remote_object,
+    server::AppState,
+};
+use jdt_activity_pub::{ApActivity, ApAdd, ApAddress};
+use reqwest::StatusCode;
+use serde_json::Value;
 
-impl Inbox for ApAdd {}
+/// Handles an incoming `ApAdd` activity.
+///
+/// Only additions to the sending actor's featured collection are supported; they update our
+/// copy of the collection so that pinned posts show on the remote profile.
+impl Inbox for ApAdd {
+    async fn inbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        _state: AppState,
+        raw: Value,
+    ) -> Result<StatusCode, StatusCode> {
+        log::debug!("{:?}", self.clone());
+
+        let actor = get_actor_by_as_id(conn, self.actor.to_string())
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+
+        if actor.featured().is_none() || self.target != actor.featured() {
+            log::warn!(
+                "Add target is not the featured collection: {:?}",
+                self.target
+            );
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let object_id = self.object.reference().ok_or_else(|| {
+            log::warn!("Add has no object");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+        let object = match get_object_by_as_id(conn, object_id.clone()).await {
+            Ok(object) => object,
+            Err(_) => {
+                fetch_remote_object(conn, object_id.clone(), guaranteed_actor(conn, None).await)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to retrieve pinned object {object_id}: {e}");
+                        StatusCode::UNPROCESSABLE_ENTITY
+                    })?
+            }
+        };
+
+        if !object.attributed_to().contains(&actor.as_id) {
+            log::warn!(
+                "{} attempted to pin an object it did not author",
+                actor.as_id
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let mut items = actor.featured_items();
+
+        if !items.contains(&object_id) {
+            items.insert(0, object_id);
+
+            update_featured_items_by_as_id(conn, actor.as_id.clone(), items)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update featured items: {e:#?}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+        }
+
+        let mut activity = NewActivity::try_from((
+            ApActivity::Add(self.clone()),
+            Some(ActivityTarget::from(object)),
+        ))
+        .map_err(|e| {
+            log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        activity.raw = Some(raw.clone());
+
+        create_activity(conn, activity.clone()).await.map_err(|e| {
+            log::error!("FAILED TO CREATE ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    fn actor(&self) -> ApAddress {
+        self.actor.clone()
+    }
+}
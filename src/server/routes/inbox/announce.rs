@@ -1,8 +1,12 @@
 use super::Inbox;
 use crate::{
     db::runner::DbRunner,
-    models::activities::{create_activity, NewActivity},
-    runner,
+    models::{
+        activities::{create_activity, NewActivity},
+        blocks::is_blocked_by_any,
+        objects::get_object_by_as_id,
+    },
+    runner::{self, relay::is_relay},
     server::AppState,
 };
 use jdt_activity_pub::{ApActivity, ApAddress, ApAnnounce};
@@ -18,6 +22,33 @@ impl Inbox for ApAnnounce {
     ) -> Result<StatusCode, StatusCode> {
         log::debug!("{:?}", self.clone());
 
+        if let Some(object) = self.object.reference() {
+            if let Ok(object) = get_object_by_as_id(conn, object).await {
+                if is_blocked_by_any(conn, self.actor.to_string(), object.attributed_to())
+                    .await
+                    .unwrap_or_default()
+                {
+                    log::info!("REJECTING ANNOUNCE FROM BLOCKED ACTOR: {}", self.actor);
+                    return Err(StatusCode::FORBIDDEN);
+                }
+            }
+        }
+
+        if is_relay(conn, self.actor.to_string()).await {
+            let announce_ap_id = self.id.clone().ok_or(StatusCode::BAD_REQUEST)?;
+            let object_ap_id = self.object.reference().ok_or(StatusCode::BAD_REQUEST)?;
+
+            runner::run(
+                runner::relay::relayed_announce_task,
+                state.db_pool,
+                None,
+                vec![announce_ap_id, object_ap_id],
+            )
+            .await;
+
+            return Ok(StatusCode::ACCEPTED);
+        }
+
         let mut activity = NewActivity::try_from((ApActivity::Announce(self.clone()), None))
             .map_err(|e| {
                 log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
@@ -2,21 +2,44 @@ use super::Inbox;
 use crate::{
     db::runner::DbRunner,
     models::activities::{create_activity, NewActivity},
+    runner,
     server::AppState,
 };
 use jdt_activity_pub::{ApActivity, ApAddress, ApMove};
 use reqwest::StatusCode;
 use serde_json::Value;
 
+/// Handles an incoming `ApMove` activity.
+///
+/// This is triggered when a remote actor migrates to a new account.
 impl Inbox for ApMove {
+    /// 1. Validates that the actor is moving itself and names a target.
+    /// 2. Stores the `Move` activity in the database.
+    /// 3. Schedules a background task that verifies the target lists the actor in
+    ///    `alsoKnownAs` and, if so, moves local followers to the target.
     async fn inbox<C: DbRunner>(
         &self,
         conn: &C,
-        _state: AppState,
+        state: AppState,
         raw: Value,
     ) -> Result<StatusCode, StatusCode> {
         log::debug!("{:?}", self.clone());
 
+        let source = self.object.reference().ok_or_else(|| {
+            log::warn!("Move object is not a reference");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+        if source != self.actor.to_string() {
+            log::warn!("Move actor {} does not match object {source}", self.actor);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let target = self.target.reference().ok_or_else(|| {
+            log::warn!("Move has no target");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
         let mut activity =
             NewActivity::try_from((ApActivity::Move(self.clone()), None)).map_err(|e| {
                 log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
@@ -29,6 +52,14 @@ impl Inbox for ApMove {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+        runner::run(
+            runner::ap_move::move_followers_task,
+            state.db_pool.clone(),
+            None,
+            vec![source, target],
+        )
+        .await;
+
         Ok(StatusCode::ACCEPTED)
     }
 
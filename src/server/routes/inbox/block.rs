@@ -1,4 +1,91 @@
 use super::Inbox;
-use jdt_activity_pub::ApBlock;
+use crate::{
+    db::runner::DbRunner,
+    models::{
+        activities::{create_activity, ActivityTarget, NewActivity},
+        actors::get_actor_by_as_id,
+        blocks::{create_block, NewBlock},
+        follows::delete_follow,
+    },
+    server::AppState,
+};
+use jdt_activity_pub::{ApActivity, ApAddress, ApBlock};
+use reqwest::StatusCode;
+use serde_json::Value;
 
-impl Inbox for ApBlock {}
+/// Handles an incoming `ApBlock` activity.
+///
+/// This is triggered when a remote actor blocks a local actor.
+impl Inbox for ApBlock {
+    /// 1. Retrieves the local actor being blocked.
+    /// 2. Stores the `Block` activity in the database.
+    /// 3. Records the block, which hides the blocker's content from the blocked actor and
+    ///    stops delivery of the blocked actor's activities to the blocker.
+    /// 4. Removes any follow relationships between the two actors.
+    async fn inbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        _state: AppState,
+        raw: Value,
+    ) -> Result<StatusCode, StatusCode> {
+        log::info!("{self}");
+
+        let blocked = get_actor_by_as_id(conn, self.object.clone())
+            .await
+            .map_err(|e| {
+                log::error!("FAILED TO RETRIEVE BLOCKED ACTOR: {e:#?}");
+                StatusCode::NOT_FOUND
+            })?;
+
+        if blocked.ek_username.is_none() {
+            log::warn!("Ignoring Block of non-local actor {}", blocked.as_id);
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let activity = NewActivity::try_from((
+            ApActivity::Block(self.clone()),
+            Some(ActivityTarget::from(blocked.clone())),
+        ))
+        .map_err(|e| {
+            log::error!("FAILED TO BUILD BLOCK ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .link_actor(conn)
+        .await
+        .set_raw(raw);
+
+        let activity = create_activity(conn, activity).await.map_err(|e| {
+            log::error!("FAILED TO CREATE BLOCK ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let block = NewBlock {
+            block_activity_ap_id: activity.ap_id,
+            ..NewBlock::from(self.clone())
+        }
+        .link(conn)
+        .await;
+
+        create_block(conn, block).await.map_err(|e| {
+            log::error!("FAILED TO CREATE BLOCK: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let blocker = self.actor.to_string();
+
+        for (follower, leader) in [
+            (blocker.clone(), blocked.as_id.clone()),
+            (blocked.as_id.clone(), blocker.clone()),
+        ] {
+            if let Err(e) = delete_follow(conn, follower, leader).await {
+                log::error!("FAILED TO REMOVE FOLLOW AFTER BLOCK: {e:#?}");
+            }
+        }
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    fn actor(&self) -> ApAddress {
+        self.actor.clone()
+    }
+}
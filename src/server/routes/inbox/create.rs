@@ -4,7 +4,8 @@ use crate::{
     db::runner::DbRunner,
     models::{
         activities::{create_activity, get_activity_by_ap_id, ActivityTarget, NewActivity},
-        objects::{create_object, NewObject},
+        blocks::is_blocked_by_any,
+        objects::{create_object, get_object_by_as_id, NewObject},
         unprocessable::create_unprocessable,
         votes::{get_question_for_vote, is_vote, validate_vote, VoteError},
     },
@@ -127,6 +128,11 @@ impl Inbox for ApCreate {
                     // Regular Note processing
                     let new_object = NewObject::from(x.clone());
 
+                    if is_blocked_reply(conn, self.actor.to_string(), &new_object).await {
+                        log::info!("REJECTING REPLY FROM BLOCKED ACTOR: {}", self.actor);
+                        return Err(StatusCode::FORBIDDEN);
+                    }
+
                     let object = create_object(conn, new_object).await.map_err(|e| {
                         log::error!("FAILED TO CREATE OR UPDATE OBJECT: {e:#?}");
                         StatusCode::INTERNAL_SERVER_ERROR
@@ -160,6 +166,11 @@ impl Inbox for ApCreate {
                 log::debug!("{article:?}");
                 let new_object = NewObject::from(article.clone());
 
+                if is_blocked_reply(conn, self.actor.to_string(), &new_object).await {
+                    log::info!("REJECTING REPLY FROM BLOCKED ACTOR: {}", self.actor);
+                    return Err(StatusCode::FORBIDDEN);
+                }
+
                 let object = create_object(conn, new_object).await.map_err(|e| {
                     log::error!("FAILED TO CREATE OR UPDATE ARTICLE: {e:#?}");
                     StatusCode::INTERNAL_SERVER_ERROR
@@ -192,6 +203,11 @@ impl Inbox for ApCreate {
                 log::debug!("{question:?}");
                 let new_object = NewObject::from(question.clone());
 
+                if is_blocked_reply(conn, self.actor.to_string(), &new_object).await {
+                    log::info!("REJECTING REPLY FROM BLOCKED ACTOR: {}", self.actor);
+                    return Err(StatusCode::FORBIDDEN);
+                }
+
                 let object = create_object(conn, new_object).await.map_err(|e| {
                     log::error!("FAILED TO CREATE OR UPDATE Object: {e:#?}");
                     StatusCode::INTERNAL_SERVER_ERROR
@@ -236,3 +252,37 @@ impl Inbox for ApCreate {
         self.actor.clone()
     }
 }
+
+/// Returns true if the object is a reply to an object whose author has blocked the actor.
+async fn is_blocked_reply<C: DbRunner>(conn: &C, actor: String, object: &NewObject) -> bool {
+    let in_reply_to: Vec<String> = match object.as_in_reply_to.clone() {
+        Some(Value::String(id)) => vec![id],
+        Some(Value::Array(values)) => values
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::String(id) => Some(id),
+                Value::Object(object) => object.get("id")?.as_str().map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::Object(object)) => object
+            .get("id")
+            .and_then(Value::as_str)
+            .map(|id| vec![id.to_string()])
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+
+    for id in in_reply_to {
+        if let Ok(parent) = get_object_by_as_id(conn, id).await {
+            if is_blocked_by_any(conn, actor.clone(), parent.attributed_to())
+                .await
+                .unwrap_or_default()
+            {
+                return true;
+            }
+        }
+    }
+
+    false
+}
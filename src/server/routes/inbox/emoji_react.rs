@@ -0,0 +1,63 @@
+use super::Inbox;
+use crate::{
+    db::runner::DbRunner,
+    models::{
+        blocks::is_blocked_by_any,
+        objects::get_object_by_as_id,
+        reactions::{create_reaction, ApEmojiReact, NewReaction},
+    },
+    server::AppState,
+};
+use jdt_activity_pub::ApAddress;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+/// Handles an incoming EmojiReact, which is not an `ApActivity`; the shared inbox and queue
+/// workers fall back to it for activities that do not otherwise parse.
+///
+/// Reactions are recorded in their own table rather than as activities, so an Undo of one
+/// is matched against the reactions before the activities.
+impl Inbox for ApEmojiReact {
+    async fn inbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        _state: AppState,
+        _raw: Value,
+    ) -> Result<StatusCode, StatusCode> {
+        log::debug!("{:?}", self.clone());
+
+        let target = get_object_by_as_id(conn, self.object.clone())
+            .await
+            .map_err(|e| {
+                log::debug!("EmojiReact target not found: {e:#?}");
+                StatusCode::NOT_FOUND
+            })?;
+
+        if is_blocked_by_any(conn, self.actor.to_string(), target.attributed_to())
+            .await
+            .unwrap_or_default()
+        {
+            log::info!("Rejecting EmojiReact from blocked actor: {}", self.actor);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let reaction = NewReaction::try_from(self.clone())
+            .map_err(|e| {
+                log::debug!("Failed to build reaction: {e}");
+                StatusCode::UNPROCESSABLE_ENTITY
+            })?
+            .link(conn)
+            .await;
+
+        create_reaction(conn, reaction).await.map_err(|e| {
+            log::error!("Failed to create reaction: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    fn actor(&self) -> ApAddress {
+        self.actor.clone()
+    }
+}
@@ -0,0 +1,74 @@
+use super::Inbox;
+use crate::{
+    db::runner::DbRunner,
+    helper::get_domain_from_url,
+    models::{
+        activities::{create_activity, ActivityType, NewActivity},
+        instances::is_reports_rejected,
+        reports::ApFlag,
+    },
+    runner::report::receive_report,
+    server::AppState,
+};
+use jdt_activity_pub::ApAddress;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+/// Handles an incoming Flag, which is not an `ApActivity`; the shared inbox and queue
+/// workers fall back to it for activities that do not otherwise parse.
+///
+/// The report is added to the same queue as reports filed by local users, unless the
+/// sender's domain has the reject_reports moderation level.
+impl Inbox for ApFlag {
+    async fn inbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        _state: AppState,
+        raw: Value,
+    ) -> Result<StatusCode, StatusCode> {
+        log::debug!("{:?}", self.clone());
+
+        if let Some(domain_name) = get_domain_from_url(self.actor.to_string()) {
+            if is_reports_rejected(conn, domain_name).await {
+                log::info!(
+                    "DROPPING FLAG FROM DOMAIN REJECTING REPORTS: {}",
+                    self.actor
+                );
+                return Ok(StatusCode::ACCEPTED);
+            }
+        }
+
+        let Some(report) = receive_report(conn, self.clone()).await.map_err(|e| {
+            log::warn!("Failed to record report from {}: {e}", self.actor);
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?
+        else {
+            log::debug!("Report has already been recorded");
+            return Ok(StatusCode::ACCEPTED);
+        };
+
+        create_activity(
+            conn,
+            NewActivity {
+                kind: ActivityType::Flag,
+                actor: self.actor.to_string(),
+                target_ap_id: Some(report.target_ap_id),
+                target_actor_id: report.target_actor_id,
+                ap_id: self.id.clone(),
+                raw: Some(raw),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| {
+            log::error!("FAILED TO CREATE ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    fn actor(&self) -> ApAddress {
+        self.actor.clone()
+    }
+}
@@ -7,8 +7,9 @@ use crate::{
             create_activity, get_activity_by_ap_id, ActivityTarget, NewActivity,
             TryFromExtendedActivity,
         },
-        actors::get_actor_by_as_id,
-        follows::{create_follow, mark_follow_accepted, NewFollow},
+        actors::{get_actor_by_as_id, Actor},
+        blocks::is_blocked_by_any,
+        follows::{create_follow, get_follow, mark_follow_accepted, NewFollow},
     },
     runner::{self, send_to_inboxes, TaskError},
     server::AppState,
@@ -49,6 +50,14 @@ impl Inbox for ApFollow {
             return Err(StatusCode::UNPROCESSABLE_ENTITY);
         };
 
+        if is_blocked_by_any(conn, self.actor.to_string(), vec![actor_as_id.clone()])
+            .await
+            .unwrap_or_default()
+        {
+            log::info!("REJECTING FOLLOW FROM BLOCKED ACTOR: {}", self.actor);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
         let actor = get_actor_by_as_id(conn, actor_as_id.clone())
             .await
             .map_err(|e| {
@@ -155,8 +164,11 @@ async fn process(
             log::warn!("Failed to create Follow record, it might already exist. Continuing.");
         }
 
-        // 3. Check if the leader requires manual approval.
-        if leader_actor.ap_manually_approves_followers {
+        // 3. Check if the leader requires manual approval. Followers arriving through a Move
+        //    from one of the leader's aliases are accepted without it.
+        if leader_actor.ap_manually_approves_followers
+            && !follows_moved_alias(&conn, &follower_actor, &leader_actor).await
+        {
             log::info!(
                 "Actor {:?} requires manual follow approval. Follow from {:?} is now pending.",
                 leader_actor.as_preferred_username,
@@ -216,3 +228,25 @@ async fn process(
 
     Ok(())
 }
+
+/// Returns true if the follower follows an alias of the leader that has moved to the leader
+async fn follows_moved_alias<C: DbRunner>(conn: &C, follower: &Actor, leader: &Actor) -> bool {
+    let aliases: Vec<String> =
+        serde_json::from_value(leader.as_also_known_as.clone()).unwrap_or_default();
+
+    for alias in aliases {
+        let moved = get_actor_by_as_id(conn, alias.clone())
+            .await
+            .is_ok_and(|alias| alias.as_moved_to.as_ref() == Some(&leader.as_id));
+
+        if moved
+            && get_follow(conn, follower.as_id.clone(), alias)
+                .await
+                .is_ok()
+        {
+            return true;
+        }
+    }
+
+    false
+}
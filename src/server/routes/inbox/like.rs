@@ -3,7 +3,9 @@ use crate::{
     db::runner::DbRunner,
     models::{
         activities::{create_activity, ActivityTarget, NewActivity},
+        blocks::is_blocked_by_any,
         objects::get_object_by_as_id,
+        reactions::{create_reaction, NewReaction},
     },
     server::AppState,
 };
@@ -34,6 +36,14 @@ impl Inbox for Box<ApLike> {
             StatusCode::NOT_FOUND
         })?;
 
+        if is_blocked_by_any(conn, self.actor.to_string(), target.attributed_to())
+            .await
+            .unwrap_or_default()
+        {
+            log::info!("REJECTING LIKE FROM BLOCKED ACTOR: {}", self.actor);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
         let mut activity = NewActivity::try_from((
             ApActivity::Like(self.clone()),
             Some(ActivityTarget::from(target)),
@@ -49,6 +59,13 @@ impl Inbox for Box<ApLike> {
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+        // Misskey, Pleroma and Akkoma send emoji reactions as a Like with content
+        if let Some(reaction) = NewReaction::from_like(self, &raw) {
+            if let Err(e) = create_reaction(conn, reaction.link(conn).await).await {
+                log::error!("Failed to create reaction: {e:#?}");
+            }
+        }
+
         Ok(StatusCode::ACCEPTED)
     }
 
@@ -1,12 +1,19 @@
 use super::{ActivityJson, Inbox};
 use crate::{
     blocklist::Permitted,
+    helper::get_domain_from_url,
+    load_page_ephemeral,
     models::{
         activities::{get_announcers, TimelineFilters, TimelineView},
         follows::get_leaders_by_follower_actor_id,
+        processing_queue::enqueue_activity,
+        reactions::ApEmojiReact,
+        reports::ApFlag,
         unprocessable::create_unprocessable,
     },
+    proofs::{has_supported_proof, verify_proof},
     retriever::{self, get_actor},
+    runner::{self, conversation::conversation_backfill_task, quote::normalize_quote},
     server::{extractors::AxumSigned, AppState},
     signing::{get_hash, verify, VerificationError},
 };
@@ -30,8 +37,11 @@ pub mod ap_move;
 pub mod block;
 pub mod create;
 pub mod delete;
+pub mod emoji_react;
+pub mod flag;
 pub mod follow;
 pub mod like;
+pub mod queue;
 pub mod reject;
 pub mod remove;
 pub mod undo;
@@ -45,6 +55,9 @@ pub fn sanitize_json_fields(mut value: Value) -> Value {
         // Handle conversation/context overlap at top level
         sanitize_level(obj, "conversation", "context");
 
+        // Keep quotes identified by properties that ApNote drops
+        normalize_quote(obj);
+
         // Handle one level deeper in "object" field
         if let Some(Value::Object(ref mut object_obj)) = obj.get_mut("object") {
             // In object level: remove "actor" if both exist and are identical
@@ -52,6 +65,8 @@ pub fn sanitize_json_fields(mut value: Value) -> Value {
 
             // Handle conversation/context overlap in object level
             sanitize_level(object_obj, "conversation", "context");
+
+            normalize_quote(object_obj);
         }
     }
     value
@@ -137,7 +152,7 @@ pub async fn axum_shared_inbox_get(
     Query(query): Query<InboxQuery>,
     _username: Option<Path<String>>,
     signed: AxumSigned,
-) -> Result<ActivityJson<ApObject>, StatusCode> {
+) -> Result<ActivityJson<Value>, StatusCode> {
     log::debug!("{query:?}");
 
     let conn = match app_state.db_pool.get().await {
@@ -188,6 +203,7 @@ pub async fn axum_shared_inbox_get(
                 excluded_words: vec![],
                 direct: false,
                 object_type: None,
+                blocked_actors: vec![],
             },
             InboxView::Home => TimelineFilters {
                 view: if let Some(profile) = profile.clone() {
@@ -209,6 +225,7 @@ pub async fn axum_shared_inbox_get(
                 excluded_words: vec![],
                 direct: false,
                 object_type: None,
+                blocked_actors: vec![],
             },
             InboxView::Local => TimelineFilters {
                 view: Some(view.into()),
@@ -218,6 +235,7 @@ pub async fn axum_shared_inbox_get(
                 excluded_words: vec![],
                 direct: false,
                 object_type: None,
+                blocked_actors: vec![],
             },
             InboxView::Direct => TimelineFilters {
                 view: Some(view.into()),
@@ -227,6 +245,7 @@ pub async fn axum_shared_inbox_get(
                 excluded_words: vec![],
                 direct: true,
                 object_type: None,
+                blocked_actors: vec![],
             },
         }
     } else {
@@ -238,6 +257,7 @@ pub async fn axum_shared_inbox_get(
             excluded_words: vec![],
             direct: false,
             object_type: None,
+            blocked_actors: vec![],
         }
     };
 
@@ -246,13 +266,19 @@ pub async fn axum_shared_inbox_get(
         query.limit.unwrap_or(20).into(),
         query.min,
         query.max,
-        profile,
+        profile.clone(),
         filters,
         Some(base_url),
     )
     .await;
 
-    Ok(ActivityJson(result))
+    load_page_ephemeral(&conn, profile, result)
+        .await
+        .map(ActivityJson)
+        .map_err(|e| {
+            log::error!("Failed to load page ephemeral data: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
 }
 
 pub async fn axum_shared_inbox_post(
@@ -292,8 +318,9 @@ pub async fn axum_shared_inbox_post(
         }
     }
 
-    // Clean up the JSON from servers like Akkoma that send spurious fields
-    let raw = sanitize_json_fields(hashed.json);
+    // Clean up the JSON from servers like Akkoma that send spurious fields; the proof is
+    // checked against the activity as it was signed
+    let raw = sanitize_json_fields(hashed.json.clone());
 
     // Wait until absolutely necessary to reserve a database connection
     let conn = state
@@ -302,27 +329,65 @@ pub async fn axum_shared_inbox_post(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Reject message if an ApActivity can not be built from it; log to the unprocessable table
-    let activity: ApActivity = match raw.clone().try_into() {
-        Ok(activity) => activity,
+    // Reject message if an ApActivity (or a Flag or EmojiReact, which are handled alongside
+    // them) can not be built from it; log to the unprocessable table
+    let (actor, is_delete) = match ApActivity::try_from(raw.clone()) {
+        Ok(activity) => (activity.actor(), activity.is_delete()),
         Err(e) => {
-            create_unprocessable(&conn, (raw, Some(format!("{e:#?}"))).into()).await;
-            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+            if let Ok(flag) = serde_json::from_value::<ApFlag>(raw.clone()) {
+                (flag.actor, false)
+            } else if let Ok(react) = serde_json::from_value::<ApEmojiReact>(raw.clone()) {
+                (react.actor, false)
+            } else {
+                create_unprocessable(&conn, (raw, Some(format!("{e:#?}"))).into()).await;
+                return Err(StatusCode::UNPROCESSABLE_ENTITY);
+            }
         }
     };
 
     // If this is a Delete and we don't have the Actor, just Accept it and do nothing
-    if activity.is_delete() && signed.deferred().is_some() {
+    if is_delete && signed.deferred().is_some() {
         log::debug!("Accepting Delete activity for non-existent Actor");
         return Ok(StatusCode::ACCEPTED);
     }
 
+    // An activity carrying a valid FEP-8b32 proof from its actor is authentic regardless of
+    // who delivered it, which is how relayed and forwarded activities are authenticated. A
+    // proof that can't be verified leaves the request to its HTTP signature.
+    let proven = if has_supported_proof(&hashed.json) {
+        match verify_proof(&conn, &hashed.json).await {
+            Ok(controller) if controller == actor.to_string() => true,
+            Ok(controller) => {
+                log::debug!("Proof by {controller} does not match actor {actor}");
+                false
+            }
+            Err(e) => {
+                log::debug!("Proof verification failed: {e}");
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    // Permitted only checks the server that delivered the request; a proven activity may
+    // have been relayed from somewhere else, so its actor's server is checked as well
+    if proven
+        && get_domain_from_url(actor.to_string())
+            .is_none_or(|domain| state.block_list.is_blocked(domain))
+    {
+        log::debug!("Rejecting proven activity from prohibited actor {actor}");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     // Handle the Deferred Actor here
-    let is_authorized = if let Some(deferred) = signed.deferred() {
+    let is_authorized = if proven {
+        true
+    } else if let Some(deferred) = signed.deferred() {
         match verify(&conn, deferred).await {
             Ok(_) => true,
             Err(VerificationError::ActorNotFound(_)) => {
-                let actor = activity.actor().to_string();
+                let actor = actor.to_string();
                 log::debug!("Attempting to retrieve {actor}");
                 get_actor(&conn, actor, None, true).await.is_ok()
             }
@@ -332,8 +397,22 @@ pub async fn axum_shared_inbox_post(
         signed.any()
     };
 
+    // Persist the verified activity and let the queue workers process it; an activity
+    // that is already queued (a redelivery) is acknowledged without requeueing unless it
+    // was dead-lettered
     if is_authorized {
-        activity.inbox(&conn, state, raw).await
+        match enqueue_activity(&conn, raw.into()).await {
+            Ok(queued) => {
+                if queued.is_some() {
+                    queue::notify_inbox_workers();
+                }
+                Ok(StatusCode::ACCEPTED)
+            }
+            Err(e) => {
+                log::error!("Failed to enqueue inbound activity: {e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
     } else {
         log::debug!("Request signature verification failed");
         Err(StatusCode::UNAUTHORIZED)
@@ -410,7 +489,7 @@ pub async fn axum_conversation_get(
     State(state): State<AppState>,
     signed: AxumSigned,
     Query(query): Query<ConversationQuery>,
-) -> Result<axum::Json<ApObject>, StatusCode> {
+) -> Result<axum::Json<Value>, StatusCode> {
     let conn = state
         .db_pool
         .get()
@@ -419,6 +498,22 @@ pub async fn axum_conversation_get(
 
     let decoded = urlencoding::decode(&query.id).map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
 
+    // Posts missing from the thread are retrieved in the background and announced
+    // through the event stream; only local users may cause the remote fetches
+    if let Some(username) = signed
+        .profile()
+        .filter(|_| signed.local())
+        .and_then(|profile| profile.ek_username)
+    {
+        runner::run(
+            conversation_backfill_task,
+            state.db_pool.clone(),
+            Some(state.event_channels.clone()),
+            vec![decoded.to_string(), username],
+        )
+        .await;
+    }
+
     let limit = query.limit.unwrap_or(20);
     let server_url = format!("https://{}", *crate::SERVER_NAME);
     let base_url = format!(
@@ -434,18 +529,25 @@ pub async fn axum_conversation_get(
         excluded_words: vec![],
         direct: false,
         object_type: None,
+        blocked_actors: vec![],
     };
 
-    Ok(axum::Json(
-        retriever::activities(
-            &conn,
-            limit.into(),
-            query.min,
-            query.max,
-            signed.profile(),
-            filters,
-            Some(base_url),
-        )
-        .await,
-    ))
+    let result = retriever::activities(
+        &conn,
+        limit.into(),
+        query.min,
+        query.max,
+        signed.profile(),
+        filters,
+        Some(base_url),
+    )
+    .await;
+
+    load_page_ephemeral(&conn, signed.profile(), result)
+        .await
+        .map(axum::Json)
+        .map_err(|e| {
+            log::error!("Failed to load page ephemeral data: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
 }
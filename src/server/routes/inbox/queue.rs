@@ -0,0 +1,195 @@
+use super::Inbox;
+use crate::{
+    models::{
+        processing_queue::{
+            claim_next_queued_activity, mark_queued_activity_complete, mark_queued_activity_failed,
+            release_stale_queued_activities, QueuedActivity,
+        },
+        reactions::ApEmojiReact,
+        reports::ApFlag,
+    },
+    server::AppState,
+};
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use jdt_activity_pub::ApActivity;
+use once_cell::sync::Lazy;
+use tokio::sync::Notify;
+
+// Workers sleep on this between polls; the shared inbox wakes one when it enqueues
+static QUEUE_NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const REAPER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+const STALE_LOCK_MINUTES: i64 = 10;
+const MAX_RETRY_DELAY_SECONDS: i64 = 6 * 60 * 60;
+
+pub fn notify_inbox_workers() {
+    QUEUE_NOTIFY.notify_one();
+}
+
+/// Starts the inbound queue workers along with a reaper that returns activities
+/// orphaned in the processing state (e.g., by a restart) to the queue.
+pub fn spawn_inbox_workers(state: AppState) {
+    log::info!("Starting {} inbox queue workers", *crate::INBOX_WORKERS);
+
+    for worker in 0..*crate::INBOX_WORKERS {
+        tokio::spawn(inbox_worker(worker, state.clone()));
+    }
+
+    tokio::spawn(stale_lock_reaper(state));
+}
+
+async fn stale_lock_reaper(state: AppState) {
+    loop {
+        if let Ok(conn) = state.db_pool.get().await {
+            let cutoff = Utc::now() - Duration::minutes(STALE_LOCK_MINUTES);
+            match release_stale_queued_activities(&conn, cutoff).await {
+                Ok(0) => {}
+                Ok(released) => {
+                    log::info!("Released {released} stale inbox queue items");
+                    notify_inbox_workers();
+                }
+                Err(e) => log::error!("Failed to release stale inbox queue items: {e}"),
+            }
+        }
+
+        tokio::time::sleep(REAPER_INTERVAL).await;
+    }
+}
+
+async fn inbox_worker(worker: usize, state: AppState) {
+    loop {
+        let claimed = match state.db_pool.get().await {
+            Ok(conn) => claim_next_queued_activity(&conn).await,
+            Err(e) => Err(anyhow::anyhow!("failed to get DB connection: {e}")),
+        };
+
+        let item = match claimed {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                tokio::select! {
+                    _ = QUEUE_NOTIFY.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+                continue;
+            }
+            Err(e) => {
+                log::error!("Inbox worker {worker} failed to claim queue item: {e}");
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+        };
+
+        let id = item.id;
+        let attempts = item.attempts;
+
+        // Run the handler in its own task so that a panic is recorded as a failure
+        // rather than taking the worker down with it
+        let handle = tokio::spawn(process_queued_activity(state.clone(), item));
+
+        let result = match handle.await {
+            Ok(result) => result,
+            Err(e) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("inbox handler panicked: {e}"),
+            )),
+        };
+
+        // If the status can't be recorded, the reaper will return the item to the queue
+        let conn = match state.db_pool.get().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Inbox worker {worker} failed to get DB connection: {e}");
+                continue;
+            }
+        };
+
+        if let Err((status, error)) = result {
+            let retry_at = (is_retryable(status) && attempts < *crate::INBOX_MAX_ATTEMPTS)
+                .then(|| Utc::now() + retry_delay(attempts));
+
+            if retry_at.is_none() {
+                log::warn!("Dead-lettering inbox queue item {id}: {error}");
+            } else {
+                log::debug!("Inbox queue item {id} failed (attempt {attempts}): {error}");
+            }
+
+            if let Err(e) = mark_queued_activity_failed(&conn, id, error, retry_at).await {
+                log::error!("Failed to record failure for inbox queue item {id}: {e}");
+            }
+        } else if let Err(e) = mark_queued_activity_complete(&conn, id).await {
+            log::error!("Failed to mark inbox queue item {id} complete: {e}");
+        }
+    }
+}
+
+async fn process_queued_activity(
+    state: AppState,
+    item: QueuedActivity,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let conn = state.db_pool.get().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to get DB connection: {e}"),
+        )
+    })?;
+
+    // Flag and EmojiReact are not ApActivity and are only tried when the activity does not
+    // parse as one
+    let result = match ApActivity::try_from(item.raw.clone()) {
+        Ok(activity) => activity.inbox(&conn, state.clone(), item.raw).await,
+        Err(e) => {
+            if let Ok(flag) = serde_json::from_value::<ApFlag>(item.raw.clone()) {
+                flag.inbox(&conn, state.clone(), item.raw).await
+            } else if let Ok(react) = serde_json::from_value::<ApEmojiReact>(item.raw.clone()) {
+                react.inbox(&conn, state.clone(), item.raw).await
+            } else {
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, format!("{e:#?}")));
+            }
+        }
+    };
+
+    result.map_err(|status| (status, format!("inbox handler returned {status}")))
+}
+
+// Client errors and unimplemented activity types will not succeed on a later attempt
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    Duration::seconds((30i64 << exponent).min(MAX_RETRY_DELAY_SECONDS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_from_thirty_seconds() {
+        assert_eq!(retry_delay(0), Duration::seconds(30));
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(5), Duration::seconds(480));
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        assert_eq!(retry_delay(11), Duration::seconds(MAX_RETRY_DELAY_SECONDS));
+        assert_eq!(
+            retry_delay(i32::MAX),
+            Duration::seconds(MAX_RETRY_DELAY_SECONDS)
+        );
+    }
+
+    #[test]
+    fn only_server_errors_are_retried() {
+        assert!(is_retryable(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_IMPLEMENTED));
+        assert!(!is_retryable(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+    }
+}
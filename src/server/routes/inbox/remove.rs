@@ -1,13 +1,19 @@
 use super::Inbox;
 use crate::{
     db::runner::DbRunner,
-    models::activities::{create_activity, NewActivity},
+    models::{
+        activities::{create_activity, NewActivity},
+        actors::{get_actor_by_as_id, update_featured_items_by_as_id},
+    },
     server::AppState,
 };
 use jdt_activity_pub::{ApActivity, ApAddress, ApRemove};
 use reqwest::StatusCode;
 use serde_json::Value;
 
+/// Handles an incoming `ApRemove` activity.
+///
+/// Removals from the sending actor's featured collection update our copy of the collection.
 impl Inbox for ApRemove {
     async fn inbox<C: DbRunner>(
         &self,
@@ -17,6 +23,36 @@ impl Inbox for ApRemove {
     ) -> Result<StatusCode, StatusCode> {
         log::debug!("{:?}", self.clone());
 
+        let actor = get_actor_by_as_id(conn, self.actor.to_string())
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+
+        if actor.featured().is_none() || self.target.reference() != actor.featured() {
+            log::warn!(
+                "Remove target is not the featured collection: {:?}",
+                self.target
+            );
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let object_id = self.object.reference().ok_or_else(|| {
+            log::warn!("Remove has no object");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+        let mut items = actor.featured_items();
+
+        if let Some(pos) = items.iter().position(|item| item == &object_id) {
+            items.remove(pos);
+
+            update_featured_items_by_as_id(conn, actor.as_id.clone(), items)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update featured items: {e:#?}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+        }
+
         let mut activity = NewActivity::try_from((ApActivity::Remove(self.clone()), None))
             .map_err(|e| {
                 log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
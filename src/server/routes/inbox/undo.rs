@@ -6,7 +6,9 @@ use crate::{
             create_activity, get_activity_by_ap_id, revoke_activity_by_apid, ActivityTarget,
             ActivityType, NewActivity,
         },
+        blocks::delete_block,
         follows::delete_follow,
+        reactions::{delete_reaction_by_ap_id, get_reaction_by_ap_id},
     },
     runner::{self},
     server::AppState,
@@ -29,13 +31,35 @@ impl Inbox for Box<ApUndo> {
             StatusCode::BAD_REQUEST
         })?;
 
-        let (_, target_activity, _, _) = get_activity_by_ap_id(conn, target_ap_id.clone())
+        // Reactions are recorded apart from activities; a Like carrying one has both
+        let reaction_removed = match get_reaction_by_ap_id(conn, target_ap_id.clone()).await {
+            Ok(reaction) if reaction.actor_ap_id == self.actor.to_string() => {
+                delete_reaction_by_ap_id(conn, target_ap_id.clone())
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to delete reaction: {e}");
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    })?;
+                true
+            }
+            Ok(_) => {
+                log::warn!("Undo actor does not match reaction actor: {target_ap_id}");
+                return Err(StatusCode::FORBIDDEN);
+            }
+            Err(_) => false,
+        };
+
+        let (_, target_activity, _, _) = match get_activity_by_ap_id(conn, target_ap_id.clone())
             .await
             .map_err(|_| StatusCode::NOT_FOUND)?
-            .ok_or_else(|| {
+        {
+            Some(found) => found,
+            None if reaction_removed => return Ok(StatusCode::ACCEPTED),
+            None => {
                 log::error!("Undo Activity not found");
-                StatusCode::NOT_FOUND
-            })?;
+                return Err(StatusCode::NOT_FOUND);
+            }
+        };
 
         let target_activity = target_activity.ok_or_else(|| {
             log::error!("Target Activity not found");
@@ -109,6 +133,29 @@ impl Inbox for Box<ApUndo> {
                 });
                 Ok(StatusCode::ACCEPTED)
             }
+            ActivityType::Block => {
+                if target_activity.actor != self.actor.to_string() {
+                    log::warn!("Undo actor does not match Block actor: {target_ap_id}");
+                    return Err(StatusCode::FORBIDDEN);
+                }
+
+                let blocked_actor_ap_id = target_activity.target_ap_id.ok_or_else(|| {
+                    log::error!("Failed to identify blocked Actor");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+                if delete_block(conn, target_activity.actor.clone(), blocked_actor_ap_id)
+                    .await
+                    .is_ok()
+                    && revoke_activity_by_apid(conn, target_ap_id.clone())
+                        .await
+                        .is_ok()
+                {
+                    log::info!("Block record deleted: {target_ap_id}");
+                }
+
+                Ok(StatusCode::ACCEPTED)
+            }
             _ => Err(StatusCode::NOT_IMPLEMENTED),
         }
     }
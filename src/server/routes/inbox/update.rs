@@ -3,7 +3,7 @@ use crate::{
     db::runner::DbRunner,
     models::{
         activities::{create_activity, NewActivity},
-        actors::{create_or_update_actor, NewActor},
+        actors::{create_or_update_actor, moved_to_of, NewActor},
         objects::create_object,
     },
     server::AppState,
@@ -33,6 +33,7 @@ impl Inbox for ApUpdate {
 
                     if let Ok(mut new_remote_actor) = NewActor::try_from(actor.clone()) {
                         new_remote_actor.ek_webfinger = webfinger;
+                        new_remote_actor.as_moved_to = raw.get("object").and_then(moved_to_of);
 
                         if actor.clone().id.unwrap_or_default() == self.actor.clone() {
                             let actor = create_or_update_actor(conn, new_remote_actor)
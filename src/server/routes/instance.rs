@@ -1,10 +1,13 @@
 use axum::{
-    extract::Path,
+    extract::{Path, State},
     http::StatusCode,
     response::{IntoResponse, Json},
 };
 use serde::{Deserialize, Serialize};
 
+use crate::models::instances::{get_moderated_instances, Instance, ModerationLevel};
+use crate::server::AppState;
+
 #[derive(Serialize, Deserialize)]
 pub struct RegistrationInformation {
     pub enabled: bool,
@@ -62,6 +65,24 @@ impl Default for InstanceInformation {
     }
 }
 
+/// A moderated domain as published to the public; private comments are never included
+#[derive(Serialize, Deserialize)]
+pub struct DomainModeration {
+    pub domain: String,
+    pub levels: Vec<ModerationLevel>,
+    pub comment: Option<String>,
+}
+
+impl From<Instance> for DomainModeration {
+    fn from(instance: Instance) -> Self {
+        DomainModeration {
+            levels: instance.moderation_levels(),
+            domain: instance.domain_name,
+            comment: instance.public_comment,
+        }
+    }
+}
+
 pub struct _ApiVersion<'r> {
     _version: &'r str,
 }
@@ -80,6 +101,29 @@ pub async fn instance_information(
     }
 }
 
+pub async fn domain_moderation(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<Json<Vec<DomainModeration>>, StatusCode> {
+    if version != "v1" && version != "v2" {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(
+        get_moderated_instances(&conn)
+            .await
+            .into_iter()
+            .map(DomainModeration::from)
+            .collect(),
+    ))
+}
+
 /// Redirects to the install.sh script (configurable via INSTALL_SCRIPT_URL env var)
 pub async fn install_script() -> impl IntoResponse {
     axum::response::Redirect::temporary(&*crate::INSTALL_SCRIPT_URL)
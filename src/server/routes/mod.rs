@@ -18,6 +18,7 @@ pub mod encryption;
 pub mod image;
 pub mod inbox;
 pub mod instance;
+pub mod nodeinfo;
 pub mod objects;
 pub mod outbox;
 pub mod remote;
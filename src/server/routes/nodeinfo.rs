@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::models::activities::get_active_local_user_count;
+use crate::models::actors::get_local_user_count;
+use crate::models::objects::get_local_post_count;
+use crate::nodeinfo::{
+    NodeInfo, NodeInfoLink, NodeInfoLinks, NodeInfoServices, NodeInfoSoftware, NodeInfoUsage,
+    NodeInfoUsers, NODEINFO_2_0_SCHEMA, NODEINFO_2_1_SCHEMA,
+};
+use crate::server::AppState;
+
+pub async fn nodeinfo_links() -> Json<NodeInfoLinks> {
+    let server_url = format!("https://{}", *crate::SERVER_NAME);
+
+    Json(NodeInfoLinks {
+        links: vec![
+            NodeInfoLink {
+                rel: NODEINFO_2_0_SCHEMA.to_string(),
+                href: format!("{server_url}/nodeinfo/2.0"),
+            },
+            NodeInfoLink {
+                rel: NODEINFO_2_1_SCHEMA.to_string(),
+                href: format!("{server_url}/nodeinfo/2.1"),
+            },
+        ],
+    })
+}
+
+pub async fn nodeinfo(
+    State(state): State<AppState>,
+    Path(version): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let schema = match version.as_str() {
+        "2.0" => NODEINFO_2_0_SCHEMA,
+        "2.1" => NODEINFO_2_1_SCHEMA,
+        _ => return Err(StatusCode::NOT_FOUND),
+    };
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let count_error = |e: anyhow::Error| {
+        log::error!("Failed to count NodeInfo usage: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let now = Utc::now();
+    let users = NodeInfoUsers {
+        total: Some(get_local_user_count(&conn).await.map_err(count_error)?),
+        active_month: Some(
+            get_active_local_user_count(&conn, now - Duration::days(30))
+                .await
+                .map_err(count_error)?,
+        ),
+        active_halfyear: Some(
+            get_active_local_user_count(&conn, now - Duration::days(180))
+                .await
+                .map_err(count_error)?,
+        ),
+    };
+
+    // The repository field was added in 2.1
+    let repository = (version == "2.1").then(|| (*crate::INSTANCE_SOURCE_URL).to_string());
+
+    let nodeinfo = NodeInfo {
+        version,
+        software: NodeInfoSoftware {
+            name: "enigmatick".to_string(),
+            version: (*crate::INSTANCE_VERSION).to_string(),
+            repository,
+            homepage: None,
+        },
+        protocols: vec!["activitypub".to_string()],
+        services: NodeInfoServices::default(),
+        open_registrations: *crate::REGISTRATION_ENABLED,
+        usage: NodeInfoUsage {
+            users,
+            local_posts: Some(get_local_post_count(&conn).await.map_err(count_error)?),
+        },
+        metadata: json!({
+            "nodeName": crate::INSTANCE_TITLE.as_str(),
+            "nodeDescription": crate::INSTANCE_DESCRIPTION.as_str(),
+        }),
+    };
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            format!("application/json; profile=\"{schema}#\""),
+        )],
+        Json(nodeinfo),
+    ))
+}
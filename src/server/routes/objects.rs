@@ -1,6 +1,14 @@
 use crate::{
-    models::objects::get_object_by_uuid,
-    server::{extractors::AxumSigned, AppState},
+    db::runner::DbRunner,
+    models::{
+        actors::get_actor_by_as_id,
+        follows::get_follow,
+        objects::{get_object_by_uuid, Object},
+    },
+    server::{
+        extractors::{authorize_fetch, AxumSigned},
+        AppState,
+    },
 };
 use axum::extract::{Path, State};
 use jdt_activity_pub::ApObject;
@@ -8,9 +16,46 @@ use reqwest::StatusCode;
 
 use super::ActivityJson;
 
+/// Whether the object's addressing lets `requester` see it. Objects addressed to an
+/// author's followers are visible to actors whose Follow of that author was accepted.
+pub async fn is_visible<C: DbRunner>(conn: &C, object: &Object, requester: Option<String>) -> bool {
+    if object.is_public() {
+        return true;
+    }
+
+    let Some(requester) = requester else {
+        return false;
+    };
+
+    let authors = object.attributed_to();
+    let recipients = object.recipients();
+
+    if authors.contains(&requester) || recipients.contains(&requester) {
+        return true;
+    }
+
+    for author in authors {
+        let Ok(actor) = get_actor_by_as_id(conn, author.clone()).await else {
+            continue;
+        };
+
+        if actor
+            .as_followers
+            .is_some_and(|followers| recipients.contains(&followers))
+            && get_follow(conn, requester.clone(), author)
+                .await
+                .is_ok_and(|follow| follow.accepted)
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
 pub async fn object_get(
     State(state): State<AppState>,
-    _signed: AxumSigned,
+    signed: AxumSigned,
     Path(uuid): Path<String>,
 ) -> Result<ActivityJson<ApObject>, StatusCode> {
     let conn = match state.db_pool.get().await {
@@ -18,19 +63,25 @@ pub async fn object_get(
         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     };
 
+    let requester = authorize_fetch(&conn, &state.block_list, &signed).await?;
+
     log::debug!("Retrieving Object: {uuid}");
 
-    get_object_by_uuid(&conn, uuid)
-        .await
-        .map_err(|e| {
-            log::error!("Unable to retrieve Object: {e:#?}");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
-        .and_then(|x| match ApObject::try_from(x) {
-            Ok(ap_object) => Ok(ActivityJson(ap_object)),
-            Err(e) => {
-                log::error!("Unable to convert to ApObject: {e:#?}");
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        })
+    let object = get_object_by_uuid(&conn, uuid).await.map_err(|e| {
+        log::error!("Unable to retrieve Object: {e:#?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Objects the requester isn't addressed by are indistinguishable from missing ones
+    if !is_visible(&conn, &object, requester).await {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    match ApObject::try_from(object) {
+        Ok(ap_object) => Ok(ActivityJson(ap_object)),
+        Err(e) => {
+            log::error!("Unable to convert to ApObject: {e:#?}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
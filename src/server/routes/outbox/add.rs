@@ -1,4 +1,118 @@
 use crate::server::routes::Outbox;
-use jdt_activity_pub::ApAdd;
+use crate::server::AppState;
+use crate::{
+    db::runner::DbRunner,
+    models::{
+        activities::{create_activity, ActivityTarget, NewActivity},
+        actors::{update_featured_items_by_as_id, Actor},
+        objects::get_object_by_as_id,
+    },
+    runner,
+};
+use jdt_activity_pub::{ApActivity, ApAdd, ApContext};
+use reqwest::StatusCode;
+use serde_json::Value;
 
-impl Outbox for ApAdd {}
+use super::ActivityJson;
+
+impl Outbox for ApAdd {
+    /// Pins one of the profile's own objects to its featured collection
+    async fn outbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        state: AppState,
+        profile: Actor,
+        raw: Value,
+    ) -> Result<ActivityJson<ApActivity>, StatusCode> {
+        log::debug!("{:?}", self.clone());
+
+        let featured = profile.featured().ok_or_else(|| {
+            log::error!("{} has no featured collection", profile.as_id);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        if self.target.as_ref() != Some(&featured) {
+            log::warn!(
+                "Add target is not the featured collection: {:?}",
+                self.target
+            );
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let object_id = self.object.reference().ok_or_else(|| {
+            log::warn!("Add has no object");
+            StatusCode::BAD_REQUEST
+        })?;
+
+        let object = get_object_by_as_id(conn, object_id.clone())
+            .await
+            .map_err(|_| StatusCode::NOT_FOUND)?;
+
+        if !object.attributed_to().contains(&profile.as_id) {
+            log::warn!("Attempt to pin an object not attributed to the profile");
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        if !object.is_public() {
+            log::warn!("Attempt to pin an object that is not public or unlisted");
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let mut items = profile.featured_items();
+
+        if !items.contains(&object_id) {
+            items.insert(0, object_id.clone());
+
+            update_featured_items_by_as_id(conn, profile.as_id.clone(), items)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update featured items: {e:#?}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+        }
+
+        let add = ApAdd {
+            context: Some(ApContext::activity_streams()),
+            kind: Default::default(),
+            id: None,
+            actor: profile.as_id.clone().into(),
+            object: object_id.into(),
+            target: Some(featured),
+        };
+
+        let mut activity = NewActivity::try_from((
+            ApActivity::Add(add.clone()),
+            Some(ActivityTarget::from(object)),
+        ))
+        .map_err(|e| {
+            log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .link_actor(conn)
+        .await;
+        activity.raw = Some(raw);
+
+        let activity = create_activity(conn, activity).await.map_err(|e| {
+            log::error!("FAILED TO CREATE ACTIVITY: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        let ap_id = activity.ap_id.clone().ok_or_else(|| {
+            log::error!("ActivityPub ID cannot be None for federation");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        runner::run(
+            runner::send_activity_task,
+            state.db_pool,
+            None,
+            vec![ap_id.clone()],
+        )
+        .await;
+
+        Ok(ActivityJson(ApActivity::Add(ApAdd {
+            id: Some(ap_id),
+            ..add
+        })))
+    }
+}
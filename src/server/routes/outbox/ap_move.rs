@@ -1,12 +1,6 @@
 use crate::server::routes::Outbox;
 use crate::server::AppState;
-use crate::{
-    db::runner::DbRunner,
-    models::{
-        activities::{create_activity, NewActivity},
-        actors::Actor,
-    },
-};
+use crate::{db::runner::DbRunner, models::actors::Actor, runner};
 use jdt_activity_pub::{ApActivity, ApMove};
 use reqwest::StatusCode;
 use serde_json::Value;
@@ -14,27 +8,51 @@ use serde_json::Value;
 use super::ActivityJson;
 
 impl Outbox for ApMove {
+    /// Moves the local `profile` to the Move's target. The target must already list the
+    /// profile in `alsoKnownAs`; the profile becomes read-only once the Move is recorded.
     async fn outbox<C: DbRunner>(
         &self,
         conn: &C,
-        _state: AppState,
-        _profile: Actor,
-        raw: Value,
+        state: AppState,
+        profile: Actor,
+        _raw: Value,
     ) -> Result<ActivityJson<ApActivity>, StatusCode> {
         log::debug!("{:?}", self.clone());
 
-        let mut activity =
-            NewActivity::try_from((ApActivity::Move(self.clone()), None)).map_err(|e| {
-                log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
-                StatusCode::INTERNAL_SERVER_ERROR
+        if self
+            .object
+            .reference()
+            .is_some_and(|object| object != profile.as_id)
+        {
+            log::warn!("Attempt to Move an Actor other than the profile");
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let target = self.target.reference().ok_or_else(|| {
+            log::warn!("Move has no target");
+            StatusCode::BAD_REQUEST
+        })?;
+
+        let ap_move = runner::ap_move::create_move(conn, profile.clone(), target.clone())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to create Move: {e:#?}");
+                StatusCode::UNPROCESSABLE_ENTITY
             })?;
-        activity.raw = Some(raw.clone());
 
-        create_activity(conn, activity.clone()).await.map_err(|e| {
-            log::error!("FAILED TO CREATE ACTIVITY: {e:#?}");
+        let ap_id = ap_move.id.clone().ok_or_else(|| {
+            log::error!("ActivityPub ID cannot be None for federation");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        Ok(ActivityJson(ApActivity::Move(self.clone())))
+        runner::run(
+            runner::ap_move::send_move_task,
+            state.db_pool,
+            None,
+            vec![profile.as_id, target, ap_id],
+        )
+        .await;
+
+        Ok(ActivityJson(ApActivity::Move(ap_move)))
     }
 }
@@ -1,4 +1,138 @@
+use super::ActivityJson;
 use crate::server::routes::Outbox;
-use jdt_activity_pub::ApBlock;
+use crate::server::AppState;
+use crate::{
+    db::runner::DbRunner,
+    models::{
+        activities::{
+            create_activity, get_unrevoked_activity_by_kind_actor_id_and_target_ap_id,
+            ActivityType, NewActivity, TryFromExtendedActivity,
+        },
+        actors::{get_actor_by_as_id, Actor},
+        blocks::{create_block, NewBlock},
+        follows::delete_follow,
+    },
+    runner,
+};
+use jdt_activity_pub::{ApActivity, ApBlock};
+use reqwest::StatusCode;
+use serde_json::Value;
 
-impl Outbox for ApBlock {}
+impl Outbox for ApBlock {
+    async fn outbox<C: DbRunner>(
+        &self,
+        conn: &C,
+        state: AppState,
+        profile: Actor,
+        raw: Value,
+    ) -> Result<ActivityJson<ApActivity>, StatusCode> {
+        block_outbox(conn, state, self.clone(), profile, raw).await
+    }
+}
+
+/// Handles an `ApBlock` activity in an outbox.
+///
+/// This records a block by a local user (`profile`) of another actor, removes any follow
+/// relationships between the two, and federates the `Block` to the blocked actor. If the
+/// actor is already blocked, the existing activity is returned and sent again.
+async fn block_outbox<C: DbRunner>(
+    conn: &C,
+    state: AppState,
+    block: ApBlock,
+    profile: Actor,
+    raw: Value,
+) -> Result<ActivityJson<ApActivity>, StatusCode> {
+    log::debug!("{block:?}");
+
+    let actor_to_block = get_actor_by_as_id(conn, block.object.clone())
+        .await
+        .map_err(|e| {
+            log::error!(
+                "Failed to retrieve actor to block '{}': {e:#?}",
+                block.object
+            );
+            StatusCode::NOT_FOUND
+        })?;
+
+    if actor_to_block.id == profile.id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let activity = if let Ok(Some(activity)) =
+        get_unrevoked_activity_by_kind_actor_id_and_target_ap_id(
+            conn,
+            ActivityType::Block,
+            profile.id,
+            actor_to_block.as_id.clone(),
+        )
+        .await
+    {
+        activity
+    } else {
+        // The Block is always attributed to the authenticated profile
+        let block = ApBlock {
+            actor: profile.as_id.clone().into(),
+            id: None,
+            ..block
+        };
+
+        let new_activity = NewActivity::try_from((
+            ApActivity::Block(block.clone()),
+            Some(actor_to_block.clone().into()),
+        ))
+        .map_err(|e| {
+            log::error!("Failed to build NewActivity for Block: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .link_actor(conn)
+        .await
+        .set_raw(raw);
+
+        let created_activity = create_activity(conn, new_activity).await.map_err(|e| {
+            log::error!("Failed to create Block activity in DB: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        create_block(
+            conn,
+            NewBlock {
+                block_activity_ap_id: created_activity.ap_id.clone(),
+                blocker_actor_id: Some(profile.id),
+                blocked_actor_id: Some(actor_to_block.id),
+                ..NewBlock::from(block)
+            },
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to create Block record in DB: {e:#?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        for (follower, leader) in [
+            (profile.as_id.clone(), actor_to_block.as_id.clone()),
+            (actor_to_block.as_id.clone(), profile.as_id.clone()),
+        ] {
+            if let Err(e) = delete_follow(conn, follower, leader).await {
+                log::error!("Failed to remove Follow after Block: {e:#?}");
+            }
+        }
+
+        created_activity
+    };
+
+    let ap_id = activity.ap_id.clone().ok_or_else(|| {
+        log::error!("ActivityPub ID cannot be None for federation");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    runner::run(runner::send_activity_task, state.db_pool, None, vec![ap_id]).await;
+
+    let ap_activity =
+        ApActivity::try_from_extended_activity((activity, None, None, Some(actor_to_block)))
+            .map_err(|e| {
+                log::error!("Failed to build ApActivity from ExtendedActivity: {e:#?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    Ok(ActivityJson(ap_activity))
+}
@@ -0,0 +1,64 @@
+use crate::server::AppState;
+use crate::{
+    db::runner::DbRunner,
+    helper::{get_activity_ap_id_from_uuid, is_local},
+    models::{
+        actors::Actor,
+        objects::get_object_by_as_id,
+        reactions::{create_reaction, ApEmojiReact, NewReaction},
+    },
+    runner,
+};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use jdt_activity_pub::ApAddress;
+
+/// Records a reaction by the `profile` from an EmojiReact posted to its outbox and sends it
+/// to the author of a remote object. EmojiReact is not an `ApActivity`, so this is called
+/// directly rather than through the `Outbox` trait.
+pub async fn outbox<C: DbRunner>(
+    conn: &C,
+    state: AppState,
+    profile: Actor,
+    mut react: ApEmojiReact,
+) -> Result<Response, StatusCode> {
+    log::debug!("{react:?}");
+
+    let object = get_object_by_as_id(conn, react.object.clone())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to retrieve Object: {e:#?}");
+            StatusCode::NOT_FOUND
+        })?;
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+    react.id = Some(get_activity_ap_id_from_uuid(uuid.clone()));
+    react.actor = ApAddress::Address(profile.as_id.clone());
+
+    let mut reaction = NewReaction::try_from(react).map_err(|e| {
+        log::warn!("Failed to build reaction: {e}");
+        StatusCode::BAD_REQUEST
+    })?;
+    reaction.uuid = uuid;
+    reaction.actor_id = Some(profile.id);
+    reaction.object_id = Some(object.id);
+
+    let reaction = create_reaction(conn, reaction).await.map_err(|e| {
+        log::error!("Failed to create reaction: {e:#?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if !is_local(object.as_id) {
+        runner::run(
+            runner::reaction::send_reaction_task,
+            state.db_pool,
+            None,
+            vec![reaction.ap_id.clone()],
+        )
+        .await;
+    }
+
+    Ok((StatusCode::CREATED, Json(ApEmojiReact::from(reaction))).into_response())
+}
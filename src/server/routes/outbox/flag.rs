@@ -0,0 +1,45 @@
+use crate::server::AppState;
+use crate::{
+    db::runner::DbRunner,
+    helper::is_local,
+    models::{actors::Actor, reports::ApFlag},
+    runner,
+};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+
+/// Files a report by the `profile` from a Flag posted to its outbox. Flag is not an
+/// `ApActivity`, so this is called directly rather than through the `Outbox` trait.
+///
+/// Reports about remote actors are forwarded to their server when the Flag sets `forward`.
+pub async fn outbox<C: DbRunner>(
+    conn: &C,
+    state: AppState,
+    profile: Actor,
+    flag: ApFlag,
+) -> Result<Response, StatusCode> {
+    log::debug!("{flag:?}");
+
+    let forward = flag.forward;
+
+    let report = runner::report::file_report(conn, profile, flag)
+        .await
+        .map_err(|e| {
+            log::warn!("Failed to file report: {e}");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+    if forward && !is_local(report.target_ap_id.clone()) {
+        runner::run(
+            runner::report::send_flag_task,
+            state.db_pool,
+            None,
+            vec![report.uuid.clone()],
+        )
+        .await;
+    }
+
+    Ok((StatusCode::CREATED, Json(report)).into_response())
+}
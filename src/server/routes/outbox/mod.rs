@@ -3,21 +3,27 @@ use super::ActivityJson;
 use crate::server::retriever;
 use crate::server::routes::Outbox;
 use crate::{
+    load_page_ephemeral,
     models::{
         activities::{TimelineFilters, TimelineView},
         actors::get_actor_by_username,
         objects::ObjectType,
+        reactions::ApEmojiReact,
+        reports::ApFlag,
         unprocessable::create_unprocessable,
     },
-    server::{extractors::AxumSigned, AppState},
+    server::{
+        extractors::{authorize_fetch, AxumSigned},
+        AppState,
+    },
 };
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
 use axum_extra::extract::Query;
-use jdt_activity_pub::{ActivityPub, ApActivity, ApObject};
+use jdt_activity_pub::ActivityPub;
 use serde::Deserialize;
 use serde_json::Value;
 
@@ -29,6 +35,8 @@ pub mod ap_move;
 pub mod block;
 pub mod create;
 pub mod delete;
+pub mod emoji_react;
+pub mod flag;
 pub mod follow;
 pub mod like;
 pub mod remove;
@@ -68,13 +76,15 @@ pub async fn axum_outbox_get(
     signed: AxumSigned,
     Path(username): Path<String>,
     Query(query): Query<OutboxQuery>,
-) -> Result<Json<ApObject>, StatusCode> {
+) -> Result<Json<Value>, StatusCode> {
     let conn = state
         .db_pool
         .get()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    authorize_fetch(&conn, &state.block_list, &signed).await?;
+
     let profile = signed.profile();
     let server_url = format!("https://{}", *crate::SERVER_NAME);
     let limit = query.limit.unwrap_or(10);
@@ -99,6 +109,7 @@ pub async fn axum_outbox_get(
             excluded_words: vec![],
             direct: false,
             object_type: query.object_type.clone(),
+            blocked_actors: vec![],
         };
 
         // Build base URL with type and hashtag parameters if specified
@@ -114,22 +125,28 @@ pub async fn axum_outbox_get(
             String::new()
         };
 
-        Ok(Json(
-            retriever::activities(
-                &conn,
-                limit.into(),
-                query.min,
-                query.max,
-                profile,
-                filters,
-                format!("{base_url}?page=true&limit={limit}{type_param}{hashtags_param}"),
-            )
-            .await,
-        ))
+        let result = retriever::activities(
+            &conn,
+            limit.into(),
+            query.min,
+            query.max,
+            profile.clone(),
+            filters,
+            format!("{base_url}?page=true&limit={limit}{type_param}{hashtags_param}"),
+        )
+        .await;
+
+        load_page_ephemeral(&conn, profile, result)
+            .await
+            .map(Json)
+            .map_err(|e| {
+                log::error!("Failed to load page ephemeral data: {e}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })
     } else if let Ok(profile) = get_actor_by_username(&conn, username).await {
-        Ok(Json(
-            retriever::outbox_collection(&conn, profile, limit).await,
-        ))
+        serde_json::to_value(retriever::outbox_collection(&conn, profile, limit).await)
+            .map(Json)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
     } else {
         Err(StatusCode::NOT_FOUND)
     }
@@ -140,9 +157,14 @@ pub async fn axum_outbox_post(
     Path(_username): Path<String>,
     signed: AxumSigned,
     Json(raw): Json<Value>,
-) -> Result<ActivityJson<ApActivity>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let profile = signed.profile().ok_or(StatusCode::UNAUTHORIZED)?;
 
+    // An account that has moved is read-only
+    if profile.as_moved_to.is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     let conn = state
         .db_pool
         .get()
@@ -151,17 +173,23 @@ pub async fn axum_outbox_post(
 
     if let Ok(object) = serde_json::from_value::<ActivityPub>(raw.clone()) {
         match object {
-            ActivityPub::Activity(activity) => {
-                activity
-                    .outbox(&conn, state.clone(), profile, raw.clone())
-                    .await
-            }
-            ActivityPub::Object(object) => object.outbox(&conn, state, profile, raw).await,
+            ActivityPub::Activity(activity) => activity
+                .outbox(&conn, state.clone(), profile, raw.clone())
+                .await
+                .map(IntoResponse::into_response),
+            ActivityPub::Object(object) => object
+                .outbox(&conn, state, profile, raw)
+                .await
+                .map(IntoResponse::into_response),
             _ => {
                 create_unprocessable(&conn, raw.into()).await;
                 Err(StatusCode::NOT_IMPLEMENTED)
             }
         }
+    } else if let Ok(flag) = serde_json::from_value::<ApFlag>(raw.clone()) {
+        flag::outbox(&conn, state, profile, flag).await
+    } else if let Ok(react) = serde_json::from_value::<ApEmojiReact>(raw.clone()) {
+        emoji_react::outbox(&conn, state, profile, react).await
     } else {
         create_unprocessable(&conn, raw.into()).await;
         Err(StatusCode::UNPROCESSABLE_ENTITY)
@@ -19,7 +19,11 @@ use crate::{
         votes::{get_question_for_vote, is_vote, validate_vote, VoteError},
     },
     retriever::get_actor,
-    runner::{self, get_inboxes, send_to_inboxes, TaskError},
+    runner::{
+        self, get_inboxes,
+        quote::{authorize_quote, quote_link, quote_of, quote_of_json},
+        send_to_inboxes, TaskError,
+    },
     server::routes::user::process_instrument,
     LoadEphemeral,
 };
@@ -257,6 +261,25 @@ async fn note_outbox<C: DbRunner>(
         Ok(instruments)
     }
 
+    // Quotes may be given as a property or as a link; the link is what's stored and sent
+    if let Some(quoted) = quote_of_json(&raw) {
+        let permitted = authorize_quote(conn, &profile, quoted.clone())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to retrieve quoted object {quoted}: {e}");
+                StatusCode::NOT_FOUND
+            })?;
+
+        if !permitted {
+            log::info!("{} is not permitted to quote {quoted}", profile.as_id);
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        if quote_of(&serde_json::to_value(&note.tag).ok()).is_none() {
+            note.tag = note.tag.clone().extend(vec![quote_link(quoted)]);
+        }
+    }
+
     prepare_note_metadata(&mut note, &profile);
 
     let start = std::time::Instant::now();
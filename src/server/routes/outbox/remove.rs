@@ -3,38 +3,106 @@ use crate::server::AppState;
 use crate::{
     db::runner::DbRunner,
     models::{
-        activities::{create_activity, NewActivity},
-        actors::Actor,
+        activities::{create_activity, ActivityTarget, NewActivity},
+        actors::{update_featured_items_by_as_id, Actor},
+        objects::get_object_by_as_id,
     },
+    runner,
 };
-use jdt_activity_pub::{ApActivity, ApRemove};
+use jdt_activity_pub::{ApActivity, ApContext, ApRemove};
 use reqwest::StatusCode;
 use serde_json::Value;
 
 use super::ActivityJson;
 
 impl Outbox for ApRemove {
+    /// Unpins an object from the profile's featured collection
     async fn outbox<C: DbRunner>(
         &self,
         conn: &C,
-        _state: AppState,
-        _profile: Actor,
+        state: AppState,
+        profile: Actor,
         raw: Value,
     ) -> Result<ActivityJson<ApActivity>, StatusCode> {
         log::debug!("{:?}", self.clone());
 
-        let mut activity = NewActivity::try_from((ApActivity::Remove(self.clone()), None))
+        let featured = profile.featured().ok_or_else(|| {
+            log::error!("{} has no featured collection", profile.as_id);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        if self.target.reference() != Some(featured.clone()) {
+            log::warn!(
+                "Remove target is not the featured collection: {:?}",
+                self.target
+            );
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let object_id = self.object.reference().ok_or_else(|| {
+            log::warn!("Remove has no object");
+            StatusCode::BAD_REQUEST
+        })?;
+
+        let mut items = profile.featured_items();
+
+        let Some(pos) = items.iter().position(|item| item == &object_id) else {
+            log::warn!("{object_id} is not pinned by {}", profile.as_id);
+            return Err(StatusCode::NOT_FOUND);
+        };
+
+        items.remove(pos);
+
+        update_featured_items_by_as_id(conn, profile.as_id.clone(), items)
+            .await
             .map_err(|e| {
-                log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
+                log::error!("Failed to update featured items: {e:#?}");
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
-        activity.raw = Some(raw.clone());
 
-        create_activity(conn, activity.clone()).await.map_err(|e| {
+        let remove = ApRemove {
+            context: Some(ApContext::activity_streams()),
+            actor: profile.as_id.clone().into(),
+            object: object_id.clone().into(),
+            target: featured.into(),
+            ..Default::default()
+        };
+
+        let target = get_object_by_as_id(conn, object_id)
+            .await
+            .ok()
+            .map(ActivityTarget::from);
+
+        let mut activity = NewActivity::try_from((ApActivity::Remove(remove.clone()), target))
+            .map_err(|e| {
+                log::error!("FAILED TO BUILD ACTIVITY: {e:#?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .link_actor(conn)
+            .await;
+        activity.raw = Some(raw);
+
+        let activity = create_activity(conn, activity).await.map_err(|e| {
             log::error!("FAILED TO CREATE ACTIVITY: {e:#?}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        Ok(ActivityJson(ApActivity::Remove(self.clone())))
+        let ap_id = activity.ap_id.clone().ok_or_else(|| {
+            log::error!("ActivityPub ID cannot be None for federation");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        runner::run(
+            runner::send_activity_task,
+            state.db_pool,
+            None,
+            vec![ap_id.clone()],
+        )
+        .await;
+
+        Ok(ActivityJson(ApActivity::Remove(ApRemove {
+            id: Some(ap_id),
+            ..remove
+        })))
     }
 }
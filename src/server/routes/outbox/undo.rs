@@ -4,8 +4,9 @@ use crate::server::AppState;
 use crate::{
     db::runner::DbRunner,
     events::EventChannels,
-    helper::{get_local_identifier, LocalIdentifierType},
-    models::{activities::revoke_activity_by_apid, follows::delete_follow},
+    helper::{get_activity_ap_id_from_uuid, get_local_identifier, is_local, LocalIdentifierType},
+    models::reactions::{delete_reaction_by_ap_id, get_reaction_by_ap_id, Reaction},
+    models::{activities::revoke_activity_by_apid, blocks::delete_block, follows::delete_follow},
     models::{
         activities::{
             create_activity, get_activity_by_ap_id, revoke_activity_by_uuid, ActivityType,
@@ -16,7 +17,7 @@ use crate::{
     runner::{self, get_inboxes, send_to_inboxes, TaskError},
 };
 use deadpool_diesel::postgres::Pool;
-use jdt_activity_pub::{ApActivity, ApAddress, ApUndo};
+use jdt_activity_pub::{ApActivity, ApAddress, ApContext, ApUndo, ApUndoType, MaybeReference};
 use reqwest::StatusCode;
 use serde_json::Value;
 
@@ -28,20 +29,32 @@ impl Outbox for Box<ApUndo> {
         profile: Actor,
         raw: Value,
     ) -> Result<ActivityJson<ApActivity>, StatusCode> {
-        let target_ap_id = self.object.reference();
+        let target_ap_id = self.object.reference().ok_or(StatusCode::BAD_REQUEST)?;
 
-        let (activity, _target_activity, target_object, _target_actor) =
-            get_activity_by_ap_id(conn, target_ap_id.ok_or(StatusCode::BAD_REQUEST)?)
+        let Some((activity, _target_activity, target_object, _target_actor)) =
+            get_activity_by_ap_id(conn, target_ap_id.clone())
                 .await
                 .map_err(|e| {
                     log::error!("Failed to retrieve target Activity: {e}");
                     StatusCode::NOT_FOUND
                 })?
-                .ok_or_else(|| {
+        else {
+            // Reactions sent as EmojiReact are recorded apart from activities
+            let reaction = get_reaction_by_ap_id(conn, target_ap_id)
+                .await
+                .map_err(|_| {
                     log::warn!("Activity not found");
                     StatusCode::NOT_FOUND
                 })?;
 
+            return undo_reaction(conn, state, profile, reaction).await;
+        };
+
+        if activity.actor != profile.as_id {
+            log::warn!("Attempt to Undo an Activity belonging to another Actor");
+            return Err(StatusCode::FORBIDDEN);
+        }
+
         let undo = create_activity(
             conn,
             NewActivity::from((
@@ -83,6 +96,47 @@ impl Outbox for Box<ApUndo> {
     }
 }
 
+/// Withdraws a reaction sent as EmojiReact. Reactions to local objects are deleted here;
+/// others are deleted by `send_reaction_undo_task` once the Undo is sent.
+async fn undo_reaction<C: DbRunner>(
+    conn: &C,
+    state: AppState,
+    profile: Actor,
+    reaction: Reaction,
+) -> Result<ActivityJson<ApActivity>, StatusCode> {
+    if reaction.actor_ap_id != profile.as_id {
+        log::warn!("Attempt to Undo a reaction belonging to another Actor");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+
+    if is_local(reaction.object_ap_id.clone()) {
+        delete_reaction_by_ap_id(conn, reaction.ap_id.clone())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete reaction: {e:#?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    } else {
+        runner::run(
+            runner::reaction::send_reaction_undo_task,
+            state.db_pool,
+            None,
+            vec![reaction.ap_id.clone(), uuid.clone()],
+        )
+        .await;
+    }
+
+    Ok(ActivityJson(ApActivity::Undo(Box::new(ApUndo {
+        context: Some(ApContext::activity_streams()),
+        kind: ApUndoType::Undo,
+        actor: ApAddress::Address(profile.as_id),
+        id: Some(get_activity_ap_id_from_uuid(uuid)),
+        object: MaybeReference::Reference(reaction.ap_id),
+    }))))
+}
+
 async fn send_task(
     pool: Pool,
     _channels: Option<EventChannels>,
@@ -183,6 +237,20 @@ async fn send_task(
                         })?;
                 }
             }
+            ApActivity::Block(block) => {
+                let block_activity_ap_id = block.id.ok_or(TaskError::TaskFailed)?;
+                log::debug!("ApBlock ID to Undo: {block_activity_ap_id}");
+
+                if delete_block(&conn, block.actor.to_string(), block.object)
+                    .await
+                    .is_ok()
+                    && revoke_activity_by_apid(&conn, block_activity_ap_id)
+                        .await
+                        .is_ok()
+                {
+                    log::info!("Block deleted");
+                }
+            }
             _ => {
                 log::error!("FAILED TO MATCH REVOCABLE ACTIVITY");
                 return Err(TaskError::TaskFailed);
@@ -1,12 +1,13 @@
 use crate::{
     db::runner::DbRunner,
     helper::{get_domain_from_url, get_domain_from_webfinger},
-    models::actors::{get_actor_by_webfinger, Actor},
+    load_page_ephemeral,
+    models::actors::{get_actor_by_as_id, get_actor_by_webfinger, Actor},
     retriever::{
         get_actor, get_ap_id_from_webfinger, get_object, get_remote_collection,
         get_remote_collection_page,
     },
-    server::{extractors::AxumSigned, AppState},
+    server::{extractors::AxumSigned, routes::user::actor_document, AppState},
     GetWebfinger, LoadEphemeral,
 };
 use axum::{
@@ -16,6 +17,7 @@ use axum::{
 };
 use jdt_activity_pub::{ApActor, ApObject};
 use serde::Deserialize;
+use serde_json::Value;
 
 // Helper function to reduce repetition
 async fn remote_actor_logic(
@@ -28,7 +30,7 @@ async fn remote_actor_logic(
         Ok(Json(
             ApActor::from(actor).load_ephemeral(conn, requester).await,
         ))
-    } else if let Ok(ap_id) = get_ap_id_from_webfinger(webfinger).await {
+    } else if let Ok(ap_id) = get_ap_id_from_webfinger(conn, webfinger).await {
         log::debug!("RETRIEVING ACTOR WEBFINGER FROM REMOTE OR LOCAL PROFILE");
         if let Ok(actor) = get_actor(conn, ap_id, requester, true).await {
             Ok(Json(actor))
@@ -62,7 +64,7 @@ pub async fn remote_actor(
     State(state): State<AppState>,
     signed: AxumSigned,
     Query(query): Query<WebfingerQuery>,
-) -> Result<Json<ApActor>, StatusCode> {
+) -> Result<Json<Value>, StatusCode> {
     if state
         .block_list
         .is_blocked(get_domain_from_webfinger(query.webfinger.clone()))
@@ -76,7 +78,17 @@ pub async fn remote_actor(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    remote_actor_logic(&conn, query.webfinger, signed.profile()).await
+    let Json(actor) = remote_actor_logic(&conn, query.webfinger, signed.profile()).await?;
+
+    let moved_to = match actor.id.clone() {
+        Some(id) => get_actor_by_as_id(&conn, id.to_string())
+            .await
+            .ok()
+            .and_then(|actor| actor.as_moved_to),
+        None => None,
+    };
+
+    Ok(Json(actor_document(actor, moved_to)?))
 }
 
 pub async fn remote_webfinger_by_id(
@@ -223,6 +235,43 @@ pub async fn remote_outbox(
     }
 }
 
+/// Serves our copy of a remote actor's featured collection, kept current by inbound
+/// Add and Remove activities
+pub async fn remote_featured(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Query(query): Query<WebfingerQuery>,
+) -> Result<Json<ApObject>, StatusCode> {
+    if state
+        .block_list
+        .is_blocked(get_domain_from_webfinger(query.webfinger.clone()))
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let Json(actor) = remote_actor_logic(&conn, query.webfinger, signed.profile()).await?;
+    let actor_id = actor.id.ok_or(StatusCode::NOT_FOUND)?.to_string();
+
+    let actor = get_actor_by_as_id(&conn, actor_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(ApObject::Collection(
+        crate::server::retriever::featured_collection(
+            &conn,
+            actor,
+            signed.profile().map(|profile| profile.as_id),
+        )
+        .await,
+    )))
+}
+
 pub async fn remote_keys(
     State(state): State<AppState>,
     signed: AxumSigned,
@@ -257,7 +306,7 @@ pub async fn remote_object(
     State(state): State<AppState>,
     signed: AxumSigned,
     Query(query): Query<IdQuery>,
-) -> Result<Json<ApObject>, StatusCode> {
+) -> Result<Json<Value>, StatusCode> {
     let url = urlencoding::decode(&query.id).map_err(|e| {
         log::warn!("Failed to decode URL parameter: {}", e);
         StatusCode::BAD_REQUEST
@@ -292,5 +341,11 @@ pub async fn remote_object(
         })?;
 
     log::debug!("remote_object succeeded for: {}", url);
-    Ok(Json(object))
+    load_page_ephemeral(&conn, signed.profile(), object)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            log::error!("Failed to load ephemeral data: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
 }
@@ -4,8 +4,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::db::runner::DbRunner;
 use crate::models::actors::Actor;
+use crate::models::blocks::get_hidden_actor_ap_ids;
+use crate::models::instances::get_hidden_domains;
 use crate::models::objects::Object;
 use crate::search::{SearchContext, SearchFilters};
+use crate::server::extractors::AxumSigned;
 use crate::server::AppState;
 
 #[derive(Deserialize)]
@@ -146,10 +149,33 @@ fn object_to_object_result(object: &Object) -> ObjectResult {
 pub async fn search(
     State(state): State<AppState>,
     Query(query): Query<SearchQuery>,
+    signed: AxumSigned,
 ) -> Result<Json<SearchResults>, StatusCode> {
-    // Build search context (unauthenticated for now)
-    // TODO: Add authentication support to filter results based on user permissions
-    let context = SearchContext::default();
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Build search context, hiding actors on either side of a block with the requester and
+    // domains hidden by moderation
+    let hidden_domains = get_hidden_domains(&conn).await.unwrap_or_default();
+
+    let context = if let Some(profile) = signed.profile() {
+        SearchContext {
+            blocked_actors: get_hidden_actor_ap_ids(&conn, profile.as_id.clone())
+                .await
+                .unwrap_or_default(),
+            user_id: Some(profile.as_id),
+            is_admin: false,
+            hidden_domains,
+        }
+    } else {
+        SearchContext {
+            hidden_domains,
+            ..Default::default()
+        }
+    };
 
     // Parse sort order
     use crate::search::SortOrder;
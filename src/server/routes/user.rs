@@ -4,8 +4,8 @@ use crate::{
         activities::lookup_activity_id_by_as_id,
         actors::{
             get_actor_by_username, set_mls_credentials_by_username, update_avatar_by_username,
-            update_banner_by_username, update_mls_storage_by_username, update_summary_by_username,
-            Actor,
+            update_banner_by_username, update_mls_storage_by_username,
+            update_quotes_allowed_by_username, update_summary_by_username, Actor,
         },
         follows::{
             get_follower_count_by_actor_id, get_followers_by_actor_id,
@@ -18,7 +18,10 @@ use crate::{
         OffsetPaging,
     },
     runner::{self, user::send_actor_update_task},
-    server::{extractors::AxumSigned, AppState},
+    server::{
+        extractors::{authorize_fetch, AxumSigned},
+        retriever, AppState,
+    },
     LoadEphemeral,
 };
 use axum::{
@@ -33,7 +36,7 @@ use img_parts::png::Png;
 use img_parts::{Bytes as ImgBytes, ImageEXIF};
 use jdt_activity_pub::{
     ActivityPub, ApActor, ApCollection, ApImage, ApInstrument, ApInstrumentType, ApObject,
-    Collectible, FollowersPage, LeadersPage, MaybeReference,
+    ApPublicKey, Collectible, FollowersPage, LeadersPage, MaybeReference,
 };
 use serde::Deserialize;
 use serde_json::json;
@@ -48,6 +51,11 @@ pub struct SummaryUpdate {
     pub markdown: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct QuotesUpdate {
+    pub allowed: bool,
+}
+
 /// Strip EXIF and other metadata from an image file
 fn strip_metadata(path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let bytes = std::fs::read(path)?;
@@ -321,12 +329,24 @@ pub async fn person_post(
     }
 }
 
+/// Serializes an actor along with the account it moved to. ApActor has no movedTo property,
+/// so it is added to the document for moved accounts.
+pub fn actor_document(actor: ApActor, moved_to: Option<String>) -> Result<Value, StatusCode> {
+    let mut actor = serde_json::to_value(actor).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let (Some(moved_to), Some(object)) = (moved_to, actor.as_object_mut()) {
+        object.insert("movedTo".to_string(), json!(moved_to));
+    }
+
+    Ok(actor)
+}
+
 pub async fn person_get(
     State(state): State<AppState>,
     signed: AxumSigned,
     Path(username): Path<String>,
     headers: HeaderMap,
-) -> Result<AbstractResponse<ApActor>, StatusCode> {
+) -> Result<AbstractResponse<Value>, StatusCode> {
     let conn = match state.db_pool.get().await {
         Ok(c) => c,
         Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
@@ -337,7 +357,31 @@ pub async fn person_get(
         Err(_) => return Err(StatusCode::NOT_FOUND),
     };
 
-    let actor = if signed.local() {
+    // In secure mode an unsigned fetch only receives what is needed to verify the actor's
+    // signatures, which lets servers that require signed fetches resolve the keys
+    let minimal = match authorize_fetch(&conn, &state.block_list, &signed).await {
+        Ok(_) => false,
+        Err(StatusCode::UNAUTHORIZED) => true,
+        Err(status) => return Err(status),
+    };
+
+    let moved_to = profile.as_moved_to.clone();
+
+    let actor = if minimal {
+        let actor = ApActor::from(profile);
+        ApActor {
+            context: actor.context,
+            kind: actor.kind,
+            id: actor.id,
+            preferred_username: actor.preferred_username,
+            inbox: actor.inbox,
+            outbox: actor.outbox,
+            public_key: actor.public_key,
+            endpoints: actor.endpoints,
+            assertion_method: actor.assertion_method,
+            ..Default::default()
+        }
+    } else if signed.local() {
         ApActor::from(profile)
             .load_ephemeral(&conn, signed.profile())
             .await
@@ -346,6 +390,8 @@ pub async fn person_get(
         ApActor::from(profile)
     };
 
+    let actor = actor_document(actor, moved_to)?;
+
     if let Some(accept) = headers.get(header::ACCEPT) {
         if let Ok(accept_str) = accept.to_str() {
             if accept_str.contains("text/html") {
@@ -369,8 +415,40 @@ pub async fn person_get(
     Ok(AbstractResponse::ActivityJson(ActivityJson(actor)))
 }
 
+/// Serves a signing key by its ID: the user's current key or, within the grace period,
+/// the key replaced by the last rotation
+pub async fn public_key_get(
+    State(state): State<AppState>,
+    Path((username, key)): Path<(String, String)>,
+) -> Result<ActivityJson<Value>, StatusCode> {
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let profile = get_actor_by_username(&conn, username)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let key_id = format!("{}/main-key/{key}", profile.as_id);
+
+    let public_key = serde_json::from_value::<ApPublicKey>(profile.as_public_key.clone())
+        .ok()
+        .into_iter()
+        .chain(profile.previous_public_key())
+        .find(|public_key| public_key.id == key_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut document = json!(public_key);
+    document["@context"] = json!("https://w3id.org/security/v1");
+
+    Ok(ActivityJson(document))
+}
+
 pub async fn liked_get(
     State(state): State<AppState>,
+    signed: AxumSigned,
     Path(username): Path<String>,
 ) -> Result<ActivityJson<ApCollection>, StatusCode> {
     // This is a stub
@@ -379,6 +457,9 @@ pub async fn liked_get(
         .get()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    authorize_fetch(&conn, &state.block_list, &signed).await?;
+
     if get_actor_by_username(&conn, username).await.is_ok() {
         Ok(ActivityJson(ApCollection::default()))
     } else {
@@ -386,6 +467,28 @@ pub async fn liked_get(
     }
 }
 
+pub async fn featured_get(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(username): Path<String>,
+) -> Result<ActivityJson<ApCollection>, StatusCode> {
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let requester = authorize_fetch(&conn, &state.block_list, &signed).await?;
+
+    let profile = get_actor_by_username(&conn, username)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(ActivityJson(
+        retriever::featured_collection(&conn, profile, requester).await,
+    ))
+}
+
 #[derive(Deserialize)]
 pub struct PagingQuery {
     page: Option<u32>,
@@ -393,6 +496,7 @@ pub struct PagingQuery {
 
 pub async fn get_followers(
     State(state): State<AppState>,
+    signed: AxumSigned,
     Path(username): Path<String>,
     Query(query): Query<PagingQuery>,
 ) -> Result<ActivityJson<ApCollection>, StatusCode> {
@@ -402,6 +506,8 @@ pub async fn get_followers(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    authorize_fetch(&conn, &state.block_list, &signed).await?;
+
     let profile = get_actor_by_username(&conn, username)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
@@ -467,6 +573,7 @@ pub async fn get_followers(
 
 pub async fn get_leaders(
     State(state): State<AppState>,
+    signed: AxumSigned,
     Path(username): Path<String>,
     Query(query): Query<PagingQuery>,
 ) -> Result<ActivityJson<ApCollection>, StatusCode> {
@@ -475,6 +582,9 @@ pub async fn get_leaders(
         .get()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    authorize_fetch(&conn, &state.block_list, &signed).await?;
+
     let profile = get_actor_by_username(&conn, username)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
@@ -545,6 +655,14 @@ pub async fn update_summary(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // An account that has moved is read-only
+    if signed
+        .profile()
+        .is_some_and(|profile| profile.as_moved_to.is_some())
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     let Json(summary) = summary.map_err(|e| {
         log::error!("Failed to decode Summary: {e:#?}");
         StatusCode::BAD_REQUEST
@@ -574,6 +692,45 @@ pub async fn update_summary(
     Ok(Json(profile))
 }
 
+/// Sets whether other actors may quote the user's posts
+pub async fn update_quotes(
+    State(state): State<AppState>,
+    signed: AxumSigned,
+    Path(username): Path<String>,
+    update: Result<Json<QuotesUpdate>, JsonRejection>,
+) -> Result<Json<Actor>, StatusCode> {
+    let profile = signed.profile().ok_or(StatusCode::FORBIDDEN)?;
+
+    if profile.ek_username.as_deref() != Some(username.as_str()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // An account that has moved is read-only
+    if profile.as_moved_to.is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let Json(update) = update.map_err(|e| {
+        log::error!("Failed to decode QuotesUpdate: {e:#?}");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let conn = state
+        .db_pool
+        .get()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let profile = update_quotes_allowed_by_username(&conn, username, update.allowed)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update quote setting: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(profile))
+}
+
 pub async fn upload_avatar(
     State(state): State<AppState>,
     signed: AxumSigned,
@@ -584,6 +741,14 @@ pub async fn upload_avatar(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // An account that has moved is read-only
+    if signed
+        .profile()
+        .is_some_and(|profile| profile.as_moved_to.is_some())
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     if bytes.len() > 20 * 1024 * 1024 {
         // 20 MiB
         return Err(StatusCode::PAYLOAD_TOO_LARGE);
@@ -635,6 +800,14 @@ pub async fn upload_banner(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // An account that has moved is read-only
+    if signed
+        .profile()
+        .is_some_and(|profile| profile.as_moved_to.is_some())
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     if bytes.len() > 20 * 1024 * 1024 {
         // 20 MiB
         return Err(StatusCode::PAYLOAD_TOO_LARGE);
@@ -679,7 +852,7 @@ pub async fn user_get_api(
     State(state): State<AppState>,
     Path(username): Path<String>,
     signed: AxumSigned,
-) -> Result<ActivityJson<ApActor>, StatusCode> {
+) -> Result<ActivityJson<Value>, StatusCode> {
     let conn = state
         .db_pool
         .get()
@@ -688,8 +861,9 @@ pub async fn user_get_api(
     let profile = get_actor_by_username(&conn, username)
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
+    let moved_to = profile.as_moved_to.clone();
     let actor = ApActor::from(profile)
         .load_ephemeral(&conn, signed.profile())
         .await;
-    Ok(ActivityJson(actor))
+    Ok(ActivityJson(actor_document(actor, moved_to)?))
 }
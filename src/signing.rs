@@ -2,6 +2,11 @@ use std::collections::HashMap;
 
 use crate::db::runner::DbRunner;
 use crate::models::actors::{get_actor_by_key_id, get_actor_by_username, Actor};
+use crate::models::instances::{
+    get_instance_by_domain_name, get_signature_schemes_by_domain, record_instance_signature_scheme,
+    SignatureScheme,
+};
+use crate::retriever::resolve_public_key;
 use crate::{ASSIGNMENT_RE, LOCAL_USER_KEY_ID_RE};
 use anyhow::anyhow;
 use base64::{engine::general_purpose, engine::Engine as _};
@@ -12,7 +17,7 @@ use rsa::{pkcs8::DecodePrivateKey, pkcs8::DecodePublicKey, RsaPrivateKey, RsaPub
 use sha2::{Digest, Sha256};
 use std::error::Error;
 use std::fmt::{self, Debug};
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
 #[derive(Clone, Debug)]
@@ -25,17 +30,26 @@ pub struct VerifyMapParams {
     pub content_type: Option<String>,
     pub content_length: Option<String>,
     pub user_agent: Option<String>,
+    /// The RFC 9421 `Signature-Input` header; when present, `signature` holds the RFC 9421
+    /// `Signature` dictionary rather than a draft-cavage signature
+    pub signature_input: Option<String>,
+    /// The RFC 9421 signature base, built by the receiver from the covered components
+    pub signature_base: Option<String>,
 }
 
 impl std::fmt::Display for VerifyMapParams {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Request[target: {}, host: {}, signature: {}, date: {}{}{}{}{}]",
+            "Request[target: {}, host: {}, signature: {}, date: {}{}{}{}{}{}]",
             self.request_target,
             self.host,
             self.signature,
             self.date,
+            self.signature_input
+                .as_ref()
+                .map(|si| format!(", signature-input: {si}"))
+                .unwrap_or_default(),
             self.digest
                 .as_ref()
                 .map(|d| format!(", digest: {d}"))
@@ -85,7 +99,296 @@ impl std::fmt::Display for VerifyParams {
     }
 }
 
+/// The signature algorithm accepted in RFC 9421 signatures
+const RFC9421_ALGORITHM: &str = "rsa-v1_5-sha256";
+
+/// The label given to RFC 9421 signatures on outbound requests
+const RFC9421_LABEL: &str = "sig1";
+
+/// How long after its `created` time an RFC 9421 signature is accepted
+const RFC9421_MAX_AGE_SECS: u64 = 12 * 60 * 60;
+
+/// How far ahead of this server's clock an RFC 9421 `created` time may be
+const RFC9421_CLOCK_SKEW_SECS: u64 = 60 * 60;
+
+/// A member of an RFC 9421 `Signature-Input` header
+#[derive(Clone, Debug)]
+pub struct SignatureInput {
+    pub label: String,
+    pub components: Vec<String>,
+    pub params: HashMap<String, String>,
+    /// The serialized inner list and parameters, as covered by `@signature-params`
+    pub signature_params: String,
+}
+
+/// Splits a structured field on `separator`, ignoring separators inside quoted strings
+/// and inner lists
+fn split_structured_field(field: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut depth = 0;
+
+    for c in field.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            c if c == separator && !quoted && depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+
+    parts
+}
+
+/// Parses the first signature in an RFC 9421 `Signature-Input` header. Covered components
+/// that carry parameters (e.g. `"@query-param";name="q"`) are not supported.
+pub fn parse_signature_input(header: &str) -> Result<SignatureInput, VerificationError> {
+    let malformed = || VerificationError::SignatureError(anyhow!("malformed Signature-Input"));
+
+    let member = split_structured_field(header, ',')
+        .into_iter()
+        .next()
+        .ok_or_else(malformed)?;
+    let (label, signature_params) = member.split_once('=').ok_or_else(malformed)?;
+    let (inner_list, params) = signature_params
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .ok_or_else(malformed)?;
+
+    let components = inner_list
+        .split_whitespace()
+        .map(|component| {
+            component
+                .strip_prefix('"')
+                .and_then(|component| component.strip_suffix('"'))
+                .filter(|component| !component.contains('"'))
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    VerificationError::SignatureError(anyhow!(
+                        "unsupported covered component: {component}"
+                    ))
+                })
+        })
+        .collect::<Result<Vec<String>, VerificationError>>()?;
+
+    let params = split_structured_field(params, ';')
+        .into_iter()
+        .filter_map(|param| {
+            param
+                .split_once('=')
+                .map(|(key, value)| (key.to_string(), value.trim_matches('"').to_string()))
+        })
+        .collect();
+
+    Ok(SignatureInput {
+        label: label.trim().to_string(),
+        components,
+        params,
+        signature_params: signature_params.to_string(),
+    })
+}
+
+/// Builds the RFC 9421 signature base for the first signature in `signature_input`,
+/// looking up the value of each covered component with `component`
+pub fn build_signature_base(
+    signature_input: &str,
+    component: impl Fn(&str) -> Option<String>,
+) -> Result<String, VerificationError> {
+    let input = parse_signature_input(signature_input)?;
+
+    let mut lines = input
+        .components
+        .iter()
+        .map(|name| {
+            component(name)
+                .map(|value| format!("\"{name}\": {value}"))
+                .ok_or_else(|| {
+                    VerificationError::SignatureError(anyhow!("missing covered component: {name}"))
+                })
+        })
+        .collect::<Result<Vec<String>, VerificationError>>()?;
+
+    lines.push(format!("\"@signature-params\": {}", input.signature_params));
+
+    Ok(lines.join("\n"))
+}
+
+/// Converts an RFC 9530 `Content-Digest` header to the `SHA-256=<hash>` form used in the
+/// draft-cavage `Digest` header
+pub fn content_digest_to_digest(content_digest: &str) -> Option<String> {
+    split_structured_field(content_digest, ',')
+        .into_iter()
+        .find_map(|member| {
+            let (algorithm, value) = member.split_once('=')?;
+            algorithm
+                .trim()
+                .eq_ignore_ascii_case("sha-256")
+                .then(|| value.trim().trim_matches(':').to_string())
+        })
+        .map(|hash| format!("SHA-256={hash}"))
+}
+
+/// Returns the keyId of a request's signature, in either format
+pub fn get_key_id(signature: &str, signature_input: Option<&str>) -> Option<String> {
+    if let Some(signature_input) = signature_input {
+        parse_signature_input(signature_input)
+            .ok()
+            .and_then(|input| input.params.get("keyid").cloned())
+    } else {
+        ASSIGNMENT_RE
+            .captures_iter(signature)
+            .find(|cap| &cap[1] == "keyId")
+            .map(|cap| cap[2].to_string())
+    }
+}
+
+fn local_key_id(key_id: &str) -> (bool, Option<String>, Option<String>) {
+    LOCAL_USER_KEY_ID_RE
+        .captures(key_id)
+        .map(|captures| {
+            (
+                true,
+                Some(captures[2].to_string()),
+                Some(captures[3].to_string()),
+            )
+        })
+        .unwrap_or((false, None, None))
+}
+
+/// Whether the request carries a body that its signature must cover
+fn has_body(params: &VerifyMapParams) -> bool {
+    let method = params
+        .request_target
+        .split_whitespace()
+        .next()
+        .unwrap_or_default();
+
+    matches!(method, "post" | "put" | "patch")
+        || params
+            .content_length
+            .as_ref()
+            .and_then(|length| length.trim().parse::<u64>().ok())
+            .is_some_and(|length| length > 0)
+}
+
+/// Checks the algorithm, covered components and validity period of an RFC 9421 signature
+fn check_rfc9421_input(
+    input: &SignatureInput,
+    params: &VerifyMapParams,
+) -> Result<(), VerificationError> {
+    if let Some(alg) = input.params.get("alg") {
+        if alg != RFC9421_ALGORITHM {
+            return Err(VerificationError::SignatureError(anyhow!(
+                "unsupported signature algorithm: {alg}"
+            )));
+        }
+    }
+
+    // The method and target must be signed so a signature can't be replayed against
+    // another endpoint, and a body must be bound to the signature through its digest
+    let mut required = vec!["@method", "@target-uri"];
+
+    if has_body(params) {
+        required.push("content-digest");
+    }
+
+    if let Some(missing) = required
+        .into_iter()
+        .find(|component| !input.components.iter().any(|c| c == component))
+    {
+        return Err(VerificationError::SignatureError(anyhow!(
+            "required component not covered: {missing}"
+        )));
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default();
+
+    let created = input
+        .params
+        .get("created")
+        .ok_or_else(|| VerificationError::SignatureError(anyhow!("no created time")))?
+        .parse::<u64>()
+        .map_err(|e| VerificationError::SignatureError(anyhow!(e)))?;
+
+    if created + RFC9421_MAX_AGE_SECS < now || created > now + RFC9421_CLOCK_SKEW_SECS {
+        return Err(VerificationError::VerificationFailed(anyhow!(
+            "signature created outside the accepted window"
+        )));
+    }
+
+    if let Some(expires) = input.params.get("expires") {
+        let expires = expires
+            .parse::<u64>()
+            .map_err(|e| VerificationError::SignatureError(anyhow!(e)))?;
+
+        if expires < now {
+            return Err(VerificationError::VerificationFailed(anyhow!(
+                "signature expired"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn build_rfc9421_verify_params(
+    params: VerifyMapParams,
+    signature_input: &str,
+) -> Result<VerifyParams, VerificationError> {
+    let input = parse_signature_input(signature_input)?;
+
+    check_rfc9421_input(&input, &params)?;
+
+    let key_id = input.params.get("keyid").cloned().ok_or_else(|| {
+        log::error!("No keyid found in Signature-Input");
+        VerificationError::NoKeyId
+    })?;
+
+    let signature = split_structured_field(&params.signature, ',')
+        .into_iter()
+        .find_map(|member| {
+            let (label, value) = member.split_once('=')?;
+            (label.trim() == input.label).then(|| value.trim().trim_matches(':').to_string())
+        })
+        .ok_or_else(|| {
+            VerificationError::SignatureError(anyhow!("no signature labelled {}", input.label))
+        })?;
+
+    let verify_string = params
+        .signature_base
+        .ok_or_else(|| VerificationError::SignatureError(anyhow!("signature base not provided")))?;
+
+    let (local, signer_username, key_selector) = local_key_id(&key_id);
+
+    Ok(VerifyParams {
+        verify_string,
+        signature,
+        key_id,
+        key_selector,
+        local,
+        signer_username,
+    })
+}
+
 pub fn build_verify_string(params: VerifyMapParams) -> Result<VerifyParams, VerificationError> {
+    if let Some(signature_input) = params.signature_input.clone() {
+        return build_rfc9421_verify_params(params, &signature_input);
+    }
+
     let mut signature_map = HashMap::<String, String>::new();
 
     for cap in ASSIGNMENT_RE.captures_iter(&params.signature) {
@@ -100,15 +403,7 @@ pub fn build_verify_string(params: VerifyMapParams) -> Result<VerifyParams, Veri
         })?
         .clone();
 
-    let mut local = false;
-    let mut signer_username = Option::<String>::None;
-    let mut key_selector = Option::<String>::None;
-
-    if let Some(captures) = LOCAL_USER_KEY_ID_RE.captures(&key_id) {
-        local = true;
-        signer_username = Some(captures[2].to_string());
-        key_selector = Some(captures[3].to_string());
-    }
+    let (local, signer_username, key_selector) = local_key_id(&key_id);
 
     let headers = signature_map
         .get("headers")
@@ -239,7 +534,7 @@ pub async fn verify(
         verify_signature_crypto(&public_key_pem, &signature_str, &verify_string)?;
 
         Ok(VerificationType::Local((Box::from(profile), params.digest)))
-    } else if let Ok(actor) = get_actor_by_key_id(conn, key_id).await {
+    } else if let Ok(actor) = get_actor_by_key_id(conn, key_id.clone()).await {
         let ap_actor = ApActor::from(actor.clone());
         let public_key_pem = ap_actor.clone().public_key.public_key_pem;
 
@@ -249,6 +544,12 @@ pub async fn verify(
             Box::new(ap_actor),
             params.digest,
         )))
+    } else if let Ok((actor, public_key_pem)) = resolve_public_key(conn, key_id).await {
+        verify_signature_crypto(&public_key_pem, &signature_str, &verify_string)?;
+        Ok(VerificationType::Remote((
+            Box::new(ApActor::from(actor)),
+            params.digest,
+        )))
     } else {
         Err(VerificationError::ActorNotFound(params.into()))
     }
@@ -293,16 +594,18 @@ pub struct SignParams {
     pub url: Url,
     pub body: Option<String>,
     pub method: Method,
+    pub scheme: SignatureScheme,
 }
 
 impl std::fmt::Display for SignParams {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Sign[{} {} by {}{}]",
+            "Sign[{} {} by {} with {}{}]",
             self.method,
             self.url,
             self.profile.as_id,
+            self.scheme,
             self.body
                 .as_ref()
                 .map(|b| format!(" with body ({} bytes)", b.len()))
@@ -315,9 +618,107 @@ pub struct SignResponse {
     pub signature: String,
     pub date: String,
     pub digest: Option<String>,
+    pub signature_input: Option<String>,
+    pub content_digest: Option<String>,
+}
+
+impl SignResponse {
+    /// The headers to attach to the signed request
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("Date", self.date.clone()),
+            ("Signature", self.signature.clone()),
+        ];
+
+        if let Some(digest) = &self.digest {
+            headers.push(("Digest", digest.clone()));
+        }
+
+        if let Some(signature_input) = &self.signature_input {
+            headers.push(("Signature-Input", signature_input.clone()));
+        }
+
+        if let Some(content_digest) = &self.content_digest {
+            headers.push(("Content-Digest", content_digest.clone()));
+        }
+
+        headers
+    }
 }
 
 pub fn sign(params: SignParams) -> Result<SignResponse, SigningError> {
+    match params.scheme {
+        SignatureScheme::Rfc9421 => sign_rfc9421(params),
+        SignatureScheme::Cavage => sign_cavage(params),
+    }
+}
+
+fn sign_rfc9421(params: SignParams) -> Result<SignResponse, SigningError> {
+    if params.url.host().is_none() {
+        return Err(SigningError::InvalidUrl);
+    }
+
+    log::debug!("{params}");
+
+    let date = httpdate::fmt_http_date(SystemTime::now());
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default();
+    let content_digest = params
+        .body
+        .as_ref()
+        .map(|body| format!("sha-256=:{}:", get_hash(body.as_bytes().to_vec())));
+
+    let mut components = vec![
+        ("@method", params.method.to_string().to_uppercase()),
+        ("@target-uri", params.url.to_string()),
+    ];
+
+    if let Some(content_digest) = &content_digest {
+        components.push(("content-digest", content_digest.clone()));
+    }
+
+    let actor = ApActor::from(params.profile.clone());
+    let signature_params = format!(
+        "({});created={created};keyid=\"{}\";alg=\"{RFC9421_ALGORITHM}\"",
+        components
+            .iter()
+            .map(|(name, _)| format!("\"{name}\""))
+            .collect::<Vec<String>>()
+            .join(" "),
+        actor.public_key.id
+    );
+
+    let mut signature_base = components
+        .iter()
+        .map(|(name, value)| format!("\"{name}\": {value}"))
+        .collect::<Vec<String>>();
+    signature_base.push(format!("\"@signature-params\": {signature_params}"));
+
+    let private_key = RsaPrivateKey::from_pkcs8_pem(
+        &params
+            .profile
+            .ek_private_key
+            .ok_or(SigningError::NoPrivateKey)?,
+    )
+    .map_err(|_| SigningError::NoPrivateKey)?;
+    let signing_key = SigningKey::<Sha256>::new(private_key);
+    let signature = compute_signature(&signing_key, &signature_base.join("\n"));
+
+    Ok(SignResponse {
+        signature: format!(
+            "{RFC9421_LABEL}=:{}:",
+            general_purpose::STANDARD.encode(signature.to_bytes())
+        ),
+        date,
+        digest: None,
+        signature_input: Some(format!("{RFC9421_LABEL}={signature_params}")),
+        content_digest,
+    })
+}
+
+fn sign_cavage(params: SignParams) -> Result<SignResponse, SigningError> {
     let digest = compute_digest(&params.body);
     if let Some(host) = params.url.host() {
         let request_target = format_request_target(&params.method, &params.url);
@@ -343,12 +744,82 @@ pub fn sign(params: SignParams) -> Result<SignResponse, SigningError> {
             signature: response_signature,
             date,
             digest,
+            signature_input: None,
+            content_digest: None,
         })
     } else {
         Err(SigningError::InvalidUrl)
     }
 }
 
+/// The signature schemes to try, in order, when signing a request to `url`. Instances are
+/// tried with RFC 9421 first, falling back to draft-cavage, unless they are known to
+/// accept only draft-cavage.
+pub async fn signature_schemes<C: DbRunner>(conn: &C, url: &str) -> Vec<SignatureScheme> {
+    let known = match url_domain(url) {
+        Some(domain) => get_instance_by_domain_name(conn, domain)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|instance| instance.signature_scheme),
+        None => None,
+    };
+
+    schemes_for(known)
+}
+
+/// Like `signature_schemes`, for many URLs at once with a single lookup. The schemes are
+/// keyed by URL.
+pub async fn signature_schemes_by_url<C: DbRunner>(
+    conn: &C,
+    urls: &[String],
+) -> HashMap<String, Vec<SignatureScheme>> {
+    let domains = urls.iter().filter_map(|url| url_domain(url)).collect();
+    let known: HashMap<String, SignatureScheme> = get_signature_schemes_by_domain(conn, domains)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to retrieve signature schemes: {e}");
+            vec![]
+        })
+        .into_iter()
+        .collect();
+
+    urls.iter()
+        .map(|url| {
+            let scheme = url_domain(url).and_then(|domain| known.get(&domain).copied());
+            (url.clone(), schemes_for(scheme))
+        })
+        .collect()
+}
+
+fn schemes_for(known: Option<SignatureScheme>) -> Vec<SignatureScheme> {
+    match known {
+        Some(SignatureScheme::Cavage) => vec![SignatureScheme::Cavage],
+        _ => vec![SignatureScheme::Rfc9421, SignatureScheme::Cavage],
+    }
+}
+
+/// Whether a response status may indicate that the recipient could not verify the
+/// signature, in which case the request is retried with the next scheme
+pub fn signature_rejected(status: u16) -> bool {
+    matches!(status, 400 | 401 | 403)
+}
+
+/// Remembers the signature scheme accepted by the instance serving `url`
+pub async fn record_signature_scheme<C: DbRunner>(conn: &C, url: &str, scheme: SignatureScheme) {
+    if let Some(domain) = url_domain(url) {
+        if let Err(e) = record_instance_signature_scheme(conn, domain.clone(), scheme).await {
+            log::error!("Failed to record signature scheme for {domain}: {e}");
+        }
+    }
+}
+
+fn url_domain(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
 pub fn get_hash(bytes: Vec<u8>) -> String {
     let mut hasher = Sha256::new();
     hasher.update(bytes);
@@ -498,3 +969,112 @@ pub enum SignatureError {
 
 //     create_or_update_instance(conn, (domain_name, shared_inbox).into()).await
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    /// Checks a signature over `components` created at `created` on a request to `target`
+    fn check(target: &str, components: &str, created: Option<u64>) -> bool {
+        let created = created
+            .map(|created| format!(";created={created}"))
+            .unwrap_or_default();
+        let input = parse_signature_input(&format!("sig1=({components}){created}")).unwrap();
+        let params = VerifyMapParams {
+            signature: String::new(),
+            request_target: target.to_string(),
+            host: "example.com".to_string(),
+            date: String::new(),
+            digest: None,
+            content_type: None,
+            content_length: None,
+            user_agent: None,
+            signature_input: None,
+            signature_base: None,
+        };
+
+        check_rfc9421_input(&input, &params).is_ok()
+    }
+
+    #[test]
+    fn split_structured_field_ignores_quoted_and_nested_separators() {
+        assert_eq!(
+            split_structured_field(r#"a=("b" "c");d="e,f", g=1"#, ','),
+            vec![r#"a=("b" "c");d="e,f""#, "g=1"]
+        );
+        assert_eq!(
+            split_structured_field(r#"created=1;keyid="a;b";alg="x""#, ';'),
+            vec!["created=1", r#"keyid="a;b""#, r#"alg="x""#]
+        );
+        assert!(split_structured_field("  ", ',').is_empty());
+    }
+
+    #[test]
+    fn parse_signature_input_reads_components_and_params() {
+        let input = parse_signature_input(
+            r#"sig1=("@method" "@target-uri" "content-digest");created=1700000000;keyid="https://remote.example/actor#main-key";alg="rsa-v1_5-sha256""#,
+        )
+        .unwrap();
+
+        assert_eq!(input.label, "sig1");
+        assert_eq!(
+            input.components,
+            vec!["@method", "@target-uri", "content-digest"]
+        );
+        assert_eq!(input.params["created"], "1700000000");
+        assert_eq!(
+            input.params["keyid"],
+            "https://remote.example/actor#main-key"
+        );
+        assert!(input
+            .signature_params
+            .starts_with(r#"("@method" "@target-uri" "content-digest");created="#));
+    }
+
+    #[test]
+    fn parse_signature_input_rejects_unsupported_input() {
+        assert!(parse_signature_input("sig1").is_err());
+        assert!(parse_signature_input(r#"sig1="@method";created=1"#).is_err());
+        assert!(parse_signature_input(r#"sig1=("@query-param";name="q");created=1"#).is_err());
+    }
+
+    #[test]
+    fn rfc9421_requires_method_target_and_body_digest() {
+        let signed = r#""@method" "@target-uri""#;
+        let signed_body = r#""@method" "@target-uri" "content-digest""#;
+
+        assert!(check("post /inbox", signed_body, Some(now())));
+        assert!(check("get /user/alice", signed, Some(now())));
+        assert!(!check("post /inbox", signed, Some(now())));
+        assert!(!check(
+            "post /inbox",
+            r#""@method" "content-digest""#,
+            Some(now())
+        ));
+        assert!(!check("get /user/alice", r#""@target-uri""#, Some(now())));
+    }
+
+    #[test]
+    fn rfc9421_rejects_stale_or_missing_created() {
+        let signed = r#""@method" "@target-uri""#;
+
+        assert!(!check(
+            "get /user/alice",
+            signed,
+            Some(now() - RFC9421_MAX_AGE_SECS - 60)
+        ));
+        assert!(!check(
+            "get /user/alice",
+            signed,
+            Some(now() + RFC9421_CLOCK_SKEW_SECS + 60)
+        ));
+        assert!(!check("get /user/alice", signed, None));
+    }
+}
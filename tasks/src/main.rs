@@ -165,7 +165,42 @@ impl Task for DatabaseCleanupTask {
     fn execute(&self) -> TaskResult {
         Box::pin(async move {
             log::info!("Running database cleanup...");
-            // TODO: Implement actual database cleanup logic
+
+            let conn = match enigmatick::db::POOL.get().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log::error!("Failed to get database connection for database cleanup: {e}");
+                    return Err(e.into());
+                }
+            };
+
+            // Finished queue items are only retained briefly; dead-lettered inbound items are kept
+            let cutoff = Utc::now() - chrono::Duration::days(7);
+            match enigmatick::models::processing_queue::prune_completed_queued_activities(
+                &conn, cutoff,
+            )
+            .await
+            {
+                Ok(deleted_count) => {
+                    log::info!("Removed {deleted_count} completed inbox queue items");
+                }
+                Err(e) => {
+                    log::error!("Error pruning inbox queue: {e}");
+                    return Err(e.into());
+                }
+            }
+
+            match enigmatick::models::delivery_jobs::prune_delivery_jobs(&conn, cutoff).await {
+                Ok(deleted_count) => {
+                    log::info!("Removed {deleted_count} finished delivery jobs");
+                }
+                Err(e) => {
+                    log::error!("Error pruning delivery jobs: {e}");
+                    return Err(e.into());
+                }
+            }
+
+            // TODO: Implement remaining database cleanup logic
             // - Remove old unprocessable entries
             // - Clean up orphaned media files
             // - Vacuum database tables
@@ -174,7 +209,7 @@ impl Task for DatabaseCleanupTask {
     }
 }
 
-/// Example task: Federation health check
+/// Federation health check task: Probe unreachable instances and revive those that answer
 pub struct FederationHealthCheckTask;
 
 impl Task for FederationHealthCheckTask {
@@ -188,17 +223,53 @@ impl Task for FederationHealthCheckTask {
 
     fn execute(&self) -> TaskResult {
         Box::pin(async move {
-            log::info!("Checking federation health...");
-            // TODO: Implement federation health checks
-            // - Check connectivity to known instances
-            // - Update instance status
-            // - Retry failed deliveries
-            Ok(())
+            log::debug!("Checking federation health...");
+
+            let pool = enigmatick::db::POOL.clone();
+
+            match enigmatick::runner::health::probe_unreachable_instances_task(pool, None, vec![])
+                .await
+            {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    log::error!("Federation health check failed: {e:?}");
+                    Err(format!("Federation health check failed: {e:?}").into())
+                }
+            }
+        })
+    }
+}
+
+/// NodeInfo crawl task: Record the software and size of known instances
+pub struct NodeInfoCrawlTask;
+
+impl Task for NodeInfoCrawlTask {
+    fn name(&self) -> &'static str {
+        "nodeinfo_crawl"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(3600) // Run every hour; each instance is crawled once a day
+    }
+
+    fn execute(&self) -> TaskResult {
+        Box::pin(async move {
+            log::debug!("Crawling instance NodeInfo...");
+
+            let pool = enigmatick::db::POOL.clone();
+
+            match enigmatick::runner::nodeinfo::crawl_nodeinfo_task(pool, None, vec![]).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    log::error!("NodeInfo crawl failed: {e:?}");
+                    Err(format!("NodeInfo crawl failed: {e:?}").into())
+                }
+            }
         })
     }
 }
 
-/// Example task: Activity delivery retry
+/// Activity delivery retry task: Redeliver activities whose earlier attempts failed
 pub struct ActivityDeliveryRetryTask;
 
 impl Task for ActivityDeliveryRetryTask {
@@ -207,17 +278,57 @@ impl Task for ActivityDeliveryRetryTask {
     }
 
     fn interval(&self) -> Duration {
-        Duration::from_secs(600) // Run every 10 minutes
+        Duration::from_secs(60) // Run every minute; backoff is tracked per delivery job
     }
 
     fn execute(&self) -> TaskResult {
         Box::pin(async move {
-            log::info!("Retrying failed activity deliveries...");
-            // TODO: Implement delivery retry logic
-            // - Find failed deliveries in database
-            // - Attempt redelivery with exponential backoff
-            // - Mark permanently failed after max attempts
-            Ok(())
+            log::debug!("Retrying failed activity deliveries...");
+
+            let pool = enigmatick::db::POOL.clone();
+
+            match enigmatick::runner::delivery::retry_deliveries_task(pool, None, vec![]).await {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    log::error!("Activity delivery retry failed: {e:?}");
+                    Err(format!("Activity delivery retry failed: {e:?}").into())
+                }
+            }
+        })
+    }
+}
+
+/// Domain blocklist import task: Re-import the blocklist at DOMAIN_BLOCKLIST_PATH
+pub struct DomainBlocklistImportTask;
+
+impl Task for DomainBlocklistImportTask {
+    fn name(&self) -> &'static str {
+        "domain_blocklist_import"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(3600) // Run every hour
+    }
+
+    fn execute(&self) -> TaskResult {
+        Box::pin(async move {
+            log::debug!("Importing domain blocklist...");
+
+            let pool = enigmatick::db::POOL.clone();
+
+            match enigmatick::runner::domain_blocks::import_domain_blocklist_task(
+                pool,
+                None,
+                vec![],
+            )
+            .await
+            {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    log::error!("Domain blocklist import failed: {e:?}");
+                    Err(format!("Domain blocklist import failed: {e:?}").into())
+                }
+            }
         })
     }
 }
@@ -319,6 +430,12 @@ async fn main() {
     scheduler
         .register_task(Box::new(ActivityDeliveryRetryTask))
         .await;
+    scheduler.register_task(Box::new(NodeInfoCrawlTask)).await;
+    if enigmatick::DOMAIN_BLOCKLIST_PATH.is_some() {
+        scheduler
+            .register_task(Box::new(DomainBlocklistImportTask))
+            .await;
+    }
     scheduler.register_task(Box::new(CacheCleanupTask)).await;
     scheduler.register_task(Box::new(SearchIndexTask)).await;
     log::info!("All tasks registered successfully");